@@ -56,6 +56,12 @@
 //!
 //! Grammars can also be inlined by using the `#[grammar_inline = "..."]` attribute.
 //!
+//! ## BNF grammars
+//!
+//! Grammars in BNF are read with the `#[grammar_bnf = "path/to/my_grammar.bnf"]` attribute
+//! and parsed to the same rules as the equivalent `.pest` grammar. See `pest_meta::bnf` for
+//! the syntax.
+//!
 //! ## Grammar
 //!
 //! A grammar is a series of rules separated by whitespace, possibly containing comments.
@@ -177,6 +183,10 @@
 //! | `e{, n}`              | matches `e` at most `n` times                              |
 //! | `e{n,}`               | matches `e` at least `n` times                             |
 //! | `e{m, n}`             | matches `e` between `m` and `n` times inclusively          |
+//! | `e1 % e2`             | matches `e1` one or more times, separated by `e2`          |
+//! | `e1 %* e2`            | matches `e1` zero or more times, separated by `e2`         |
+//! | `e1 %... e2`          | matches `e1 % e2`, optionally followed by `e2`             |
+//! | `e1 %...* e2`         | matches `e1 %* e2`, optionally followed by `e2`            |
 //! | `e?`                  | optionally matches `e`                                     |
 //! | `&e`                  | matches `e` without making progress                        |
 //! | `!e`                  | matches if `e` doesn't match without making progress       |
@@ -184,6 +194,10 @@
 //!
 //! where `e`, `e1`, and `e2` are expressions.
 //!
+//! The separator operators bind tighter than `~` and `|`, so
+//! `"(" ~ e % "," ~ ")"` matches a list of `e` separated by commas in
+//! parentheses.
+//!
 //! Matching is greedy, without backtracking.  Note the difference in behavior for
 //! these two rules in matching identifiers that don't end in an underscore:
 //!
@@ -201,7 +215,7 @@
 //! if `e1` in the compound expression `e1 | e2` does not match the input, then
 //! it does not modify the stack, so `e2` sees the stack in the same state as
 //! `e1` did. Repetitions and optionals (`e*`, `e+`, `e{, n}`, `e{n,}`,
-//! `e{m,n}`, `e?`) can modify the stack each time `e` matches. The `!e` and `&e`
+//! `e{m,n}`, `e?`, `e % s`) can modify the stack each time `e` matches. The `!e` and `&e`
 //! expressions are a special case; they never modify the stack.
 //! Many languages have "keyword" tokens (e.g. if, for, while) as well as general
 //! tokens (e.g. identifier) that matches any word. In order to match a keyword,
@@ -299,6 +313,30 @@
 //! All rules defined or used in the grammar populate a generated `enum` called `Rule`. This
 //! implements `pest`'s `RuleType` and can be used throughout the API.
 //!
+//! ## Syntax trees
+//!
+//! The `#[syntax_tree = "syntax"]` attribute also generates a module `syntax` with typed
+//! nodes over the pairs of the parse, in the style of rust-analyzer's AST:
+//!
+//! * every rule that produces pairs gets a struct, e.g. `syntax::FnDecl` for `fn_decl`
+//! * every silent rule that is a choice between such rules, like `item = _{ fn_decl | struct_decl }`,
+//!   gets an enum `syntax::Item` with a variant for each
+//! * other silent rules are inlined into the rules that use them
+//!
+//! Nodes implement `pest::syntax::AstNode`, which casts a `Pair` into them. Each has an accessor
+//! for the rules it contains: `fn_decl()` returns an `Option` of the first one and `fn_decls()`
+//! iterates over all of them if it may contain more than one. Each node tag `#name = e` gets an
+//! accessor `name()` as well.
+//!
+//! ```ignore
+//! let pair = MyParser::parse(Rule::file, input)?.next().unwrap();
+//! for item in syntax::File::cast(pair).unwrap().items() {
+//!     if let syntax::Item::FnDecl(fn_decl) = item {
+//!         println!("{}", fn_decl.ident().unwrap().text());
+//!     }
+//! }
+//! ```
+//!
 //! ## `Built-in rules`
 //!
 //! Pest also comes with a number of built-in rules for convenience. They are:
@@ -319,7 +357,7 @@ use proc_macro::TokenStream;
 
 /// The main method that's called by the proc macro
 /// (a wrapper around `pest_generator::derive_parser`)
-#[proc_macro_derive(Parser, attributes(grammar, grammar_inline))]
+#[proc_macro_derive(Parser, attributes(grammar, grammar_inline, grammar_bnf, syntax_tree))]
 pub fn derive_parser(input: TokenStream) -> TokenStream {
     pest_generator::derive_parser(input.into(), true).into()
 }
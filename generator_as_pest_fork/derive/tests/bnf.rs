@@ -0,0 +1,62 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+use alloc::{format, vec::Vec};
+
+#[macro_use]
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+
+use pest::Parser;
+
+#[derive(Parser)]
+#[grammar_bnf = "tests/bnf.bnf"]
+struct BnfParser;
+
+#[test]
+fn separated() {
+    parses_to! {
+        parser: BnfParser,
+        input: "1,ab,(0x1f,)",
+        rule: Rule::start,
+        tokens: [
+            start(0, 12, [
+                list(0, 12, [
+                    item(0, 1, [number(0, 1)]),
+                    item(2, 4, [word(2, 4)]),
+                    item(5, 12, [
+                        list(6, 11, [
+                            item(6, 10, [number(6, 10)])
+                        ])
+                    ])
+                ]),
+                EOI(12, 12)
+            ])
+        ]
+    };
+}
+
+#[test]
+fn empty_separated() {
+    parses_to! {
+        parser: BnfParser,
+        input: "()",
+        rule: Rule::item,
+        tokens: [
+            item(0, 2, [list(1, 1)])
+        ]
+    };
+}
+
+#[test]
+fn missing_element() {
+    assert!(BnfParser::parse(Rule::start, "1,,2").is_err());
+}
@@ -0,0 +1,306 @@
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+
+use pest::generation::{DerivationTree, GenerationError};
+use pest::iterators::Pairs;
+use pest::Parser;
+
+#[derive(Parser)]
+#[grammar_inline = "
+three = { \"abc\" | \"d\" | \"ef\" }
+unweighted = { \"x\" | \"y\" }
+weighted = { W:9 ~ \"x\" | \"y\" }
+positive_atomic = @{ (\"a\" | \"b\") ~ &\"b\" ~ (\"a\" | \"b\") }
+positive = { \"<\" ~ \"a\" ~ &(\",\" | \">\") ~ (\"a\" | \",\" | \">\") }
+keyword = @{ (\"fn\" | \"let\") ~ !ASCII_ALPHANUMERIC }
+identifier = @{ !keyword ~ (\"f\" | \"n\" | \"l\" | \"e\" | \"t\")+ }
+insensitive = @{ ^\"let-1\" }
+raw_string = @{ \"r\" ~ PUSH(\"#\"*) ~ \"\\\"\" ~ (\"a\" | \"b\")* ~ \"\\\"\" ~ POP }
+stack = { PUSH(\"a\" | \"b\") ~ PUSH(\"c\" | \"d\") ~ PEEK[..] ~ PEEK_ALL ~ DROP ~ POP }
+skip_until = @{ (!\"xy\" ~ ANY)* ~ \"xy\" }
+classes = @{ ASCII_ALPHANUMERIC ~ ASCII_HEX_DIGIT ~ ASCII_ALPHA_UPPER ~ ASCII_NONZERO_DIGIT ~ NEWLINE ~ XID_START ~ LETTER ~ HAN }
+any = @{ ANY ~ ANY ~ ANY ~ ANY }
+negative_sequence = { !(\"a\" ~ \"b\") ~ (\"a\" | \"b\") ~ (\"a\" | \"b\") }
+document = { SOI ~ pair ~ EOI }
+pair = { key ~ \"=\" ~ value }
+key = @{ (\"a\" | \"b\")+ }
+value = _{ number | list }
+number = @{ ASCII_DIGIT+ }
+list = { \"[\" ~ (value ~ (\",\" ~ value)*)? ~ \"]\" }
+endless = { \"a\" ~ endless | \"b\" }
+nested = { \"(\" ~ nested? ~ \")\" }
+unsupported = { !nested ~ \"a\" }
+separated = @{ \"[\" ~ (\"a\" | \"b\") %...* \",\" ~ \"]\" }
+arguments = { \"(\" ~ \"x\" % \",\" ~ \")\" }
+WHITESPACE = _{ \" \" }
+"]
+struct GenerationParser;
+
+#[test]
+fn choice_without_weights() {
+    assert_eq!(GenerationParser::generate("unweighted", &[100], None).map(|d| d.string), Ok("x".to_string()));
+    assert_eq!(GenerationParser::generate("unweighted", &[200], None).map(|d| d.string), Ok("y".to_string()));
+}
+
+#[test]
+fn choice_of_three() {
+    let mut generated = std::collections::HashSet::new();
+    for first in (0..=255).step_by(15) {
+        for second in (0..=255).step_by(15) {
+            generated.insert(GenerationParser::generate("three", &[first, second], None).unwrap().string);
+        }
+    }
+    assert_eq!(generated.len(), 3, "{:?}", generated);
+}
+
+#[test]
+fn choice_with_weights() {
+    assert_eq!(GenerationParser::generate("weighted", &[100], None).map(|d| d.string), Ok("x".to_string()));
+    assert_eq!(GenerationParser::generate("weighted", &[200], None).map(|d| d.string), Ok("x".to_string()));
+    assert_eq!(GenerationParser::generate("weighted", &[250], None).map(|d| d.string), Ok("y".to_string()));
+}
+
+#[test]
+fn positive_lookahead_atomic() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("positive_atomic", &[byte, byte], None).unwrap().string;
+        assert!(generated == "ab" || generated == "bb", "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::positive_atomic, &generated).is_ok());
+    }
+}
+
+#[test]
+fn positive_lookahead_skips_whitespace() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("positive", &[byte, byte], None).unwrap().string;
+        assert!(generated.ends_with(',') || generated.ends_with('>'), "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::positive, &generated).is_ok());
+    }
+}
+
+#[test]
+fn insensitive_string() {
+    let mut seen = std::collections::HashSet::new();
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("insensitive", &driver(byte), None).unwrap().string;
+        assert!(generated.eq_ignore_ascii_case("let-1"), "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::insensitive, &generated).is_ok());
+        seen.insert(generated);
+    }
+    assert!(seen.contains("let-1"));
+    assert!(seen.contains("LET-1"));
+    assert!(seen.len() > 2);
+}
+
+fn driver(byte: u8) -> Vec<u8> {
+    (0..16u8).map(|i| byte.wrapping_mul(i.wrapping_add(3))).collect()
+}
+
+#[test]
+fn negative_lookahead_rule() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("identifier", &driver(byte), None).unwrap().string;
+        assert!(generated != "fn" && generated != "let", "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::identifier, &generated).is_ok());
+    }
+}
+
+#[test]
+fn negative_lookahead_sequence() {
+    let mut rejections = 0;
+    for byte in 0..=255 {
+        let derivation = GenerationParser::generate("negative_sequence", &driver(byte), None).unwrap();
+        let generated = derivation.string;
+        let stripped: String = generated.chars().filter(|&ch| ch != ' ').collect();
+        assert!(stripped != "ab", "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::negative_sequence, &generated).is_ok());
+        rejections += derivation.rejections;
+    }
+    // Some drivers first derive the forbidden `a b`, which the lookahead rejects.
+    assert!(rejections > 0);
+}
+
+#[test]
+fn push_pop() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("raw_string", &driver(byte), None).unwrap().string;
+        let hashes = generated.chars().skip(1).take_while(|&ch| ch == '#').count();
+        assert!(generated.ends_with(&"#".repeat(hashes)), "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::raw_string, &generated).is_ok());
+    }
+}
+
+#[test]
+fn peek_slice_and_drop() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("stack", &driver(byte), None).unwrap().string;
+        assert!(GenerationParser::parse(Rule::stack, &generated).is_ok(), "{:?}", generated);
+    }
+}
+
+#[test]
+fn skip_until() {
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("skip_until", &driver(byte), None).unwrap().string;
+        assert_eq!(generated.find("xy"), Some(generated.len() - 2), "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::skip_until, &generated).is_ok());
+    }
+}
+
+#[test]
+fn builtin_classes() {
+    let mut alphanumeric = std::collections::HashSet::new();
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("classes", &driver(byte), None).unwrap().string;
+        assert!(GenerationParser::parse(Rule::classes, &generated).is_ok(), "{:?}", generated);
+        alphanumeric.insert(generated.chars().next().unwrap());
+    }
+    assert!(alphanumeric.iter().any(|ch| ch.is_ascii_digit()));
+    assert!(alphanumeric.iter().any(|ch| ch.is_ascii_lowercase()));
+    assert!(alphanumeric.iter().any(|ch| ch.is_ascii_uppercase()));
+}
+
+#[test]
+fn any_unicode() {
+    let mut non_ascii = 0;
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("any", &driver(byte), None).unwrap().string;
+        assert_eq!(generated.chars().count(), 4, "{:?}", generated);
+        assert!(GenerationParser::parse(Rule::any, &generated).is_ok());
+        non_ascii += generated.chars().filter(|ch| !ch.is_ascii()).count();
+    }
+    assert!(non_ascii > 0);
+}
+
+fn trees(pairs: Pairs<'_, Rule>) -> Vec<DerivationTree<Rule>> {
+    pairs
+        .map(|pair| DerivationTree {
+            rule: pair.as_rule(),
+            start: pair.as_span().start(),
+            end: pair.as_span().end(),
+            children: trees(pair.into_inner()),
+        })
+        .collect()
+}
+
+#[test]
+fn derivation_matches_pairs() {
+    for byte in 0..=255 {
+        let derivation = GenerationParser::generate("document", &driver(byte), Some(1_000)).unwrap();
+        let pairs = GenerationParser::parse(Rule::document, &derivation.string).unwrap();
+        assert_eq!(derivation.trees, trees(pairs), "{:?}", derivation.string);
+    }
+}
+
+#[test]
+fn derivation_rules_at() {
+    let derivation = GenerationParser::generate("document", &[100, 200, 0, 250, 0, 0], None).unwrap();
+    let number = derivation.string.find(|ch: char| ch.is_ascii_digit()).unwrap();
+    let rules = derivation.rules_at(number);
+    assert_eq!(rules[..2], [Rule::document, Rule::pair]);
+    assert_eq!(rules.last(), Some(&Rule::number));
+}
+
+#[test]
+fn generation_errors() {
+    assert_eq!(
+        GenerationParser::generate("nope", &[], None),
+        Err(GenerationError::UnknownStartRule("nope".to_owned()))
+    );
+    assert_eq!(
+        GenerationParser::generate("endless", &[0; 64], Some(10)),
+        Err(GenerationError::LimitExceeded)
+    );
+    assert_eq!(
+        GenerationParser::generate("endless", &[0; 4], Some(10)),
+        Err(GenerationError::DriverExhausted)
+    );
+    match GenerationParser::generate("unsupported", &[], None) {
+        Err(GenerationError::UnsupportedExpression { rule, .. }) => assert_eq!(rule, "unsupported"),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn literals() {
+    let literals = GenerationParser::literals();
+    for literal in &["fn", "let", "let-1", "xy", "=", "[", " "] {
+        assert!(literals.contains(literal), "{:?}", literals);
+    }
+    assert!(literals.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", literals);
+}
+
+#[test]
+fn alternatives() {
+    let names = GenerationParser::alternatives();
+    let mut seen = std::collections::HashSet::new();
+    for first in (0..=255).step_by(15) {
+        for second in (0..=255).step_by(15) {
+            let derivation = GenerationParser::generate("three", &[first, second], None).unwrap();
+            let picked: Vec<_> = derivation.alternatives.iter().map(|&idx| names[idx]).collect();
+            assert_eq!(picked, [("three", &format!("{:?}", derivation.string)[..])]);
+            seen.insert(derivation.string);
+        }
+    }
+    assert_eq!(seen.len(), 3, "{:?}", seen);
+    assert!(names.contains(&("weighted", "\"x\"")), "{:?}", names);
+}
+
+#[test]
+fn rule_names() {
+    let names = GenerationParser::rule_names();
+    assert!(names.contains(&"document") && names.contains(&"EOI"), "{:?}", names);
+    assert!(!names.contains(&"value") && !names.contains(&"WHITESPACE"), "{:?}", names);
+}
+
+#[test]
+fn guided() {
+    let strings = |guide: Option<&mut pest::generation::Guide>| {
+        let mut guide = guide;
+        (0..8u8)
+            .map(|byte| {
+                let derivation = match guide.as_deref_mut() {
+                    Some(guide) => GenerationParser::generate_guided("weighted", &[byte * 31], None, guide),
+                    None => GenerationParser::generate("weighted", &[byte * 31], None),
+                };
+                derivation.unwrap().string
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut guide = pest::generation::Guide::new();
+    let unguided = strings(None);
+    let guided = strings(Some(&mut guide));
+    assert!(!unguided.contains(&"y".to_string()), "{:?}", unguided);
+    assert!(guided.contains(&"y".to_string()), "{:?}", guided);
+    assert_eq!(guided[0], unguided[0]);
+    assert_eq!(guide.covered(), 2);
+}
+
+#[test]
+fn generate_all() {
+    // Each `~` of a non-atomic sequence derives the implicit WHITESPACE, here a single
+    // space, so the lookahead, which derives nothing, leaves two spaces in a row.
+    assert_eq!(GenerationParser::generate_all("positive", 12).unwrap(), ["< a  ,", "< a  >"]);
+    let strings = GenerationParser::generate_all("identifier", 2).unwrap();
+    assert_eq!(&strings[..6], ["e", "f", "l", "n", "t", "ee"]);
+    assert!(!strings.contains(&"fn".to_string()), "{:?}", strings);
+    assert!(GenerationParser::generate_all("stack", 12).unwrap().is_empty());
+}
+
+#[test]
+fn separated_lists() {
+    let strings = GenerationParser::generate_all("separated", 5).unwrap();
+    assert_eq!(strings, ["[]", "[a]", "[b]", "[a,]", "[b,]", "[a,a]", "[a,b]", "[b,a]", "[b,b]"]);
+    assert_eq!(GenerationParser::generate_all("arguments", 9).unwrap(), ["( x )", "( x , x )"]);
+    for byte in 0..=255 {
+        let generated = GenerationParser::generate("arguments", &driver(byte), None).unwrap().string;
+        assert!(GenerationParser::parse(Rule::arguments, &generated).is_ok(), "{:?}", generated);
+    }
+}
@@ -0,0 +1,96 @@
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+extern crate alloc;
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+
+use pest::syntax::AstNode;
+use pest::Parser;
+
+#[derive(Parser)]
+#[grammar_inline = "
+WHITESPACE = _{ \" \" }
+/// A whole program.
+program = { SOI ~ statement* ~ EOI }
+statement = _{ assignment | print }
+assignment = { \"let\" ~ ident ~ \"=\" ~ expr ~ \";\" }
+print = { \"print\" ~ expr % \",\" ~ \";\" }
+expr = { #lhs = atom ~ (operator ~ #rhs = atom)? }
+atom = _{ call | ident | number }
+call = { ident ~ \"(\" ~ arguments? ~ \")\" }
+arguments = _{ expr % \",\" }
+operator = @{ \"+\" | \"-\" }
+ident = @{ ASCII_ALPHA+ }
+number = @{ ASCII_DIGIT+ }
+"]
+#[syntax_tree = "syntax"]
+struct SyntaxParser;
+
+fn program(input: &str) -> syntax::Program<'_> {
+    let pair = SyntaxParser::parse(Rule::program, input)
+        .unwrap()
+        .next()
+        .unwrap();
+    syntax::Program::cast(pair).unwrap()
+}
+
+#[test]
+fn statements() {
+    let program = program("let a = 1 + b; print a, f(c, 2);");
+    let statements: Vec<_> = program.statements().collect();
+    assert_eq!(statements.len(), 2);
+
+    let assignment = match &statements[0] {
+        syntax::Statement::Assignment(assignment) => assignment,
+        statement => panic!("expected an assignment, found {:?}", statement),
+    };
+    assert_eq!(assignment.ident().unwrap().text(), "a");
+    let expr = assignment.expr().unwrap();
+    assert_eq!(expr.text(), "1 + b");
+    assert!(matches!(expr.lhs(), Some(syntax::Atom::Number(_))));
+    assert_eq!(expr.operator().unwrap().text(), "+");
+    assert_eq!(expr.rhs().unwrap().text(), "b");
+
+    let print = match &statements[1] {
+        syntax::Statement::Print(print) => print,
+        statement => panic!("expected a print, found {:?}", statement),
+    };
+    let exprs: Vec<_> = print.exprs().map(|expr| expr.text()).collect();
+    assert_eq!(exprs, ["a", "f(c, 2)"]);
+}
+
+#[test]
+fn inlined_rules() {
+    let program = program("print f(a, 1 - b);");
+    let print = match program.statements().next().unwrap() {
+        syntax::Statement::Print(print) => print,
+        statement => panic!("expected a print, found {:?}", statement),
+    };
+    let call = match print.exprs().next().unwrap().lhs().unwrap() {
+        syntax::Atom::Call(call) => call,
+        atom => panic!("expected a call, found {:?}", atom),
+    };
+    assert_eq!(call.ident().unwrap().text(), "f");
+    let arguments: Vec<_> = call.exprs().map(|expr| expr.text()).collect();
+    assert_eq!(arguments, ["a", "1 - b"]);
+    assert!(call.exprs().nth(1).unwrap().rhs().is_some());
+}
+
+#[test]
+fn cast() {
+    let pair = SyntaxParser::parse(Rule::ident, "abc")
+        .unwrap()
+        .next()
+        .unwrap();
+    assert!(syntax::Number::cast(pair.clone()).is_none());
+    assert!(syntax::Atom::can_cast(Rule::ident));
+    assert!(!syntax::Statement::can_cast(Rule::ident));
+    let atom = syntax::Atom::cast(pair.clone()).unwrap();
+    assert_eq!(atom.pair(), &pair);
+    assert_eq!(atom, syntax::Atom::Ident(syntax::Ident::cast(pair).unwrap()));
+}
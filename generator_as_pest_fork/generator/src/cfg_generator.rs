@@ -7,7 +7,10 @@ use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ops::{Range, RangeInclusive};
 
+use cfg::history::LinkedHistoryNode;
 use cfg::prelude::*;
+use cfg::sequence::Separator;
+use pest::generation::{pick_any, pick_char, Alternative, Derivation, Driver, Generation, GenerationError, Guide, NegativeRule, Pattern, PositiveRule, StackOp, StackRule, UnsupportedRule};
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 enum SymbolKind {
@@ -15,6 +18,10 @@ enum SymbolKind {
     Null,
     Single(char),
     Range(char, char),
+    /// One of the ranges in `Generator::classes`.
+    Class(usize),
+    /// Any character, for `ANY`.
+    Any,
 }
 
 #[derive(Clone, Copy)]
@@ -24,14 +31,55 @@ struct SymbolWithKind {
 }
 struct NegativeRuleMeta {
     neg: Symbol,
-    chars: String,
+    pattern: Pattern,
+}
+struct PositiveRuleMeta {
+    pos: Symbol,
+    pattern: Pattern,
+}
+struct StackRuleMeta {
+    name: String,
+    op: StackOp,
+}
+struct UnsupportedRuleMeta {
+    name: String,
+    rule: String,
+    expr: String,
+}
+struct AlternativeMeta {
+    name: String,
+    index: usize,
+    rule: String,
+    expr: String,
+}
+struct NamedRule {
+    lhs: String,
+    rhs: Vec<String>,
+    weight: Option<f64>,
 }
 
 struct Generator {
     grammar: Cfg,
     syms: BTreeMap<String, SymbolWithKind>,
     syms_by_range: HashMap<RangeInclusive<char>, String>,
+    /// Rules that derive either case of an ASCII letter, for `^"..."`.
+    syms_by_insens: HashMap<char, Symbol>,
+    /// Characters of builtin rules that match more than a single range.
+    classes: Vec<Vec<(char, char)>>,
     negative_rules: Vec<NegativeRuleMeta>,
+    positive_rules: Vec<PositiveRuleMeta>,
+    stack_rules: Vec<StackRuleMeta>,
+    unsupported_rules: Vec<UnsupportedRuleMeta>,
+    /// The strings of `Str` and `Insens` expressions, e.g. for fuzzer dictionaries.
+    literals: BTreeSet<String>,
+    /// Branches of choices, except for branches that are choices themselves.
+    alternatives: Vec<AlternativeMeta>,
+    /// The name of the rule being processed.
+    rule_name: String,
+    /// Rules by name, inlined into lookahead patterns.
+    rules: HashMap<String, OptimizedRule>,
+    /// Implicit whitespace and comments, skipped by lookahead in non-atomic rules.
+    whitespace: Option<Pattern>,
     // chars: BTreeMap<Terminal, NamedSymbol>,
     // // chars_by_sym: BTreeMap<Symbol, Terminal>,
     // rules: BTreeMap<String, Symbol>,
@@ -43,11 +91,22 @@ impl Generator {
             grammar: Cfg::new(),
             syms: BTreeMap::new(),
             syms_by_range: HashMap::new(),
+            syms_by_insens: HashMap::new(),
+            classes: vec![],
             negative_rules: vec![],
+            positive_rules: vec![],
+            stack_rules: vec![],
+            unsupported_rules: vec![],
+            literals: BTreeSet::new(),
+            alternatives: vec![],
+            rule_name: String::new(),
+            rules: HashMap::new(),
+            whitespace: None,
         }
     }
 
     fn process_rule(&mut self, rule: &OptimizedRule) {
+        self.rule_name = rule.name.clone();
         let lhs = self.intern_ident(rule.name.clone());
         let rhs = self.process_expr(&rule.expr, rule.ty);
         self.grammar.rule(lhs).rhs(rhs);
@@ -69,6 +128,24 @@ impl Generator {
         }
     }
 
+    fn intern_insens_char(&mut self, ch: char) -> Symbol {
+        let lower = ch.to_ascii_lowercase();
+        let upper = ch.to_ascii_uppercase();
+        if lower == upper {
+            return self.intern_chars(ch..=ch);
+        }
+        if let Some(&sym) = self.syms_by_insens.get(&lower) {
+            return sym;
+        }
+        let lower_sym = self.intern_chars(lower..=lower);
+        let upper_sym = self.intern_chars(upper..=upper);
+        let lhs = self.grammar.sym();
+        self.grammar.rule(lhs).rhs([lower_sym]).rhs([upper_sym]);
+        self.add_rule(lhs);
+        self.syms_by_insens.insert(lower, lhs);
+        lhs
+    }
+
     fn intern_ident(&mut self, name: String) -> Symbol {
         self.syms.entry(name).or_insert_with(|| {
             SymbolWithKind { symbol: self.grammar.sym(), kind: SymbolKind::Nonterminal }
@@ -79,34 +156,238 @@ impl Generator {
         self.syms.insert(format!("r_{}", lhs.usize()), SymbolWithKind { symbol: lhs, kind: SymbolKind::Nonterminal });
     }
 
+    /// Returns the only symbol of `rhs`, or a new rule deriving all of them.
+    fn single_sym(&mut self, rhs: Vec<Symbol>) -> Symbol {
+        if rhs.len() == 1 {
+            return rhs[0];
+        }
+        let lhs = self.grammar.sym();
+        self.grammar.rule(lhs).rhs(rhs);
+        self.add_rule(lhs);
+        lhs
+    }
+
     fn add_negative_sym(&mut self, neg: Symbol) {
         self.syms.insert(format!("neg_{}", neg.usize()), SymbolWithKind { symbol: neg, kind: SymbolKind::Nonterminal });
     }
 
-    fn add_negative_rule(&mut self, neg: Symbol, chars: String) {
-        self.negative_rules.push(NegativeRuleMeta { neg, chars });
+    fn add_negative_rule(&mut self, neg: Symbol, pattern: Pattern) {
+        self.negative_rules.push(NegativeRuleMeta { neg, pattern });
+    }
+
+    fn add_positive_rule(&mut self, pos: Symbol, pattern: Pattern) {
+        self.syms.insert(format!("pos_{}", pos.usize()), SymbolWithKind { symbol: pos, kind: SymbolKind::Nonterminal });
+        self.positive_rules.push(PositiveRuleMeta { pos, pattern });
+    }
+
+    fn add_stack_rule(&mut self, sym: Symbol, name: String, op: StackOp) {
+        self.syms.insert(name.clone(), SymbolWithKind { symbol: sym, kind: SymbolKind::Nonterminal });
+        self.grammar.rule(sym).rhs([]);
+        self.stack_rules.push(StackRuleMeta { name, op });
+    }
+
+    /// Marks `expr` in the current rule as unsupported, failing generation that reaches it.
+    fn add_unsupported_rule(&mut self, expr: &OptimizedExpr) -> Symbol {
+        let sym: Symbol = self.grammar.sym();
+        let name = format!("unsupported_{}", sym.usize());
+        self.syms.insert(name.clone(), SymbolWithKind { symbol: sym, kind: SymbolKind::Nonterminal });
+        self.grammar.rule(sym).rhs([]);
+        self.unsupported_rules.push(UnsupportedRuleMeta { name, rule: self.rule_name.clone(), expr: format!("{:?}", expr) });
+        sym
+    }
+
+    /// Registers the branches of the choice `lhs` for coverage. A branch that is a
+    /// choice itself registers its own branches instead.
+    fn add_alternatives(&mut self, lhs: Symbol, branches: [&OptimizedExpr; 2]) {
+        for (index, branch) in branches.iter().enumerate() {
+            if !matches!(branch, OptimizedExpr::Choice(..)) {
+                self.alternatives.push(AlternativeMeta {
+                    name: format!("r_{}", lhs.usize()),
+                    index,
+                    rule: self.rule_name.clone(),
+                    expr: expr_label(branch),
+                });
+            }
+        }
+    }
+
+    fn add_literal(&mut self, string: &str) {
+        if !string.is_empty() {
+            self.literals.insert(string.to_owned());
+        }
+    }
+
+    /// Adds rules for the builtin rules used in the grammar that match more than
+    /// one character, and registers the stack operations of builtin rules.
+    fn add_builtin_rules(&mut self) {
+        if let Some(newline) = self.syms.get("NEWLINE").map(|sym| sym.symbol) {
+            if !self.rules.contains_key("NEWLINE") {
+                for string in ["\n", "\r\n", "\r"] {
+                    let rhs = self.process_expr(&OptimizedExpr::Str(string.to_string()), RuleType::Atomic);
+                    self.grammar.rule(newline).rhs(rhs);
+                }
+            }
+        }
+        let builtins = [
+            ("PEEK", StackOp::Peek),
+            ("PEEK_ALL", StackOp::PeekAll),
+            ("POP", StackOp::Pop),
+            ("POP_ALL", StackOp::PopAll),
+            ("DROP", StackOp::Drop),
+        ];
+        for &(name, op) in &builtins {
+            if self.syms.contains_key(name) {
+                self.stack_rules.push(StackRuleMeta { name: name.to_string(), op });
+            }
+        }
+    }
+
+    fn decl_stack_rules(&self) -> Vec<TokenStream> {
+        self.stack_rules.iter().map(|stack_rule| {
+            let ident = Ident::new_raw(&stack_rule.name[..], Span::call_site());
+            let op = match stack_rule.op {
+                StackOp::PushStart => quote! { StackOp::PushStart },
+                StackOp::Push => quote! { StackOp::Push },
+                StackOp::Peek => quote! { StackOp::Peek },
+                StackOp::PeekAll => quote! { StackOp::PeekAll },
+                StackOp::PeekSlice(start, Some(end)) => quote! { StackOp::PeekSlice(#start, Some(#end)) },
+                StackOp::PeekSlice(start, None) => quote! { StackOp::PeekSlice(#start, None) },
+                StackOp::Pop => quote! { StackOp::Pop },
+                StackOp::PopAll => quote! { StackOp::PopAll },
+                StackOp::Drop => quote! { StackOp::Drop },
+            };
+            quote! { StackRule { sym: #ident, op: #op } }
+        }).collect()
+    }
+
+    fn decl_unsupported_rules(&self) -> Vec<TokenStream> {
+        self.unsupported_rules.iter().map(|unsupported_rule| {
+            let ident = Ident::new_raw(&unsupported_rule.name[..], Span::call_site());
+            let rule = &unsupported_rule.rule;
+            let expr = &unsupported_rule.expr;
+            quote! { UnsupportedRule { sym: #ident, rule: #rule.to_owned(), expr: #expr.to_owned() } }
+        }).collect()
+    }
+
+    fn decl_alternatives(&self) -> Vec<TokenStream> {
+        self.alternatives.iter().map(|alternative| {
+            let ident = Ident::new_raw(&alternative.name[..], Span::call_site());
+            let index = alternative.index;
+            quote! { Alternative { sym: #ident, index: #index } }
+        }).collect()
+    }
+
+    fn alternative_names(&self) -> Vec<TokenStream> {
+        self.alternatives.iter().map(|alternative| {
+            let rule = &alternative.rule;
+            let expr = &alternative.expr;
+            quote! { (#rule, #expr) }
+        }).collect()
+    }
+
+    fn decl_positive_rules(&self) -> Vec<TokenStream> {
+        self.positive_rules.iter().map(|pos_rule| {
+            let pattern = quote_pattern(&pos_rule.pattern);
+            let name = format!("pos_{}", pos_rule.pos.usize());
+            let ident = Ident::new_raw(&name[..], Span::call_site());
+            quote! { PositiveRule { sym: #ident, pattern: #pattern } }
+        }).collect()
     }
 
     fn decl_negative_rules(&self) -> Vec<TokenStream> {
         self.negative_rules.iter().map(|neg_rule| {
-            let chars = &neg_rule.chars;
+            let pattern = quote_pattern(&neg_rule.pattern);
             let name = format!("neg_{}", neg_rule.neg.usize());
             let ident = Ident::new_raw(&name[..], Span::call_site());
-            quote! { NegativeRule { sym: #ident, chars: #chars } }
+            quote! { NegativeRule { sym: #ident, pattern: #pattern } }
         }).collect()
     }
 
+    /// Returns the pattern checked by a lookahead on `expr` in a rule of `rule_type`.
+    fn lookahead_pattern(&self, expr: &OptimizedExpr, rule_type: RuleType) -> Option<Pattern> {
+        let atomic = matches!(rule_type, RuleType::Atomic | RuleType::CompoundAtomic);
+        let pattern = self.pattern(expr, atomic, &mut vec![])?;
+        match &self.whitespace {
+            Some(whitespace) if !atomic => Some(Pattern::Seq(vec![whitespace.clone(), pattern])),
+            _ => Some(pattern),
+        }
+    }
+
+    /// Converts `expr` into a pattern, inlining the rules it refers to. Returns `None`
+    /// if `expr` is not regular, i.e. it refers to a recursive rule or uses the stack.
+    fn pattern(&self, expr: &OptimizedExpr, atomic: bool, visiting: &mut Vec<String>) -> Option<Pattern> {
+        use OptimizedExpr::*;
+        let whitespace = self.whitespace.as_ref().filter(|_| !atomic);
+        let pattern = match expr {
+            Str(string) => Pattern::Str(string.clone()),
+            Insens(string) => Pattern::Insens(string.clone()),
+            Range(first, last) => Pattern::Range(first.chars().next()?, last.chars().next()?),
+            Ident(name) => match self.rules.get(name) {
+                Some(_) if visiting.contains(name) => return None,
+                Some(rule) => {
+                    let atomic = match rule.ty {
+                        RuleType::Atomic | RuleType::CompoundAtomic => true,
+                        RuleType::NonAtomic => false,
+                        RuleType::Normal | RuleType::Silent => atomic,
+                    };
+                    visiting.push(name.clone());
+                    let pattern = self.pattern(&rule.expr, atomic, visiting);
+                    visiting.pop();
+                    pattern?
+                }
+                None => builtin_pattern(name)?,
+            },
+            Seq(left, right) => {
+                let mut patterns = vec![self.pattern(left, atomic, visiting)?];
+                let is_weight = matches!(**left, Weight) || matches!(**right, Weight);
+                if let Some(whitespace) = whitespace.filter(|_| !is_weight) {
+                    patterns.push(whitespace.clone());
+                }
+                patterns.push(self.pattern(right, atomic, visiting)?);
+                Pattern::Seq(patterns)
+            }
+            Choice(left, right, _) => Pattern::Choice(vec![
+                self.pattern(left, atomic, visiting)?,
+                self.pattern(right, atomic, visiting)?,
+            ]),
+            Opt(expr) => Pattern::Opt(Box::new(self.pattern(expr, atomic, visiting)?)),
+            Rep(expr) => {
+                let pattern = self.pattern(expr, atomic, visiting)?;
+                match whitespace {
+                    Some(whitespace) => {
+                        let rest = Pattern::Seq(vec![whitespace.clone(), pattern.clone()]);
+                        Pattern::Opt(Box::new(Pattern::Seq(vec![pattern, Pattern::Rep(Box::new(rest))])))
+                    }
+                    None => Pattern::Rep(Box::new(pattern)),
+                }
+            }
+            Separated(expr, separator, separation) => {
+                let list = expand_separated((**expr).clone(), (**separator).clone(), *separation);
+                self.pattern(&list, atomic, visiting)?
+            }
+            PosPred(expr) => Pattern::PosPred(Box::new(self.pattern(expr, atomic, visiting)?)),
+            NegPred(expr) => Pattern::NegPred(Box::new(self.pattern(expr, atomic, visiting)?)),
+            NodeTag(expr, _) | RestoreOnErr(expr) => self.pattern(expr, atomic, visiting)?,
+            Weight => Pattern::Seq(vec![]),
+            Skip(strings) => self.pattern(&skip_expr(strings), true, visiting)?,
+            PeekSlice(..) | Push(..) => return None,
+        };
+        Some(pattern)
+    }
+
     fn process_expr(&mut self, expr: &OptimizedExpr, rule_type: RuleType) -> Vec<Symbol> {
         use OptimizedExpr::*;
         match expr {
             // /// Matches an exact string, e.g. `"a"`
-            Str(chars) | Insens(chars) => {
-                // TODO: Insens
+            Str(chars) => {
+                self.add_literal(chars);
                 chars.chars().map(|ch| self.intern_chars(ch..=ch)).collect()
             }
-
             // /// Matches an exact string, case insensitively (ASCII only), e.g. `^"a"`
-            // Insens(String),
+            Insens(chars) => {
+                self.add_literal(chars);
+                chars.chars().map(|ch| self.intern_insens_char(ch)).collect()
+            }
             // /// Matches one character in the range, e.g. `'a'..'z'`
             Range(ch_a, ch_b) => {
                 assert_eq!(ch_a.chars().count(), 1);
@@ -120,8 +401,11 @@ impl Generator {
             // /// Matches a sequence of two expressions, e.g. `e1 ~ e2`
             Seq(left, right) => {
                 let mut result = self.process_expr(left, rule_type);
+                // A weight marker `W:n` generates nothing, so it does not need a separator.
+                let is_weight = matches!(**left, Weight) || matches!(**right, Weight);
                 match rule_type {
                     RuleType::Atomic | RuleType::CompoundAtomic => {}
+                    RuleType::NonAtomic | RuleType::Silent | RuleType::Normal if is_weight => {}
                     RuleType::NonAtomic | RuleType::Silent | RuleType::Normal => {
                         result.push(self.intern_ident("WHITESPACE".to_string()));
                     }
@@ -130,12 +414,18 @@ impl Generator {
                 result
             }
             // /// Matches either of two expressions, e.g. `e1 | e2`
-            Choice(left, right, _weights) => {
+            Choice(left, right, (left_weight, right_weight)) => {
                 let lhs = self.grammar.sym();
                 let left_rhs = self.process_expr(left, rule_type);
                 let right_rhs = self.process_expr(right, rule_type);
-                self.grammar.rule(lhs).rhs(left_rhs).rhs(right_rhs);
+                // The right weight already sums up all alternatives nested on the right,
+                // see `rotater`.
+                self.grammar
+                    .rule(lhs)
+                    .rhs_with_linked_history(left_rhs, LinkedHistoryNode::Weight { weight: *left_weight as f64 })
+                    .rhs_with_linked_history(right_rhs, LinkedHistoryNode::Weight { weight: *right_weight as f64 });
                 self.add_rule(lhs);
+                self.add_alternatives(lhs, [left, right]);
                 vec![lhs]
             }
             // /// Optionally matches an expression, e.g. `e?`
@@ -150,52 +440,90 @@ impl Generator {
             Rep(expr) => {
                 let lhs = self.grammar.sym();
                 let rhs = self.process_expr(expr, rule_type);
-                let rhs_sym = if rhs.len() > 1 {
-                    let lhs = self.grammar.sym();
-                    self.grammar.rule(lhs).rhs(rhs);
-                    self.add_rule(lhs);
-                    lhs
+                let rhs_sym = self.single_sym(rhs);
+                self.grammar.sequence(lhs).inclusive(0, None).rhs(rhs_sym);
+                self.add_rule(lhs);
+                vec![lhs]
+            }
+            // /// Matches an expression one or more times, separated by another, e.g. `e % ","`
+            Separated(expr, separator, separation) => {
+                let lhs = self.grammar.sym();
+                let rhs = self.process_expr(expr, rule_type);
+                let rhs_sym = self.single_sym(rhs);
+                let mut separator = self.process_expr(separator, rule_type);
+                if !matches!(rule_type, RuleType::Atomic | RuleType::CompoundAtomic) {
+                    let whitespace = self.intern_ident("WHITESPACE".to_string());
+                    separator.insert(0, whitespace);
+                    separator.push(whitespace);
+                }
+                let separator_sym = self.single_sym(separator);
+                // The sequence derives the list length and the trailing separator directly.
+                let separator = if separation.allow_trailing {
+                    Separator::Liberal(separator_sym)
                 } else {
-                    rhs[0]
+                    Separator::Proper(separator_sym)
                 };
-                self.grammar.sequence(lhs).inclusive(0, None).rhs(rhs_sym);
+                let min = if separation.allow_empty { 0 } else { 1 };
+                self.grammar.sequence(lhs).separator(separator).inclusive(min, None).rhs(rhs_sym);
                 self.add_rule(lhs);
                 vec![lhs]
             }
             // /// Matches a custom part of the stack, e.g. `PEEK[..]`
-            PeekSlice(..) => panic!(),
+            PeekSlice(start, end) => {
+                let peek = self.grammar.sym();
+                self.add_stack_rule(peek, format!("peek_{}", peek.usize()), StackOp::PeekSlice(*start, *end));
+                vec![peek]
+            }
             // /// Positive lookahead; matches expression without making progress, e.g. `&e`
             PosPred(expr) => {
-                // TODO
-                vec![]
+                match self.lookahead_pattern(expr, rule_type) {
+                    Some(pattern) => {
+                        let pos = self.grammar.sym();
+                        self.add_positive_rule(pos, pattern);
+                        self.grammar.rule(pos).rhs([]);
+                        vec![pos]
+                    }
+                    None => {
+                        eprintln!("positive lookahead is not supported by generation: {:?}", expr);
+                        vec![self.add_unsupported_rule(expr)]
+                    }
+                }
             },
             // /// Negative lookahead; matches if expression doesn't match, without making progress, e.g. `!e`
             NegPred(expr) => {
-                let neg = self.grammar.sym();
-                self.add_negative_sym(neg);
-                eprintln!("{:?}", expr);
-                match &**expr {
-                    Str(chars) => {
-                        self.add_negative_rule(neg, chars.clone());
+                match self.lookahead_pattern(expr, rule_type) {
+                    Some(pattern) => {
+                        let neg = self.grammar.sym();
+                        self.add_negative_sym(neg);
+                        self.add_negative_rule(neg, pattern);
+                        self.grammar.rule(neg).rhs([]);
+                        vec![neg]
                     }
-                    Ident(name) if name == "ASCII_ALPHA" => {
-                        for ch in ('0'..='9').chain('a'..='z').chain('A'..='Z') {
-                            self.add_negative_rule(neg, ch.to_string());
-                        }
+                    None => {
+                        eprintln!("negative lookahead is not supported by generation: {:?}", expr);
+                        vec![self.add_unsupported_rule(expr)]
                     }
-                    _ => panic!("invalid negative lookahead - only strings or ASCII_ALPHA are allowed")
                 }
-                self.grammar.rule(neg).rhs([]);
-                vec![neg]
             },
             // /// Continues to match expressions until one of the strings in the `Vec` is found
-            Skip(v) => panic!(),
+            Skip(strings) => {
+                self.process_expr(&skip_expr(strings), RuleType::Atomic)
+            }
             // /// Matches an expression and pushes it to the stack, e.g. `push(e)`
-            Push(v) => panic!(),
+            Push(expr) => {
+                let push = self.grammar.sym();
+                let pushed = self.grammar.sym();
+                self.add_stack_rule(push, format!("push_{}", push.usize()), StackOp::PushStart);
+                self.add_stack_rule(pushed, format!("pushed_{}", pushed.usize()), StackOp::Push);
+                let mut result = vec![push];
+                result.extend(self.process_expr(expr, rule_type));
+                result.push(pushed);
+                result
+            }
             // /// Matches an expression and assigns a label to it, e.g. #label = exp
-            NodeTag(expr, s) => panic!(),
+            NodeTag(expr, _) => self.process_expr(expr, rule_type),
             // /// Restores an expression's checkpoint
-            RestoreOnErr(expr) => panic!(),
+            RestoreOnErr(expr) => self.process_expr(expr, rule_type),
             // /// Weight.
             Weight => {
                 vec![]
@@ -217,29 +545,43 @@ impl Generator {
                 let name = syms.get(&terminal).map(|r| &r.0[..]).unwrap();
                 let gen = match name {
                     "SOI" | "EOI" => SymbolKind::Null,
-                    "ASCII_ALPHA" => {
-                        SymbolKind::Range('a', 'z')
-                    }
-                    "ASCII_ALPHANUMERIC" => {
-                        SymbolKind::Range('a', 'z')
-                    }
-                    "ASCII_DIGIT" => {
-                        SymbolKind::Range('0', '9')
-                    }
-                    "ANY" => {
-                        SymbolKind::Range('\0', '\x7f')
-                    }
-                    other => {
-                        eprintln!("unknown terminal: {:?}", other);
-                        SymbolKind::Null
-                    }
+                    // Generated by the stack operation.
+                    "PEEK" | "PEEK_ALL" | "POP" | "POP_ALL" | "DROP" => SymbolKind::Null,
+                    // Implicit whitespace in grammars that don't define it.
+                    "WHITESPACE" | "COMMENT" => SymbolKind::Null,
+                    "ANY" => SymbolKind::Any,
+                    other => match builtin_ranges(other) {
+                        Some(ranges) if ranges.len() == 1 => SymbolKind::Range(ranges[0].0, ranges[0].1),
+                        Some(ranges) => {
+                            self.classes.push(ranges);
+                            SymbolKind::Class(self.classes.len() - 1)
+                        }
+                        None => {
+                            eprintln!("unknown terminal: {:?}", other);
+                            SymbolKind::Null
+                        }
+                    },
                 };
                 self.syms.insert(name.to_string(), SymbolWithKind { symbol: terminal, kind: gen });
             }
         }
     }
 
-    fn decl_rules(&mut self) -> Vec<TokenStream> {
+    fn rule_weight(&self, history_id: HistoryId) -> Option<f64> {
+        let history_graph = self.grammar.history_graph();
+        let mut history_id = history_id;
+        while let HistoryNode::Linked { prev, ref node } = history_graph[history_id.get()] {
+            if let LinkedHistoryNode::Weight { weight } = *node {
+                return Some(weight);
+            }
+            history_id = prev;
+        }
+        None
+    }
+
+    /// Returns the rules of the grammar with their symbols named, naming the symbols
+    /// that don't have a name yet.
+    fn named_rules(&mut self) -> Vec<NamedRule> {
         let sym_names: BTreeMap<Symbol, String> = self.syms.iter().map(|(name, sym_with_kind)| (sym_with_kind.symbol, name.clone())).collect();
         let mut new_syms = BTreeMap::new();
         let result = self.grammar.rules().map(|rule| {
@@ -253,20 +595,30 @@ impl Generator {
                 }
             };
             let lhs = get_or_intern(sym_names.get(&rule.lhs()), rule.lhs());
-            let lhs = Ident::new_raw(&lhs[..], Span::call_site());
-            let rhs: Vec<Ident> = rule.rhs().iter().map(|&sym| {
+            let rhs = rule.rhs().iter().map(|&sym| {
                 get_or_intern(sym_names.get(&sym), sym)
-            }).map(|name| {
-                Ident::new_raw(&name[..], Span::call_site())
             }).collect();
-            quote! {
-                grammar.rule(#lhs).rhs([#(#rhs),*]);
-            }
+            NamedRule { lhs, rhs, weight: self.rule_weight(rule.history_id()) }
         }).collect();
         self.syms.append(&mut new_syms);
         result
     }
 
+    fn decl_rules(&mut self) -> Vec<TokenStream> {
+        self.named_rules().into_iter().map(|rule| {
+            let lhs = Ident::new_raw(&rule.lhs[..], Span::call_site());
+            let rhs = rule.rhs.iter().map(|name| Ident::new_raw(&name[..], Span::call_site()));
+            match rule.weight {
+                Some(weight) => quote! {
+                    grammar.rule(#lhs).rhs_with_linked_history([#(#rhs),*], LinkedHistoryNode::Weight { weight: #weight });
+                },
+                None => quote! {
+                    grammar.rule(#lhs).rhs([#(#rhs),*]);
+                },
+            }
+        }).collect()
+    }
+
     fn decl_symbols(&self) -> Vec<TokenStream> {
         self.syms.keys()
             .map(|literal| {
@@ -279,6 +631,35 @@ impl Generator {
             .collect()
     }
 
+    /// Returns the names of the rules that show up in the derivation, which are the
+    /// rules that produce pairs when parsing.
+    fn derived_rule_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.rules.values()
+            .filter(|rule| rule.ty != RuleType::Silent)
+            .map(|rule| rule.name.clone())
+            .collect();
+        if self.syms.contains_key("EOI") {
+            names.push("EOI".to_string());
+        }
+        names.sort();
+        names
+    }
+
+    fn decl_rule_symbols(&self) -> Vec<TokenStream> {
+        self.derived_rule_names().iter()
+            .map(|name| {
+                let sym = Ident::new_raw(&name[..], Span::call_site());
+                let rule = if name == "EOI" {
+                    quote! { Rule::EOI }
+                } else {
+                    let rule = Ident::new_raw(&name[..], Span::call_site());
+                    quote! { Rule::#rule }
+                };
+                quote! { (#sym, #rule) }
+            })
+            .collect()
+    }
+
     fn match_start(&self) -> Vec<TokenStream> {
         self.syms.keys()
             .map(|name| {
@@ -295,13 +676,13 @@ impl Generator {
             .filter_map(|(name, sym_with_kind)| {
                 let ch = match sym_with_kind.kind {
                     SymbolKind::Single(ch) => quote! { Some(#ch) },
-                    SymbolKind::Range(start, end) => quote! { {
-                        let start = #start as u32;
-                        let end = #end as u32;
-                        let offset = byte_source.gen(end as f64 - start as f64);
-                        let result = char::from_u32(start + offset as u32).expect("incorrect char");
-                        Some(result)
-                    } },
+                    SymbolKind::Range(start, end) => quote! { Some(pick_char(&[(#start, #end)], driver)) },
+                    SymbolKind::Class(idx) => {
+                        let firsts = self.classes[idx].iter().map(|range| range.0);
+                        let lasts = self.classes[idx].iter().map(|range| range.1);
+                        quote! { Some(pick_char(&[#((#firsts, #lasts)),*], driver)) }
+                    }
+                    SymbolKind::Any => quote! { Some(pick_any(driver)) },
                     SymbolKind::Null => quote! { None },
                     SymbolKind::Nonterminal => return None,
                 };
@@ -312,43 +693,392 @@ impl Generator {
     }
 }
 
-pub fn generate_cfg_generator(rules: &[OptimizedRule]) -> TokenStream {
-    let mut generator = Generator::new();
-    for rule in rules {
-        generator.process_rule(rule);
+/// Renders `expr` in pest syntax, to name a branch of a choice.
+fn expr_label(expr: &OptimizedExpr) -> String {
+    use OptimizedExpr::*;
+    match expr {
+        Str(string) => format!("{:?}", string),
+        Insens(string) => format!("^{:?}", string),
+        Range(start, end) => format!("'{}'..'{}'", start, end),
+        Ident(name) => name.clone(),
+        PeekSlice(start, Some(end)) => format!("PEEK[{}..{}]", start, end),
+        PeekSlice(start, None) => format!("PEEK[{}..]", start),
+        PosPred(expr) => format!("&{}", expr_label_inner(expr)),
+        NegPred(expr) => format!("!{}", expr_label_inner(expr)),
+        Seq(left, right) => match (&**left, &**right) {
+            (Weight, expr) | (expr, Weight) => expr_label(expr),
+            _ => format!("{} ~ {}", expr_label(left), expr_label(right)),
+        },
+        Choice(left, right, _) => format!("{} | {}", expr_label(left), expr_label(right)),
+        Opt(expr) => format!("{}?", expr_label_inner(expr)),
+        Rep(expr) => format!("{}*", expr_label_inner(expr)),
+        Separated(expr, separator, separation) => format!(
+            "{} %{}{} {}",
+            expr_label_inner(expr),
+            if separation.allow_trailing { "..." } else { "" },
+            if separation.allow_empty { "*" } else { "" },
+            expr_label_inner(separator)
+        ),
+        Skip(strings) => format!("(!({}) ~ ANY)*", strings.iter().map(|string| format!("{:?}", string)).collect::<Vec<_>>().join(" | ")),
+        Push(expr) => format!("PUSH({})", expr_label(expr)),
+        NodeTag(expr, tag) => format!("#{} = {}", tag, expr_label_inner(expr)),
+        RestoreOnErr(expr) => expr_label(expr),
+        Weight => String::new(),
     }
-    generator.rewrite_sequences();
-    generator.update_chars();
+}
+
+/// Renders `expr` in pest syntax, in parentheses unless it is a single term.
+fn expr_label_inner(expr: &OptimizedExpr) -> String {
+    match expr {
+        OptimizedExpr::Seq(left, right) if !matches!((&**left, &**right), (OptimizedExpr::Weight, _) | (_, OptimizedExpr::Weight)) => {
+            format!("({})", expr_label(expr))
+        }
+        OptimizedExpr::Choice(..) | OptimizedExpr::Separated(..) => format!("({})", expr_label(expr)),
+        _ => expr_label(expr),
+    }
+}
+
+/// Returns the expression that `Skip` matches, i.e. `(!(s1 | s2 | ...) ~ ANY)*`.
+fn skip_expr(strings: &[String]) -> OptimizedExpr {
+    use OptimizedExpr::*;
+    let stop = strings
+        .iter()
+        .rev()
+        .map(|string| Str(string.clone()))
+        .reduce(|right, left| Choice(Box::new(left), Box::new(right), (1, 1)))
+        .unwrap_or_else(|| Str(String::new()));
+    let next = Seq(Box::new(NegPred(Box::new(stop))), Box::new(Ident("ANY".to_string())));
+    Rep(Box::new(next))
+}
+
+/// Returns the characters matched by a builtin rule that matches a single character,
+/// other than `ANY`.
+fn builtin_ranges(name: &str) -> Option<Vec<(char, char)>> {
+    let ranges = match name {
+        "ASCII_DIGIT" => vec![('0', '9')],
+        "ASCII_NONZERO_DIGIT" => vec![('1', '9')],
+        "ASCII_BIN_DIGIT" => vec![('0', '1')],
+        "ASCII_OCT_DIGIT" => vec![('0', '7')],
+        "ASCII_HEX_DIGIT" => vec![('0', '9'), ('a', 'f'), ('A', 'F')],
+        "ASCII_ALPHA_LOWER" => vec![('a', 'z')],
+        "ASCII_ALPHA_UPPER" => vec![('A', 'Z')],
+        "ASCII_ALPHA" => vec![('a', 'z'), ('A', 'Z')],
+        "ASCII_ALPHANUMERIC" => vec![('0', '9'), ('a', 'z'), ('A', 'Z')],
+        "ASCII" => vec![('\0', '\x7f')],
+        _ => pest::generation::property_ranges(name)?,
+    };
+    Some(ranges).filter(|ranges| !ranges.is_empty())
+}
+
+/// Returns the pattern of a builtin rule.
+fn builtin_pattern(name: &str) -> Option<Pattern> {
+    let pattern = match name {
+        "ANY" => Pattern::Range('\0', char::MAX),
+        "NEWLINE" => Pattern::Choice(vec![
+            Pattern::Str("\n".to_owned()),
+            Pattern::Str("\r\n".to_owned()),
+            Pattern::Str("\r".to_owned()),
+        ]),
+        _ => {
+            let ranges = builtin_ranges(name)?;
+            Pattern::Choice(ranges.into_iter().map(|(first, last)| Pattern::Range(first, last)).collect())
+        }
+    };
+    Some(pattern)
+}
+
+fn quote_pattern(pattern: &Pattern) -> TokenStream {
+    match pattern {
+        Pattern::Str(string) => quote! { Pattern::Str(#string.to_owned()) },
+        Pattern::Insens(string) => quote! { Pattern::Insens(#string.to_owned()) },
+        Pattern::Range(first, last) => quote! { Pattern::Range(#first, #last) },
+        Pattern::Seq(patterns) => {
+            let patterns = patterns.iter().map(quote_pattern);
+            quote! { Pattern::Seq(vec![#(#patterns),*]) }
+        }
+        Pattern::Choice(patterns) => {
+            let patterns = patterns.iter().map(quote_pattern);
+            quote! { Pattern::Choice(vec![#(#patterns),*]) }
+        }
+        Pattern::Opt(pattern) => {
+            let pattern = quote_pattern(pattern);
+            quote! { Pattern::Opt(Box::new(#pattern)) }
+        }
+        Pattern::Rep(pattern) => {
+            let pattern = quote_pattern(pattern);
+            quote! { Pattern::Rep(Box::new(#pattern)) }
+        }
+        Pattern::PosPred(pattern) => {
+            let pattern = quote_pattern(pattern);
+            quote! { Pattern::PosPred(Box::new(#pattern)) }
+        }
+        Pattern::NegPred(pattern) => {
+            let pattern = quote_pattern(pattern);
+            quote! { Pattern::NegPred(Box::new(#pattern)) }
+        }
+    }
+}
+
+impl Generator {
+    fn build(rules: &[OptimizedRule]) -> Self {
+        let mut generator = Generator::new();
+        generator.rules = rules.iter().map(|rule| (rule.name.clone(), rule.clone())).collect();
+        let skipped: Vec<Pattern> = ["WHITESPACE", "COMMENT"]
+            .iter()
+            .filter(|name| generator.rules.contains_key(**name))
+            .filter_map(|name| generator.pattern(&OptimizedExpr::Ident(name.to_string()), true, &mut vec![]))
+            .collect();
+        if !skipped.is_empty() {
+            generator.whitespace = Some(Pattern::Rep(Box::new(Pattern::Choice(skipped))));
+        }
+        for rule in rules {
+            generator.process_rule(rule);
+        }
+        generator.add_builtin_rules();
+        generator.rewrite_sequences();
+        generator.update_chars();
+        generator
+    }
+}
+
+/// Generation from pest rules at runtime, e.g. in `pest_vm`. Builds the same
+/// context-free grammar as the `generate` function emitted by `#[derive(Parser)]`.
+pub struct CfgGenerator {
+    grammar: BinarizedCfg,
+    syms: BTreeMap<String, Symbol>,
+    kinds: BTreeMap<Symbol, SymbolKind>,
+    classes: Vec<Vec<(char, char)>>,
+    negative_rules: Vec<NegativeRule>,
+    positive_rules: Vec<PositiveRule>,
+    stack_rules: Vec<StackRule>,
+    unsupported_rules: Vec<UnsupportedRule>,
+    rule_names: BTreeMap<Symbol, String>,
+    literals: Vec<String>,
+    alternatives: Vec<Alternative>,
+    alternative_names: Vec<(String, String)>,
+}
+
+impl CfgGenerator {
+    /// Builds the grammar for `rules`.
+    pub fn new(rules: &[OptimizedRule]) -> Self {
+        let mut generator = Generator::build(rules);
+        let named_rules = generator.named_rules();
+        let mut grammar = Cfg::new();
+        let syms: BTreeMap<String, Symbol> = generator.syms.keys().map(|name| (name.clone(), grammar.sym())).collect();
+        for rule in named_rules {
+            let lhs = syms[&rule.lhs];
+            let rhs: Vec<Symbol> = rule.rhs.iter().map(|name| syms[name]).collect();
+            match rule.weight {
+                Some(weight) => {
+                    grammar.rule(lhs).rhs_with_linked_history(rhs, LinkedHistoryNode::Weight { weight });
+                }
+                None => {
+                    grammar.rule(lhs).rhs(rhs);
+                }
+            }
+        }
+        let kinds = generator.syms.iter()
+            .filter(|(_, sym_with_kind)| sym_with_kind.kind != SymbolKind::Nonterminal)
+            .map(|(name, sym_with_kind)| (syms[name], sym_with_kind.kind))
+            .collect();
+        let negative_rules = generator.negative_rules.iter().map(|neg_rule| NegativeRule {
+            sym: syms[&format!("neg_{}", neg_rule.neg.usize())],
+            pattern: neg_rule.pattern.clone(),
+        }).collect();
+        let positive_rules = generator.positive_rules.iter().map(|pos_rule| PositiveRule {
+            sym: syms[&format!("pos_{}", pos_rule.pos.usize())],
+            pattern: pos_rule.pattern.clone(),
+        }).collect();
+        let stack_rules = generator.stack_rules.iter().map(|stack_rule| StackRule {
+            sym: syms[&stack_rule.name],
+            op: stack_rule.op,
+        }).collect();
+        let unsupported_rules = generator.unsupported_rules.iter().map(|unsupported_rule| UnsupportedRule {
+            sym: syms[&unsupported_rule.name],
+            rule: unsupported_rule.rule.clone(),
+            expr: unsupported_rule.expr.clone(),
+        }).collect();
+        let rule_names = generator.derived_rule_names().into_iter().map(|name| (syms[&name], name)).collect();
+        let alternatives = generator.alternatives.iter().map(|alternative| Alternative {
+            sym: syms[&alternative.name],
+            index: alternative.index,
+        }).collect();
+        let alternative_names = generator.alternatives.iter().map(|alternative| (alternative.rule.clone(), alternative.expr.clone())).collect();
+        CfgGenerator {
+            grammar: grammar.binarize(),
+            syms,
+            kinds,
+            classes: generator.classes,
+            negative_rules,
+            positive_rules,
+            stack_rules,
+            unsupported_rules,
+            rule_names,
+            literals: generator.literals.into_iter().collect(),
+            alternatives,
+            alternative_names,
+        }
+    }
+
+    /// Generates a string derived from the rule `start_sym`, driven by the bytes of `driver`,
+    /// along with its derivation by rule name.
+    pub fn generate(&self, start_sym: &str, driver: &[u8], limit: Option<u64>) -> Result<Derivation<String>, GenerationError> {
+        self.generate_guided(start_sym, driver, limit, &mut Guide::new())
+    }
+
+    /// Like `generate`, but favors the choices that `guide` hasn't seen and records the choices
+    /// made in `guide`.
+    pub fn generate_guided(&self, start_sym: &str, driver: &[u8], limit: Option<u64>, guide: &mut Guide) -> Result<Derivation<String>, GenerationError> {
+        let start_sym = *self.syms.get(start_sym).ok_or_else(|| GenerationError::UnknownStartRule(start_sym.to_owned()))?;
+        let rule_symbols: Vec<Symbol> = self.rule_names.keys().cloned().collect();
+        let generation = Generation::new(&self.grammar)
+            .negative_rules(&self.negative_rules[..])
+            .positive_rules(&self.positive_rules[..])
+            .stack_rules(&self.stack_rules[..])
+            .unsupported_rules(&self.unsupported_rules[..])
+            .rule_symbols(&rule_symbols[..])
+            .alternatives(&self.alternatives[..]);
+        let mut driver = Driver::new(driver);
+        let to_char = |sym, driver: &mut Driver<'_>| match self.kinds.get(&sym) {
+            Some(&SymbolKind::Single(ch)) => Some(ch),
+            Some(&SymbolKind::Range(start, end)) => Some(pick_char(&[(start, end)], driver)),
+            Some(&SymbolKind::Class(idx)) => Some(pick_char(&self.classes[idx], driver)),
+            Some(&SymbolKind::Any) => Some(pick_any(driver)),
+            Some(&SymbolKind::Null) => None,
+            Some(&SymbolKind::Nonterminal) | None => Some('X'),
+        };
+        let generated = generation.drive_guided(start_sym, limit, &mut driver, guide, to_char)?;
+        let derivation = Derivation { string: generated.string, trees: generated.trees, alternatives: generated.alternatives, rejections: generated.rejections };
+        Ok(derivation.map(|sym| self.rule_names[&sym].clone()))
+    }
+
+    /// Returns all strings derived from the rule `start_sym` with at most `max_len` characters,
+    /// shortest first. Lookaheads aren't checked, so the caller has to parse the strings to
+    /// filter out those that the rule doesn't match.
+    pub fn enumerate(&self, start_sym: &str, max_len: usize) -> Result<Vec<String>, GenerationError> {
+        let start_sym = *self.syms.get(start_sym).ok_or_else(|| GenerationError::UnknownStartRule(start_sym.to_owned()))?;
+        let generation = Generation::new(&self.grammar)
+            .negative_rules(&self.negative_rules[..])
+            .positive_rules(&self.positive_rules[..])
+            .stack_rules(&self.stack_rules[..])
+            .unsupported_rules(&self.unsupported_rules[..]);
+        let to_char = |sym| match self.kinds.get(&sym) {
+            Some(&SymbolKind::Single(ch)) => Some(ch),
+            Some(&SymbolKind::Range(start, end)) => Some(pick_char(&[(start, end)], &mut Driver::new(&[]))),
+            Some(&SymbolKind::Class(idx)) => Some(pick_char(&self.classes[idx], &mut Driver::new(&[]))),
+            Some(&SymbolKind::Any) => Some(pick_any(&mut Driver::new(&[]))),
+            Some(&SymbolKind::Null) => None,
+            Some(&SymbolKind::Nonterminal) | None => Some('X'),
+        };
+        Ok(generation.enumerate(start_sym, max_len, to_char).collect())
+    }
+
+    /// Returns the strings that the grammar matches literally, in order.
+    pub fn literals(&self) -> &[String] {
+        &self.literals
+    }
+
+    /// Returns the names of the rules that derivations record, in order.
+    pub fn rule_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.rule_names.values().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the branches of choices as the name of the rule and the branch's
+    /// expression. `Derivation::alternatives` indexes into these.
+    pub fn alternatives(&self) -> &[(String, String)] {
+        &self.alternative_names
+    }
+}
+
+pub fn generate_cfg_generator(rules: &[OptimizedRule]) -> TokenStream {
+    let mut generator = Generator::build(rules);
     let stmt_char_from_sym = generator.stmt_char_from_sym();
     let decl_rules = generator.decl_rules();
     let decl_symbols = generator.decl_symbols();
     let match_start = generator.match_start();
     let decl_negative_rules = generator.decl_negative_rules();
+    let decl_positive_rules = generator.decl_positive_rules();
+    let decl_stack_rules = generator.decl_stack_rules();
+    let decl_unsupported_rules = generator.decl_unsupported_rules();
+    let decl_rule_symbols = generator.decl_rule_symbols();
+    let decl_alternatives = generator.decl_alternatives();
+    let alternative_names = generator.alternative_names();
+    let literals = generator.literals.iter();
+    let rule_names = generator.derived_rule_names();
+    let setup = quote! {
+        use pest::cfg::prelude::*;
+        use pest::cfg::history::LinkedHistoryNode;
+        use pest::generation::{pick_any, pick_char, Alternative, Derivation, Driver, Generation, GenerationError, NegativeRule, Pattern, PositiveRule, StackOp, StackRule, UnsupportedRule};
+        use pest::env_logger::try_init;
+        use pest::log::debug;
+        let _ = try_init();
+        let mut grammar = Cfg::new();
+        #(#decl_symbols)*
+        #(#decl_rules)*
+        let mut binarized = grammar.binarize();
+        let start_sym = match start_sym {
+            #(#match_start)*
+            _ => return Err(GenerationError::UnknownStartRule(start_sym.to_owned())),
+        };
+        let negative_rules = vec![#(#decl_negative_rules),*];
+        let positive_rules = vec![#(#decl_positive_rules),*];
+        let stack_rules = vec![#(#decl_stack_rules),*];
+        let unsupported_rules = vec![#(#decl_unsupported_rules),*];
+        let rules_by_sym: ::std::collections::BTreeMap<Symbol, Rule> = vec![#(#decl_rule_symbols),*].into_iter().collect();
+        let rule_symbols: Vec<Symbol> = rules_by_sym.keys().cloned().collect();
+        let alternatives = vec![#(#decl_alternatives),*];
+        let generation = Generation::new(&binarized)
+            .negative_rules(&negative_rules[..])
+            .positive_rules(&positive_rules[..])
+            .stack_rules(&stack_rules[..])
+            .unsupported_rules(&unsupported_rules[..])
+            .rule_symbols(&rule_symbols[..])
+            .alternatives(&alternatives[..]);
+        let to_stmt_char_with_driver = |sym, driver: &mut Driver<'_>| {
+            #(#stmt_char_from_sym)*
+            return Some('X');
+        };
+    };
     let result = quote! {
-        fn generate(start_sym: &str, driver: &[u8], limit: Option<u64>) -> Result<String, ()> {
-            use pest::cfg::prelude::*;
-            use pest::cfg::generation::weighted::{Random, NegativeRule};
-            use pest::cfg::generation::weighted::random::ByteSource;
-            use pest::cfg::generation::weighted::random::GenRange;
-            use pest::env_logger::try_init;
-            use pest::log::debug;
-            let _ = try_init();
-            let mut grammar = Cfg::new();
-            #(#decl_symbols)*
-            #(#decl_rules)*
-            let mut binarized = grammar.binarize();
-            let start_sym = match start_sym {
-                #(#match_start)*
-                _ => panic!("incorrect start_sym provided"),
-            };
-            let negative_rules = vec![#(#decl_negative_rules),*];
-            let mut byte_source = ByteSource::new(driver.iter().cloned());
-            let to_stmt_char_with_byte_source = |sym, byte_source: &mut ByteSource<_>| {
-                #(#stmt_char_from_sym)*
-                return Some('X');
+        fn literals() -> &'static [&'static str] {
+            &[#(#literals),*]
+        }
+
+        fn rule_names() -> &'static [&'static str] {
+            &[#(#rule_names),*]
+        }
+
+        fn alternatives() -> &'static [(&'static str, &'static str)] {
+            &[#(#alternative_names),*]
+        }
+
+
+        fn generate(start_sym: &str, driver: &[u8], limit: Option<u64>) -> Result<pest::generation::Derivation<Rule>, pest::generation::GenerationError> {
+            Self::generate_guided(start_sym, driver, limit, &mut pest::generation::Guide::new())
+        }
+
+        fn generate_guided(start_sym: &str, driver: &[u8], limit: Option<u64>, guide: &mut pest::generation::Guide) -> Result<pest::generation::Derivation<Rule>, pest::generation::GenerationError> {
+            #setup
+            let mut driver = Driver::new(driver);
+            let generated = generation.drive_guided(start_sym, limit, &mut driver, guide, to_stmt_char_with_driver)?;
+            let derivation = Derivation { string: generated.string, trees: generated.trees, alternatives: generated.alternatives, rejections: generated.rejections };
+            Ok(derivation.map(|sym| rules_by_sym[&sym]))
+        }
+
+        fn generate_all(start_sym: &str, max_len: usize) -> Result<Vec<String>, pest::generation::GenerationError> {
+            #setup
+            let to_char = |sym| to_stmt_char_with_driver(sym, &mut Driver::new(&[]));
+            let rule = rules_by_sym.get(&start_sym).cloned();
+            let parses = |string: &String| match rule {
+                Some(rule) => match Self::parse(rule, string) {
+                    Ok(pairs) => pairs.last().map_or(string.is_empty(), |pair| pair.as_span().end() == string.len()),
+                    Err(_) => false,
+                },
+                None => true,
             };
-            let (result, string) = binarized.random(start_sym, limit, &mut byte_source, &negative_rules[..], to_stmt_char_with_byte_source).map_err(|_| ())?;
-            Ok(string.into_iter().collect())
+            Ok(generation.enumerate(start_sym, max_len, to_char).filter(parses).collect())
         }
     };
     eprintln!("GENERATE: {}", result);
@@ -2,7 +2,7 @@ use pest::iterators::Pairs;
 use pest_meta::parser::Rule;
 use std::collections::HashMap;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub(crate) struct DocComment {
     pub grammar_doc: String,
 
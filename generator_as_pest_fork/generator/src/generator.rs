@@ -501,6 +501,9 @@ fn generate_expr(expr: OptimizedExpr) -> TokenStream {
                 })
             }
         }
+        OptimizedExpr::Separated(expr, separator, separation) => {
+            generate_expr(expand_separated(*expr, *separator, separation))
+        }
         OptimizedExpr::Skip(strings) => {
             quote! {
                 let strings = [#(#strings),*];
@@ -640,6 +643,9 @@ fn generate_expr_atomic(expr: OptimizedExpr) -> TokenStream {
                 })
             }
         }
+        OptimizedExpr::Separated(expr, separator, separation) => {
+            generate_expr_atomic(expand_separated(*expr, *separator, separation))
+        }
         OptimizedExpr::Skip(strings) => {
             quote! {
                 let strings = [#(#strings),*];
@@ -36,23 +36,33 @@ mod macros;
 mod docs;
 mod generator;
 mod cfg_generator;
+mod syntax_generator;
+
+pub use crate::cfg_generator::CfgGenerator;
 
 use pest_meta::parser::{self, rename_meta_rule, Rule};
-use pest_meta::{optimizer, unwrap_or_report, validator};
+use pest_meta::{bnf, optimizer, unwrap_or_report, validator};
 
 /// Processes the derive/proc macro input and generates the corresponding parser based
 /// on the parsed grammar. If `include_grammar` is set to true, it'll generate an explicit
 /// "include_str" statement (done in pest_derive, but turned off in the local bootstrap).
 pub fn derive_parser(input: TokenStream, include_grammar: bool) -> TokenStream {
     let ast: DeriveInput = syn::parse2(input).unwrap();
+    let syntax_tree = parse_syntax_tree(&ast);
     let (name, generics, contents) = parse_derive(ast);
 
     let mut data = String::new();
     let mut paths = vec![];
+    let mut is_bnf = false;
+    let mut is_pest = false;
 
     for content in contents {
+        match content {
+            GrammarSource::Bnf(_) => is_bnf = true,
+            _ => is_pest = true,
+        }
         let (_data, _path) = match content {
-            GrammarSource::File(ref path) => {
+            GrammarSource::File(ref path) | GrammarSource::Bnf(ref path) => {
                 let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
 
                 // Check whether we can find a file at the path relative to the CARGO_MANIFEST_DIR
@@ -89,17 +99,32 @@ pub fn derive_parser(input: TokenStream, include_grammar: bool) -> TokenStream {
         }
     }
 
-    let pairs = match parser::parse(Rule::grammar_rules, &data) {
-        Ok(pairs) => pairs,
-        Err(error) => panic!("error parsing \n{}", error.renamed_rules(rename_meta_rule)),
-    };
+    if is_bnf && is_pest {
+        panic!("BNF grammars cannot be combined with pest grammars");
+    }
+
+    let (defaults, doc_comment, ast) = if is_bnf {
+        let rules = unwrap_or_report(bnf::parse(&data));
+        let defaults = unwrap_or_report(validator::validate_rules(&rules));
+        let ast = unwrap_or_report(parser::convert_rules(rules));
+        (defaults, docs::DocComment::default(), ast)
+    } else {
+        let pairs = match parser::parse(Rule::grammar_rules, &data) {
+            Ok(pairs) => pairs,
+            Err(error) => panic!("error parsing \n{}", error.renamed_rules(rename_meta_rule)),
+        };
 
-    let defaults = unwrap_or_report(validator::validate_pairs(pairs.clone()));
-    let doc_comment = docs::consume(pairs.clone());
-    let ast = unwrap_or_report(parser::consume_rules(pairs));
+        let defaults = unwrap_or_report(validator::validate_pairs(pairs.clone()));
+        let doc_comment = docs::consume(pairs.clone());
+        let ast = unwrap_or_report(parser::consume_rules(pairs));
+        (defaults, doc_comment, ast)
+    };
     let optimized = optimizer::optimize(ast);
 
-    generator::generate(
+    let syntax_tree = syntax_tree.map(|module| {
+        syntax_generator::generate_syntax_tree(&module, &optimized, &doc_comment)
+    });
+    let parser = generator::generate(
         name,
         &generics,
         paths,
@@ -107,7 +132,12 @@ pub fn derive_parser(input: TokenStream, include_grammar: bool) -> TokenStream {
         defaults,
         &doc_comment,
         include_grammar,
-    )
+    );
+
+    quote! {
+        #parser
+        #syntax_tree
+    }
 }
 
 fn read_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
@@ -121,6 +151,7 @@ fn read_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
 enum GrammarSource {
     File(String),
     Inline(String),
+    Bnf(String),
 }
 
 fn parse_derive(ast: DeriveInput) -> (Ident, Generics, Vec<GrammarSource>) {
@@ -132,7 +163,9 @@ fn parse_derive(ast: DeriveInput) -> (Ident, Generics, Vec<GrammarSource>) {
         .iter()
         .filter(|attr| {
             let path = attr.meta.path();
-            path.is_ident("grammar") || path.is_ident("grammar_inline")
+            path.is_ident("grammar")
+                || path.is_ident("grammar_inline")
+                || path.is_ident("grammar_bnf")
         })
         .collect();
 
@@ -148,6 +181,28 @@ fn parse_derive(ast: DeriveInput) -> (Ident, Generics, Vec<GrammarSource>) {
     (name, generics, grammar_sources)
 }
 
+/// Returns the module of the `#[syntax_tree = "MODULE"]` attribute, if any.
+fn parse_syntax_tree(ast: &DeriveInput) -> Option<Ident> {
+    let attr = ast
+        .attrs
+        .iter()
+        .find(|attr| attr.meta.path().is_ident("syntax_tree"))?;
+
+    match &attr.meta {
+        Meta::NameValue(name_value) => match &name_value.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(string),
+                ..
+            }) => match syn::parse_str(&string.value()) {
+                Ok(module) => Some(module),
+                Err(_) => panic!("syntax_tree attribute must name a module"),
+            },
+            _ => panic!("syntax_tree attribute must be a string"),
+        },
+        _ => panic!("syntax_tree attribute must be of the form `syntax_tree = \"...\"`"),
+    }
+}
+
 fn get_attribute(attr: &Attribute) -> GrammarSource {
     match &attr.meta {
         Meta::NameValue(name_value) => match &name_value.value {
@@ -157,6 +212,8 @@ fn get_attribute(attr: &Attribute) -> GrammarSource {
             }) => {
                 if name_value.path.is_ident("grammar") {
                     GrammarSource::File(string.value())
+                } else if name_value.path.is_ident("grammar_bnf") {
+                    GrammarSource::Bnf(string.value())
                 } else {
                     GrammarSource::Inline(string.value())
                 }
@@ -215,6 +272,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_bnf_file() {
+        let definition = "
+            #[other_attr]
+            #[grammar_bnf = \"myfile.bnf\"]
+            pub struct MyParser<'a, T>;
+        ";
+        let ast = syn::parse_str(definition).unwrap();
+        let (_, _, filenames) = parse_derive(ast);
+        assert_eq!(filenames, [GrammarSource::Bnf("myfile.bnf".to_string())]);
+    }
+
+    #[test]
+    fn derive_syntax_tree() {
+        let definition = "
+            #[grammar = \"myfile.pest\"]
+            #[syntax_tree = \"syntax\"]
+            pub struct MyParser<'a, T>;
+        ";
+        let ast = syn::parse_str(definition).unwrap();
+        let module = super::parse_syntax_tree(&ast);
+        assert_eq!(module.unwrap(), "syntax");
+        let (_, _, filenames) = parse_derive(ast);
+        assert_eq!(filenames, [GrammarSource::File("myfile.pest".to_string())]);
+    }
+
     #[test]
     #[should_panic(expected = "grammar attribute must be a string")]
     fn derive_wrong_arg() {
@@ -0,0 +1,602 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Generates the typed syntax tree of `#[syntax_tree = "module"]`, with the nodes
+//! of `pest::syntax`.
+
+use std::collections::{HashMap, HashSet};
+
+use proc_macro2::TokenStream;
+use syn::Ident;
+
+use pest_meta::ast::RuleType;
+use pest_meta::optimizer::*;
+
+use crate::docs::DocComment;
+
+/// The names that accessors must not take, since they would shadow the methods
+/// of `AstNode` and `Clone`.
+const RESERVED_METHODS: &[&str] = &["can_cast", "cast", "clone", "pair", "text"];
+
+/// How many times a child may occur in a node.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Cardinality {
+    One,
+    Opt,
+    Many,
+}
+
+/// The children of a node by rule name, in the order they first occur.
+#[derive(Debug, Default)]
+struct Children {
+    rules: Vec<(String, Cardinality)>,
+    /// The node tags and the rule of the tagged child, if it is a single node.
+    tags: Vec<(String, Option<String>)>,
+}
+
+impl Children {
+    fn get(&self, rule: &str) -> Option<Cardinality> {
+        self.rules
+            .iter()
+            .find(|(name, _)| name == rule)
+            .map(|&(_, cardinality)| cardinality)
+    }
+
+    fn set(&mut self, rule: &str, cardinality: Cardinality) {
+        match self.rules.iter_mut().find(|(name, _)| name == rule) {
+            Some(entry) => entry.1 = cardinality,
+            None => self.rules.push((rule.to_owned(), cardinality)),
+        }
+    }
+
+    fn map(mut self, f: impl Fn(Cardinality) -> Cardinality) -> Self {
+        for entry in &mut self.rules {
+            entry.1 = f(entry.1);
+        }
+        self
+    }
+
+    fn add_tags(&mut self, tags: Vec<(String, Option<String>)>) {
+        for (tag, rule) in tags {
+            match self.tags.iter_mut().find(|(name, _)| *name == tag) {
+                Some(entry) if entry.1 != rule => entry.1 = None,
+                Some(_) => {}
+                None => self.tags.push((tag, rule)),
+            }
+        }
+    }
+
+    /// The children of `self` followed by `other`.
+    fn seq(mut self, other: Children) -> Self {
+        for (rule, cardinality) in other.rules {
+            match self.get(&rule) {
+                Some(_) => self.set(&rule, Cardinality::Many),
+                None => self.set(&rule, cardinality),
+            }
+        }
+        self.add_tags(other.tags);
+        self
+    }
+
+    /// The children of either `self` or `other`.
+    fn choice(mut self, other: Children) -> Self {
+        for entry in &mut self.rules {
+            if other.get(&entry.0).is_none() {
+                entry.1 = entry.1.max(Cardinality::Opt);
+            }
+        }
+        for (rule, cardinality) in other.rules {
+            match self.get(&rule) {
+                Some(existing) => self.set(&rule, existing.max(cardinality)),
+                None => self.set(&rule, cardinality.max(Cardinality::Opt)),
+            }
+        }
+        self.add_tags(other.tags);
+        self
+    }
+}
+
+struct SyntaxTree<'a> {
+    rules: HashMap<&'a str, &'a OptimizedRule>,
+    /// The silent rules that are choices between nodes, by the rules they choose.
+    enums: HashMap<&'a str, Vec<&'a str>>,
+    type_names: HashMap<&'a str, Ident>,
+}
+
+impl<'a> SyntaxTree<'a> {
+    fn new(rules: &'a [OptimizedRule]) -> Self {
+        let rule_map: HashMap<&str, &OptimizedRule> = rules
+            .iter()
+            .map(|rule| (rule.name.as_str(), rule))
+            .collect();
+
+        // A silent rule is an enum once all the rules it chooses between are nodes or
+        // enums, so enums never contain themselves.
+        let mut enums: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in rules {
+                if rule.ty != RuleType::Silent || enums.contains_key(rule.name.as_str()) {
+                    continue;
+                }
+                let mut variants = vec![];
+                if choice_of_idents(&rule.expr, &mut variants)
+                    && variants.iter().all(|variant| {
+                        rule_map
+                            .get(variant)
+                            .map_or(false, |rule| rule.ty != RuleType::Silent)
+                            || enums.contains_key(variant)
+                    })
+                {
+                    let mut unique = vec![];
+                    for variant in variants {
+                        if !unique.contains(&variant) {
+                            unique.push(variant);
+                        }
+                    }
+                    enums.insert(rule.name.as_str(), unique);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut used: HashSet<String> = HashSet::new();
+        used.insert("Self".to_owned());
+        let mut type_names = HashMap::new();
+        for rule in rules {
+            if rule.ty == RuleType::Silent && !enums.contains_key(rule.name.as_str()) {
+                continue;
+            }
+            let camel = camel_case(&rule.name);
+            let name = if used.insert(camel.clone()) {
+                format_ident!("{}", camel)
+            } else {
+                used.insert(rule.name.clone());
+                format_ident!("r#{}", rule.name)
+            };
+            type_names.insert(rule.name.as_str(), name);
+        }
+
+        SyntaxTree {
+            rules: rule_map,
+            enums,
+            type_names,
+        }
+    }
+
+    /// Returns the children that `expr` produces, in an atomic context if `atomic`.
+    /// `inlined` holds the rules being walked through, and collects those that
+    /// refer to themselves.
+    fn children(
+        &self,
+        expr: &'a OptimizedExpr,
+        atomic: bool,
+        inlined: &mut Vec<(&'a str, bool)>,
+    ) -> Children {
+        match expr {
+            OptimizedExpr::Ident(name) => {
+                let rule = match self.rules.get(name.as_str()) {
+                    Some(rule) => *rule,
+                    None => return Children::default(),
+                };
+                let produces_pair = match rule.ty {
+                    RuleType::CompoundAtomic | RuleType::NonAtomic => true,
+                    RuleType::Normal | RuleType::Atomic => !atomic,
+                    RuleType::Silent => !atomic && self.enums.contains_key(name.as_str()),
+                };
+                if produces_pair {
+                    let mut children = Children::default();
+                    children.set(name, Cardinality::One);
+                    return children;
+                }
+                if let Some(entry) = inlined.iter_mut().find(|(rule, _)| rule == name) {
+                    entry.1 = true;
+                    return Children::default();
+                }
+                inlined.push((name.as_str(), false));
+                let children = self.children(&rule.expr, atomic, inlined);
+                let (_, recursive) = inlined.pop().unwrap();
+                if recursive {
+                    children.map(|_| Cardinality::Many)
+                } else {
+                    children
+                }
+            }
+            OptimizedExpr::Seq(lhs, rhs) => {
+                let lhs = self.children(lhs, atomic, inlined);
+                lhs.seq(self.children(rhs, atomic, inlined))
+            }
+            OptimizedExpr::Choice(lhs, rhs, _) => {
+                let lhs = self.children(lhs, atomic, inlined);
+                lhs.choice(self.children(rhs, atomic, inlined))
+            }
+            OptimizedExpr::Opt(expr) => self
+                .children(expr, atomic, inlined)
+                .map(|cardinality| cardinality.max(Cardinality::Opt)),
+            OptimizedExpr::Rep(expr) => self
+                .children(expr, atomic, inlined)
+                .map(|_| Cardinality::Many),
+            OptimizedExpr::Separated(expr, separator, _) => {
+                let expr = self.children(expr, atomic, inlined);
+                expr.seq(self.children(separator, atomic, inlined))
+                    .map(|_| Cardinality::Many)
+            }
+            OptimizedExpr::Push(expr) | OptimizedExpr::RestoreOnErr(expr) => {
+                self.children(expr, atomic, inlined)
+            }
+            OptimizedExpr::NodeTag(expr, tag) => {
+                let mut children = self.children(expr, atomic, inlined);
+                let rule = match &**expr {
+                    OptimizedExpr::Ident(name) if children.get(name).is_some() => {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                };
+                if rule.is_some() || !children.rules.is_empty() {
+                    children.add_tags(vec![(tag.clone(), rule)]);
+                }
+                children
+            }
+            OptimizedExpr::Str(_)
+            | OptimizedExpr::Insens(_)
+            | OptimizedExpr::Range(_, _)
+            | OptimizedExpr::PeekSlice(_, _)
+            | OptimizedExpr::PosPred(_)
+            | OptimizedExpr::NegPred(_)
+            | OptimizedExpr::Skip(_)
+            | OptimizedExpr::Weight => Children::default(),
+        }
+    }
+
+    fn generate_node(&self, rule: &'a OptimizedRule, doc_comment: &DocComment) -> TokenStream {
+        let name = &self.type_names[rule.name.as_str()];
+        let rule_name = format_ident!("r#{}", rule.name);
+        let doc = node_doc(rule, doc_comment);
+        let children = self.children(&rule.expr, rule.ty == RuleType::Atomic, &mut vec![]);
+        let accessors = self.generate_accessors(children);
+
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+            pub struct #name<'i> {
+                pair: ::pest::iterators::Pair<'i, super::Rule>,
+            }
+
+            impl<'i> ::pest::syntax::AstNode<'i, super::Rule> for #name<'i> {
+                fn can_cast(rule: super::Rule) -> bool {
+                    rule == super::Rule::#rule_name
+                }
+
+                fn cast(
+                    pair: ::pest::iterators::Pair<'i, super::Rule>,
+                ) -> ::core::option::Option<Self> {
+                    if pair.as_rule() == super::Rule::#rule_name {
+                        ::core::option::Option::Some(#name { pair })
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+
+                fn pair(&self) -> &::pest::iterators::Pair<'i, super::Rule> {
+                    &self.pair
+                }
+            }
+
+            impl<'i> #name<'i> {
+                #( #accessors )*
+            }
+        }
+    }
+
+    fn generate_enum(&self, rule: &'a OptimizedRule, doc_comment: &DocComment) -> TokenStream {
+        let name = &self.type_names[rule.name.as_str()];
+        let doc = node_doc(rule, doc_comment);
+        let variants: Vec<&Ident> = self.enums[rule.name.as_str()]
+            .iter()
+            .map(|variant| &self.type_names[variant])
+            .collect();
+        let first = variants[0];
+        let rest = &variants[1..];
+
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+            pub enum #name<'i> {
+                #( #variants(#variants<'i>) ),*
+            }
+
+            impl<'i> ::pest::syntax::AstNode<'i, super::Rule> for #name<'i> {
+                fn can_cast(rule: super::Rule) -> bool {
+                    #( <#variants<'i> as ::pest::syntax::AstNode<'i, super::Rule>>::can_cast(rule) )||*
+                }
+
+                fn cast(
+                    pair: ::pest::iterators::Pair<'i, super::Rule>,
+                ) -> ::core::option::Option<Self> {
+                    let rule = pair.as_rule();
+                    if <#first<'i> as ::pest::syntax::AstNode<'i, super::Rule>>::can_cast(rule) {
+                        ::pest::syntax::AstNode::cast(pair).map(#name::#first)
+                    }
+                    #(
+                        else if <#rest<'i> as ::pest::syntax::AstNode<'i, super::Rule>>::can_cast(rule) {
+                            ::pest::syntax::AstNode::cast(pair).map(#name::#rest)
+                        }
+                    )*
+                    else {
+                        ::core::option::Option::None
+                    }
+                }
+
+                fn pair(&self) -> &::pest::iterators::Pair<'i, super::Rule> {
+                    match self {
+                        #( #name::#variants(node) => ::pest::syntax::AstNode::pair(node) ),*
+                    }
+                }
+            }
+        }
+    }
+
+    fn generate_accessors(&self, children: Children) -> Vec<TokenStream> {
+        let mut used: HashSet<String> = RESERVED_METHODS
+            .iter()
+            .map(|&name| name.to_owned())
+            .collect();
+        let mut accessors = vec![];
+
+        for (tag, rule) in &children.tags {
+            let method = format_ident!(
+                "r#{}",
+                unique_name(&mut used, &[tag.clone(), format!("{}_tag", tag)])
+            );
+            let doc = format!("Returns the child tagged `#{}`.", tag);
+            let ty = match rule {
+                Some(rule) => {
+                    let ty = &self.type_names[rule.as_str()];
+                    quote! { #ty<'i> }
+                }
+                None => quote! { ::pest::iterators::Pair<'i, super::Rule> },
+            };
+            accessors.push(quote! {
+                #[doc = #doc]
+                pub fn #method(&self) -> ::core::option::Option<#ty> {
+                    ::pest::syntax::tagged_child(&self.pair, #tag)
+                }
+            });
+        }
+
+        for (rule, cardinality) in &children.rules {
+            let ty = &self.type_names[rule.as_str()];
+            if *cardinality == Cardinality::Many {
+                let method = format_ident!(
+                    "r#{}",
+                    unique_name(&mut used, &[plural(rule), format!("{}_list", rule)])
+                );
+                let doc = format!("Returns the `{}` children.", rule);
+                accessors.push(quote! {
+                    #[doc = #doc]
+                    pub fn #method(&self) -> ::pest::syntax::AstChildren<'i, super::Rule, #ty<'i>> {
+                        ::pest::syntax::children(&self.pair)
+                    }
+                });
+            } else {
+                let method = format_ident!(
+                    "r#{}",
+                    unique_name(&mut used, &[rule.clone(), format!("{}_child", rule)])
+                );
+                let doc = format!("Returns the `{}` child.", rule);
+                accessors.push(quote! {
+                    #[doc = #doc]
+                    pub fn #method(&self) -> ::core::option::Option<#ty<'i>> {
+                        ::pest::syntax::child(&self.pair)
+                    }
+                });
+            }
+        }
+
+        accessors
+    }
+}
+
+/// Generates the module `name` with a typed node for each rule that produces pairs
+/// and an enum for each silent rule that chooses between them.
+pub(crate) fn generate_syntax_tree(
+    name: &Ident,
+    rules: &[OptimizedRule],
+    doc_comment: &DocComment,
+) -> TokenStream {
+    let tree = SyntaxTree::new(rules);
+    let nodes = rules.iter().filter_map(|rule| {
+        if tree.enums.contains_key(rule.name.as_str()) {
+            Some(tree.generate_enum(rule, doc_comment))
+        } else if rule.ty != RuleType::Silent {
+            Some(tree.generate_node(rule, doc_comment))
+        } else {
+            None
+        }
+    });
+
+    quote! {
+        #[doc = "The typed syntax tree of the grammar."]
+        #[allow(non_camel_case_types, clippy::all)]
+        pub mod #name {
+            #( #nodes )*
+        }
+    }
+}
+
+/// Collects the rules that `expr` chooses between, returning whether it is only a
+/// choice between rules.
+fn choice_of_idents<'a>(expr: &'a OptimizedExpr, idents: &mut Vec<&'a str>) -> bool {
+    match expr {
+        OptimizedExpr::Ident(name) => {
+            idents.push(name);
+            true
+        }
+        OptimizedExpr::Choice(lhs, rhs, _) => {
+            choice_of_idents(lhs, idents) && choice_of_idents(rhs, idents)
+        }
+        _ => false,
+    }
+}
+
+fn node_doc(rule: &OptimizedRule, doc_comment: &DocComment) -> String {
+    match doc_comment.line_docs.get(&rule.name) {
+        Some(doc) => doc.clone(),
+        None => format!("A `{}` node.", rule.name),
+    }
+}
+
+/// Returns the first of `candidates` that is not used yet, or the last one with a
+/// number appended.
+fn unique_name(used: &mut HashSet<String>, candidates: &[String]) -> String {
+    let mut name = candidates
+        .iter()
+        .find(|candidate| !used.contains(*candidate))
+        .cloned();
+    if name.is_none() {
+        let last = candidates.last().unwrap();
+        name = (2..)
+            .map(|number| format!("{}{}", last, number))
+            .find(|candidate| !used.contains(candidate));
+    }
+    let name = name.unwrap();
+    used.insert(name.clone());
+    name
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// Returns the name of the accessor for many `name` children. Names that may be plural
+/// already, like `call_rhs` or `args`, get a `_list` suffix.
+fn plural(name: &str) -> String {
+    if name.ends_with("ss") || name.ends_with('x') || name.ends_with("ch") || name.ends_with("sh") {
+        format!("{}es", name)
+    } else if name.ends_with('s') {
+        format!("{}_list", name)
+    } else if name.ends_with('y')
+        && !name[..name.len() - 1].ends_with(|c: char| "aeiou".contains(c))
+    {
+        format!("{}ies", &name[..name.len() - 1])
+    } else {
+        format!("{}s", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pest_meta::parser::{self, Rule};
+
+    fn children(grammar: &str, rule: &str) -> Children {
+        let pairs = parser::parse(Rule::grammar_rules, grammar).unwrap();
+        let rules = optimize(parser::consume_rules(pairs).unwrap());
+        let tree = SyntaxTree::new(&rules);
+        let rule = tree.rules[rule];
+        tree.children(&rule.expr, rule.ty == RuleType::Atomic, &mut vec![])
+    }
+
+    fn rules(rules: &[(&str, Cardinality)]) -> Vec<(String, Cardinality)> {
+        rules
+            .iter()
+            .map(|&(name, cardinality)| (name.to_owned(), cardinality))
+            .collect()
+    }
+
+    #[test]
+    fn cardinalities() {
+        let children = children(
+            "a = { \"a\" } b = { a ~ c? ~ (d | e ~ c) ~ f* ~ (a | f) } c = { \"c\" } d = { \"d\" } \
+             e = { \"e\" } f = { \"f\" }",
+            "b",
+        );
+        assert_eq!(
+            children.rules,
+            rules(&[
+                ("a", Cardinality::Many),
+                ("c", Cardinality::Many),
+                ("d", Cardinality::Opt),
+                ("e", Cardinality::Opt),
+                ("f", Cardinality::Many),
+            ])
+        );
+    }
+
+    #[test]
+    fn silent_rules() {
+        let grammar = "a = { \"a\" } b = { \"b\" } item = _{ a | b } inlined = _{ a ~ b } \
+                       recursive = _{ b ~ recursive? } \
+                       list = { item ~ inlined ~ recursive ~ a % \",\" }";
+        let children = children(grammar, "list");
+        assert_eq!(
+            children.rules,
+            rules(&[
+                ("item", Cardinality::One),
+                ("a", Cardinality::Many),
+                ("b", Cardinality::Many),
+            ])
+        );
+    }
+
+    #[test]
+    fn atomic_rules() {
+        let grammar = "a = { \"a\" } b = ${ \"b\" } c = @{ a ~ b }";
+        let children = children(grammar, "c");
+        assert_eq!(children.rules, rules(&[("b", Cardinality::One)]));
+    }
+
+    #[test]
+    fn tags() {
+        let grammar = "a = { \"a\" } b = { #lhs = a ~ \"+\" ~ #rhs = (a ~ a) ~ #op = \"-\" }";
+        let children = children(grammar, "b");
+        assert_eq!(children.rules, rules(&[("a", Cardinality::Many)]));
+        assert_eq!(
+            children.tags,
+            [
+                ("lhs".to_owned(), Some("a".to_owned())),
+                ("rhs".to_owned(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(camel_case("function_definition"), "FunctionDefinition");
+        assert_eq!(camel_case("_inner_"), "Inner");
+        assert_eq!(camel_case("fooBar"), "FooBar");
+        assert_eq!(plural("statement"), "statements");
+        assert_eq!(plural("expression"), "expressions");
+        assert_eq!(plural("class"), "classes");
+        assert_eq!(plural("call_rhs"), "call_rhs_list");
+        assert_eq!(plural("body"), "bodies");
+        assert_eq!(plural("key"), "keys");
+        let mut used = HashSet::new();
+        used.insert("items".to_owned());
+        assert_eq!(
+            unique_name(&mut used, &["items".to_owned(), "item_list".to_owned()]),
+            "item_list"
+        );
+        assert_eq!(
+            unique_name(&mut used, &["items".to_owned(), "item_list".to_owned()]),
+            "item_list2"
+        );
+    }
+}
@@ -90,6 +90,8 @@ pub enum Expr {
     RepMax(Box<Expr>, u32),
     /// Matches an expression a number of times within a range, e.g. `e{m, n}`
     RepMinMax(Box<Expr>, u32, u32),
+    /// Matches an expression one or more times, separated by another, e.g. `e % ","`
+    Separated(Box<Expr>, Box<Expr>, Separation),
     /// Continues to match expressions until one of the strings in the `Vec` is found
     Skip(Vec<String>),
     /// Matches an expression and pushes it to the stack, e.g. `push(e)`
@@ -100,6 +102,16 @@ pub enum Expr {
     Weight,
 }
 
+/// What a separated list accepts besides elements separated one by one, e.g.
+/// `e %...* ","` for both
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Separation {
+    /// Whether the list may end with a separator, e.g. `e %... ","`
+    pub allow_trailing: bool,
+    /// Whether the list may be empty, e.g. `e %* ","`
+    pub allow_empty: bool,
+}
+
 impl Expr {
     /// Returns the iterator that steps the expression from top to bottom.
     pub fn iter_top_down(&self) -> ExprTopDownIterator {
@@ -160,6 +172,11 @@ impl Expr {
                     let mapped = Box::new(map_internal(*expr, f));
                     Expr::RepMinMax(mapped, min, max)
                 }
+                Expr::Separated(expr, separator, separation) => {
+                    let mapped = Box::new(map_internal(*expr, f));
+                    let mapped_separator = Box::new(map_internal(*separator, f));
+                    Expr::Separated(mapped, mapped_separator, separation)
+                }
                 Expr::Opt(expr) => {
                     let mapped = Box::new(map_internal(*expr, f));
                     Expr::Opt(mapped)
@@ -231,6 +248,11 @@ impl Expr {
                     let mapped = Box::new(map_internal(*expr, f));
                     Expr::RepMinMax(mapped, min, max)
                 }
+                Expr::Separated(expr, separator, separation) => {
+                    let mapped = Box::new(map_internal(*expr, f));
+                    let mapped_separator = Box::new(map_internal(*separator, f));
+                    Expr::Separated(mapped, mapped_separator, separation)
+                }
                 Expr::Opt(expr) => {
                     let mapped = Box::new(map_internal(*expr, f));
                     Expr::Opt(mapped)
@@ -283,6 +305,10 @@ impl ExprTopDownIterator {
                 self.right_branches.push(*rhs);
                 self.next = Some(*lhs);
             }
+            Expr::Separated(expr, separator, _separation) => {
+                self.right_branches.push(*separator);
+                self.next = Some(*expr);
+            }
             Expr::PosPred(expr)
             | Expr::NegPred(expr)
             | Expr::Rep(expr)
@@ -0,0 +1,889 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! A parser for grammars in BNF, which produces the same rules as the equivalent
+//! pest grammar.
+//!
+//! A rule is `name ::= expression` on a single line, continued on the following
+//! lines that start with `|`. Terms in a row form a sequence and `|` separates
+//! choices. `?`, `*`, `+` and `{n,m}` repeat a term, `&` and `!` look ahead, `()`
+//! derives the empty string and `//` starts a comment.
+//!
+//! `e%","+` and `e%","*` match one or more and zero or more `e` separated by `","`,
+//! and `e%","...+` and `e%","...*` also allow a trailing separator, like the pest
+//! separated lists `e % ","`, `e %* ","`, `e %... ","` and `e %...* ","`.
+//!
+//! `#start name` declares the rule `start = { SOI ~ name ~ EOI }` and `#atomic`
+//! makes the following rule atomic.
+//!
+//! `'text' -> name` and `/regex/ -> name` declare atomic rules for tokens, possibly
+//! followed by a type as in `/[0-9]+/ -> int : u64 { ... }`, which is ignored. The
+//! regular expressions support groups, classes, `.`, `\d`, `\w`, `\s` and their
+//! negations, and the repetitions above, which never backtrack, as in pest.
+
+use pest::error::{Error, ErrorVariant};
+use pest::{Position, Span};
+
+use crate::ast::{RuleType, Separation};
+use crate::parser::{unescape, ParserExpr, ParserNode, ParserRule, Rule};
+
+/// Parses a BNF grammar to the rules that the equivalent pest grammar parses to.
+pub fn parse(input: &str) -> Result<Vec<ParserRule<'_>>, Vec<Error<Rule>>> {
+    let mut parser = BnfParser {
+        input,
+        pos: 0,
+        depth: 0,
+    };
+    parser.rules()
+}
+
+/// A character of a regular expression, or a class of characters from an escape
+/// sequence such as `\d`.
+enum Escape<'i> {
+    Char(char),
+    Class(ParserExpr<'i>),
+}
+
+struct BnfParser<'i> {
+    input: &'i str,
+    pos: usize,
+    /// The number of open parentheses. Line breaks inside them never end the rule.
+    depth: usize,
+}
+
+impl<'i> BnfParser<'i> {
+    fn rules(&mut self) -> Result<Vec<ParserRule<'i>>, Vec<Error<Rule>>> {
+        let mut rules = vec![];
+        let mut ty = RuleType::Normal;
+
+        loop {
+            self.skip_lines();
+            let start = self.pos;
+            let rule = match self.peek() {
+                None if ty == RuleType::Atomic => {
+                    return Err(self.error("expected a rule after `#atomic`"))
+                }
+                None => return Ok(rules),
+                Some('#') if self.eat("#atomic") => {
+                    ty = RuleType::Atomic;
+                    continue;
+                }
+                Some('#') if self.eat("#start") => {
+                    self.skip_blanks();
+                    let name = self.identifier()?;
+                    let span = self.span(start);
+                    self.end_of_line()?;
+                    let ident = |name: &str, span| ParserNode {
+                        expr: ParserExpr::Ident(name.to_owned()),
+                        span,
+                    };
+                    let node = infix(
+                        infix(
+                            ident("SOI", span),
+                            ident(name.as_str(), name),
+                            ParserExpr::Seq,
+                        ),
+                        ident("EOI", span),
+                        ParserExpr::Seq,
+                    );
+                    rules.push(ParserRule {
+                        name: "start".to_owned(),
+                        span,
+                        ty: RuleType::Normal,
+                        node,
+                    });
+                    continue;
+                }
+                Some('\'') => {
+                    let text = self.quoted('\'')?;
+                    let node = ParserNode {
+                        expr: ParserExpr::Str(text),
+                        span: self.span(start),
+                    };
+                    self.token(node)?
+                }
+                Some('/') => {
+                    let node = self.regex()?;
+                    self.token(node)?
+                }
+                Some(c) if is_identifier_start(c) => {
+                    let span = self.identifier()?;
+                    self.skip_blanks();
+                    self.expect("::=")?;
+                    let node = self.choice()?;
+                    self.end_of_line()?;
+                    ParserRule {
+                        name: span.as_str().to_owned(),
+                        span,
+                        ty,
+                        node,
+                    }
+                }
+                Some(_) => return Err(self.error("expected a rule, `#start` or `#atomic`")),
+            };
+            rules.push(rule);
+            ty = RuleType::Normal;
+        }
+    }
+
+    /// Parses the rest of a token declaration after the token, `-> name` and an
+    /// optional type.
+    fn token(&mut self, node: ParserNode<'i>) -> Result<ParserRule<'i>, Vec<Error<Rule>>> {
+        self.skip_blanks();
+        self.expect("->")?;
+        self.skip_blanks();
+        let span = self.identifier()?;
+        self.skip_blanks();
+        if self.eat(":") {
+            self.skip_blanks();
+            self.identifier()?;
+            self.skip_blanks();
+            let start = self.pos;
+            self.expect("{")?;
+            let mut depth = 1;
+            while depth > 0 {
+                match self.bump() {
+                    Some('{') => depth += 1,
+                    Some('}') => depth -= 1,
+                    None => return Err(self.error_at(start, "unclosed `{`")),
+                    Some(_) => {}
+                }
+            }
+        }
+        self.end_of_line()?;
+
+        Ok(ParserRule {
+            name: span.as_str().to_owned(),
+            span,
+            ty: RuleType::Atomic,
+            node,
+        })
+    }
+
+    fn choice(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        self.skip_space();
+        // Like in pest, the first choice may start with `|`.
+        self.eat("|");
+        let mut node = self.sequence()?;
+        loop {
+            self.skip_space();
+            if !self.eat("|") {
+                return Ok(node);
+            }
+            let rhs = self.sequence()?;
+            node = infix(node, rhs, ParserExpr::Choice);
+        }
+    }
+
+    fn sequence(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let mut nodes = vec![];
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(c) if is_identifier_start(c) || "\"(&!".contains(c) => {
+                    nodes.push(self.term()?)
+                }
+                _ => break,
+            }
+        }
+        let mut nodes = nodes.into_iter();
+        let first = nodes.next().ok_or_else(|| self.error("expected a term"))?;
+        Ok(nodes.fold(first, |lhs, rhs| infix(lhs, rhs, ParserExpr::Seq)))
+    }
+
+    /// Parses a term with its operators.
+    fn term(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let start = self.pos;
+        let predicate: Option<fn(Box<ParserNode<'i>>) -> ParserExpr<'i>> = if self.eat("&") {
+            Some(ParserExpr::PosPred)
+        } else if self.eat("!") {
+            Some(ParserExpr::NegPred)
+        } else {
+            None
+        };
+        if let Some(predicate) = predicate {
+            self.skip_space();
+            let node = self.term()?;
+            return Ok(ParserNode {
+                expr: predicate(Box::new(node)),
+                span: self.span(start),
+            });
+        }
+
+        let mut node = self.primary()?;
+        loop {
+            let expr = match self.peek() {
+                Some('?') => {
+                    self.bump();
+                    ParserExpr::Opt(Box::new(node))
+                }
+                Some('*') => {
+                    self.bump();
+                    ParserExpr::Rep(Box::new(node))
+                }
+                Some('+') => {
+                    self.bump();
+                    ParserExpr::RepOnce(Box::new(node))
+                }
+                Some('{') => self.repetition(node)?,
+                Some('%') => return self.separated(node, start),
+                _ => return Ok(node),
+            };
+            node = ParserNode {
+                expr,
+                span: self.span(start),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => {
+                let text = self.quoted('"')?;
+                Ok(ParserNode {
+                    expr: ParserExpr::Str(text),
+                    span: self.span(start),
+                })
+            }
+            Some('(') => {
+                self.bump();
+                self.depth += 1;
+                self.skip_space();
+                let expr = if self.peek() == Some(')') {
+                    ParserExpr::Str(String::new())
+                } else {
+                    self.choice()?.expr
+                };
+                if !self.eat(")") {
+                    return Err(self.error_at(start, "unclosed `(`"));
+                }
+                self.depth -= 1;
+                Ok(ParserNode {
+                    expr,
+                    span: self.span(start),
+                })
+            }
+            Some(c) if is_identifier_start(c) => {
+                let span = self.identifier()?;
+                Ok(ParserNode {
+                    expr: ParserExpr::Ident(span.as_str().to_owned()),
+                    span,
+                })
+            }
+            _ => Err(self.error("expected a term")),
+        }
+    }
+
+    /// Parses `{n}`, `{n,}`, `{,m}` or `{n,m}` after `node`.
+    fn repetition(&mut self, node: ParserNode<'i>) -> Result<ParserExpr<'i>, Vec<Error<Rule>>> {
+        self.expect("{")?;
+        let min = self.number()?;
+        let expr = if self.eat(",") {
+            match (min, self.number()?) {
+                (Some(min), Some(max)) => ParserExpr::RepMinMax(Box::new(node), min, max),
+                (Some(min), None) => ParserExpr::RepMin(Box::new(node), min),
+                (None, Some(max)) => ParserExpr::RepMax(Box::new(node), max),
+                (None, None) => return Err(self.error("expected a number")),
+            }
+        } else {
+            match min {
+                Some(num) => ParserExpr::RepExact(Box::new(node), num),
+                None => return Err(self.error("expected a number")),
+            }
+        };
+        self.expect("}")?;
+        Ok(expr)
+    }
+
+    /// Parses an optional number of repetitions.
+    fn number(&mut self) -> Result<Option<u32>, Vec<Error<Rule>>> {
+        self.skip_blanks();
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        let digits = &self.input[start..self.pos];
+        self.skip_blanks();
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        match digits.parse::<u32>() {
+            Ok(0) if self.peek() == Some('}') => Err(self.error_at(start, "cannot repeat 0 times")),
+            Ok(num) => Ok(Some(num)),
+            Err(_) => Err(self.error_at(start, "number cannot overflow u32")),
+        }
+    }
+
+    /// Parses the separator and the operator of `e%"sep"+`, `e%"sep"*`, `e%"sep"...+`
+    /// or `e%"sep"...*` after `element`, and builds a single `ParserExpr::Separated`
+    /// node whose `Separation` records whether a trailing separator and an empty
+    /// list are allowed.
+    fn separated(
+        &mut self,
+        element: ParserNode<'i>,
+        start: usize,
+    ) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        self.expect("%")?;
+        let separator_start = self.pos;
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a string separator"));
+        }
+        let separator = ParserNode {
+            expr: ParserExpr::Str(self.quoted('"')?),
+            span: self.span(separator_start),
+        };
+        let allow_trailing = self.eat("...");
+        let allow_empty = if self.eat("+") {
+            false
+        } else if self.eat("*") {
+            true
+        } else {
+            return Err(self.error("expected `+` or `*`"));
+        };
+
+        Ok(ParserNode {
+            expr: ParserExpr::Separated(
+                Box::new(element),
+                Box::new(separator),
+                Separation {
+                    allow_trailing,
+                    allow_empty,
+                },
+            ),
+            span: self.span(start),
+        })
+    }
+
+    fn regex(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        self.expect("/")?;
+        let node = self.regex_choice()?;
+        self.expect("/")?;
+        Ok(node)
+    }
+
+    fn regex_choice(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let mut node = self.regex_sequence()?;
+        while self.eat("|") {
+            let rhs = self.regex_sequence()?;
+            node = infix(node, rhs, ParserExpr::Choice);
+        }
+        Ok(node)
+    }
+
+    fn regex_sequence(&mut self) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let start = self.pos;
+        let mut nodes: Vec<ParserNode<'i>> = vec![];
+        // Whether the last node is a string of single characters, which the next one
+        // extends.
+        let mut in_string = false;
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err(self.error("unterminated regular expression")),
+                Some('/') | Some('|') | Some(')') => break,
+                Some(_) => {}
+            }
+            let atom_start = self.pos;
+            let (atom, is_char) = self.regex_atom()?;
+            let atom_end = self.pos;
+            let node = self.regex_quantifier(atom, atom_start)?;
+            let is_char = is_char && self.pos == atom_end;
+
+            match nodes.last_mut() {
+                Some(last) if in_string && is_char => {
+                    if let (ParserExpr::Str(string), ParserExpr::Str(next)) =
+                        (&mut last.expr, node.expr)
+                    {
+                        string.push_str(&next);
+                    }
+                    last.span = last.span.start_pos().span(&node.span.end_pos());
+                }
+                _ => nodes.push(node),
+            }
+            in_string = is_char;
+        }
+
+        let mut nodes = nodes.into_iter();
+        Ok(match nodes.next() {
+            Some(first) => nodes.fold(first, |lhs, rhs| infix(lhs, rhs, ParserExpr::Seq)),
+            None => ParserNode {
+                expr: ParserExpr::Str(String::new()),
+                span: self.span(start),
+            },
+        })
+    }
+
+    /// Parses a character, a class, `.` or a group of a regular expression, and
+    /// returns whether it is a single character.
+    fn regex_atom(&mut self) -> Result<(ParserNode<'i>, bool), Vec<Error<Rule>>> {
+        let start = self.pos;
+        let expr = match self.peek() {
+            Some('(') => {
+                self.bump();
+                self.eat("?:");
+                let node = self.regex_choice()?;
+                self.expect(")")?;
+                node.expr
+            }
+            Some('[') => self.regex_class()?,
+            Some('.') => {
+                self.bump();
+                let newline = ParserNode {
+                    expr: ParserExpr::Str("\n".to_owned()),
+                    span: self.span(start),
+                };
+                any_except(newline)
+            }
+            Some('\\') => {
+                self.bump();
+                match self.regex_escape()? {
+                    Escape::Char(c) => {
+                        let node = ParserNode {
+                            expr: ParserExpr::Str(c.to_string()),
+                            span: self.span(start),
+                        };
+                        return Ok((node, true));
+                    }
+                    Escape::Class(expr) => expr,
+                }
+            }
+            Some('^') | Some('$') => return Err(self.error("anchors are not supported")),
+            Some('*') | Some('+') | Some('?') | Some('{') => {
+                return Err(self.error("nothing to repeat"))
+            }
+            Some(c) => {
+                self.bump();
+                let node = ParserNode {
+                    expr: ParserExpr::Str(c.to_string()),
+                    span: self.span(start),
+                };
+                return Ok((node, true));
+            }
+            None => return Err(self.error("unterminated regular expression")),
+        };
+        let node = ParserNode {
+            expr,
+            span: self.span(start),
+        };
+        Ok((node, false))
+    }
+
+    fn regex_quantifier(
+        &mut self,
+        node: ParserNode<'i>,
+        start: usize,
+    ) -> Result<ParserNode<'i>, Vec<Error<Rule>>> {
+        let expr = match self.peek() {
+            Some('?') => {
+                self.bump();
+                ParserExpr::Opt(Box::new(node))
+            }
+            Some('*') => {
+                self.bump();
+                ParserExpr::Rep(Box::new(node))
+            }
+            Some('+') => {
+                self.bump();
+                ParserExpr::RepOnce(Box::new(node))
+            }
+            Some('{') => self.repetition(node)?,
+            _ => return Ok(node),
+        };
+        match self.peek() {
+            Some('?') => Err(self.error("lazy repetitions are not supported")),
+            Some('*') | Some('+') | Some('{') => Err(self.error("nothing to repeat")),
+            _ => Ok(ParserNode {
+                expr,
+                span: self.span(start),
+            }),
+        }
+    }
+
+    /// Parses a class of characters such as `[a-z_]` or `[^"]`.
+    fn regex_class(&mut self) -> Result<ParserExpr<'i>, Vec<Error<Rule>>> {
+        let start = self.pos;
+        self.expect("[")?;
+        let negated = self.eat("^");
+        let mut items: Vec<ParserNode<'i>> = vec![];
+        loop {
+            let item_start = self.pos;
+            let from = match self.bump() {
+                None | Some('\n') => {
+                    return Err(self.error_at(start, "unterminated character class"))
+                }
+                // A `]` right after the `[` is a character of the class.
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => self.regex_escape()?,
+                Some(c) => Escape::Char(c),
+            };
+            let expr = match from {
+                Escape::Class(expr) => expr,
+                Escape::Char(from)
+                    if self.peek() == Some('-') && !self.rest().starts_with("-]") =>
+                {
+                    self.bump();
+                    let to = match self.bump() {
+                        Some('\\') => self.regex_escape()?,
+                        Some(c) if c != '\n' => Escape::Char(c),
+                        _ => return Err(self.error_at(start, "unterminated character class")),
+                    };
+                    match to {
+                        Escape::Char(to) if from <= to => {
+                            ParserExpr::Range(from.to_string(), to.to_string())
+                        }
+                        _ => return Err(self.error_at(item_start, "invalid range")),
+                    }
+                }
+                Escape::Char(c) => ParserExpr::Str(c.to_string()),
+            };
+            items.push(ParserNode {
+                expr,
+                span: self.span(item_start),
+            });
+        }
+
+        let mut items = items.into_iter();
+        let first = items.next().unwrap();
+        let class = items.fold(first, |lhs, rhs| infix(lhs, rhs, ParserExpr::Choice));
+        Ok(if negated {
+            any_except(class)
+        } else {
+            class.expr
+        })
+    }
+
+    /// Parses the escape sequence after a `\` in a regular expression.
+    fn regex_escape(&mut self) -> Result<Escape<'i>, Vec<Error<Rule>>> {
+        let start = self.pos - 1;
+        let c = match self.bump() {
+            Some(c) if c != '\n' => c,
+            _ => return Err(self.error_at(start, "unterminated escape sequence")),
+        };
+        let span = self.span(start);
+        let ident = |name: &str| ParserNode {
+            expr: ParserExpr::Ident(name.to_owned()),
+            span,
+        };
+        let choice = |strings: &[&str]| {
+            let mut nodes = strings.iter().map(|string| ParserNode {
+                expr: ParserExpr::Str((*string).to_owned()),
+                span,
+            });
+            let first = nodes.next().unwrap();
+            nodes.fold(first, |lhs, rhs| infix(lhs, rhs, ParserExpr::Choice))
+        };
+        let class = match c.to_ascii_lowercase() {
+            'd' => ident("ASCII_DIGIT"),
+            'w' => infix(
+                ident("ASCII_ALPHANUMERIC"),
+                choice(&["_"]),
+                ParserExpr::Choice,
+            ),
+            's' => choice(&[" ", "\t", "\n", "\r", "\x0b", "\x0c"]),
+            _ => {
+                return match c {
+                    'n' => Ok(Escape::Char('\n')),
+                    'r' => Ok(Escape::Char('\r')),
+                    't' => Ok(Escape::Char('\t')),
+                    'f' => Ok(Escape::Char('\x0c')),
+                    'v' => Ok(Escape::Char('\x0b')),
+                    '0' => Ok(Escape::Char('\0')),
+                    c if c.is_ascii_punctuation() || c == ' ' => Ok(Escape::Char(c)),
+                    _ => Err(self.error_at(start, "unsupported escape sequence")),
+                }
+            }
+        };
+        Ok(Escape::Class(if c.is_ascii_uppercase() {
+            any_except(class)
+        } else {
+            class.expr
+        }))
+    }
+
+    /// Parses a literal in `quote`s with pest's escape sequences.
+    fn quoted(&mut self, quote: char) -> Result<String, Vec<Error<Rule>>> {
+        let start = self.pos;
+        self.expect(&quote.to_string())?;
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error_at(start, "unterminated literal")),
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => break,
+                Some(_) => {}
+            }
+        }
+        unescape(&self.input[start + 1..self.pos - 1])
+            .ok_or_else(|| self.error_at(start, "incorrect escape sequence"))
+    }
+
+    fn identifier(&mut self) -> Result<Span<'i>, Vec<Error<Rule>>> {
+        let start = self.pos;
+        if !self.peek().map_or(false, is_identifier_start) {
+            return Err(self.error("expected an identifier"));
+        }
+        while self
+            .peek()
+            .map_or(false, |c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.bump();
+        }
+        Ok(self.span(start))
+    }
+
+    /// Skips spaces and comments up to the end of the line.
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') | Some('\r') => {
+                    self.bump();
+                }
+                Some('/') if self.rest().starts_with("//") => {
+                    self.pos += self.rest().find('\n').unwrap_or(self.rest().len());
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skips spaces, comments and line breaks.
+    fn skip_lines(&mut self) {
+        self.skip_blanks();
+        while self.eat("\n") {
+            self.skip_blanks();
+        }
+    }
+
+    /// Skips the space between terms. It only spans lines inside parentheses or
+    /// when the next line continues the rule with `|`.
+    fn skip_space(&mut self) {
+        self.skip_blanks();
+        let end_of_line = self.pos;
+        self.skip_lines();
+        if self.depth == 0 && self.peek() != Some('|') {
+            self.pos = end_of_line;
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), Vec<Error<Rule>>> {
+        self.skip_blanks();
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    fn expect(&mut self, string: &str) -> Result<(), Vec<Error<Rule>>> {
+        if self.eat(string) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", string)))
+        }
+    }
+
+    fn eat(&mut self, string: &str) -> bool {
+        let matched = self.rest().starts_with(string);
+        if matched {
+            self.pos += string.len();
+        }
+        matched
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'i str {
+        &self.input[self.pos..]
+    }
+
+    fn span(&self, start: usize) -> Span<'i> {
+        Span::new(self.input, start, self.pos).unwrap()
+    }
+
+    fn error(&self, message: &str) -> Vec<Error<Rule>> {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: &str) -> Vec<Error<Rule>> {
+        vec![Error::new_from_pos(
+            ErrorVariant::CustomError {
+                message: message.to_owned(),
+            },
+            Position::new(self.input, pos).unwrap(),
+        )]
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn infix<'i>(
+    lhs: ParserNode<'i>,
+    rhs: ParserNode<'i>,
+    expr: fn(Box<ParserNode<'i>>, Box<ParserNode<'i>>) -> ParserExpr<'i>,
+) -> ParserNode<'i> {
+    let span = lhs.span.start_pos().span(&rhs.span.end_pos());
+    ParserNode {
+        expr: expr(Box::new(lhs), Box::new(rhs)),
+        span,
+    }
+}
+
+/// Matches any character that `node` doesn't match, i.e. `!node ~ ANY`.
+fn any_except(node: ParserNode<'_>) -> ParserExpr<'_> {
+    let span = node.span;
+    ParserExpr::Seq(
+        Box::new(ParserNode {
+            expr: ParserExpr::NegPred(Box::new(node)),
+            span,
+        }),
+        Box::new(ParserNode {
+            expr: ParserExpr::Ident("ANY".to_owned()),
+            span,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{consume_rules, convert_rules, PestParser};
+    use crate::unwrap_or_report;
+    use pest::Parser;
+
+    fn assert_same_rules(bnf: &str, pest: &str) {
+        let pairs = PestParser::parse(Rule::grammar_rules, pest).unwrap();
+        assert_eq!(
+            unwrap_or_report(convert_rules(unwrap_or_report(parse(bnf)))),
+            unwrap_or_report(consume_rules(pairs))
+        );
+    }
+
+    #[test]
+    fn rules() {
+        assert_same_rules(
+            r#"
+// comment
+#start a
+a ::= b "c" | b{2} // comment
+    | ( b
+        c )?
+#atomic
+b ::= &"b" !(c c) c* c+ c{1,} c{,3} c{1,3} d
+c ::= "c"
+d ::= ()
+"#,
+            r#"
+start = { SOI ~ a ~ EOI }
+a = { b ~ "c" | b{2} | (b ~ c)? }
+b = @{ &"b" ~ !(c ~ c) ~ c* ~ c+ ~ c{1,} ~ c{,3} ~ c{1,3} ~ d }
+c = { "c" }
+d = { "" }
+"#,
+        );
+    }
+
+    #[test]
+    fn separators() {
+        assert_same_rules(
+            r#"
+a ::= "[" b%","* "]"
+b ::= "(" c%";"+ ")" | c%"\""...+ "!" | (c c)%"."...*
+c ::= "c"
+"#,
+            r#"
+a = { "[" ~ b %* "," ~ "]" }
+b = { "(" ~ c % ";" ~ ")" | c %... "\"" ~ "!" | (c ~ c) %...* "." }
+c = { "c" }
+"#,
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        assert_same_rules(
+            r#"
+'fn' -> fn_keyword
+/[a-z_][a-z0-9_]*/ -> ident
+/0x[0-9a-fA-F]+|\d{1,3}/ -> int : u64 { |s| s.parse() }
+/"[^"\\]*"/ -> string
+/\w\s.\S/ -> other
+"#,
+            r#"
+fn_keyword = @{ "fn" }
+ident = @{ ('a'..'z' | "_") ~ ('a'..'z' | '0'..'9' | "_")* }
+int = @{ "0x" ~ ('0'..'9' | 'a'..'f' | 'A'..'F')+ | ASCII_DIGIT{1,3} }
+string = @{ "\"" ~ (!("\"" | "\\") ~ ANY)* ~ "\"" }
+other = @{
+    (ASCII_ALPHANUMERIC | "_")
+  ~ (" " | "\t" | "\n" | "\r" | "\x0b" | "\x0c")
+  ~ (!"\n" ~ ANY)
+  ~ (!(" " | "\t" | "\n" | "\r" | "\x0b" | "\x0c") ~ ANY)
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn builtins() {
+        let rules = parse("#start a\na ::= ASCII_DIGIT ANY | a\n").unwrap();
+        assert_eq!(
+            crate::validator::validate_rules(&rules).unwrap(),
+            ["SOI", "EOI", "ASCII_DIGIT", "ANY"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "grammar error
+
+ --> 1:3
+  |
+1 | a = { \"a\" }
+  |   ^---
+  |
+  = expected `::=`")]
+    fn pest_rule() {
+        unwrap_or_report(parse("a = { \"a\" }\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "grammar error
+
+ --> 1:7
+  |
+1 | a ::= (b
+  |       ^---
+  |
+  = unclosed `(`")]
+    fn unclosed_paren() {
+        unwrap_or_report(parse("a ::= (b\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "grammar error
+
+ --> 1:4
+  |
+1 | /a*?/ -> a
+  |    ^---
+  |
+  = lazy repetitions are not supported")]
+    fn lazy_repetition() {
+        unwrap_or_report(parse("/a*?/ -> a\n"));
+    }
+}
@@ -27,6 +27,7 @@ use pest::error::Error;
 use pest::unicode::unicode_property_names;
 
 pub mod ast;
+pub mod bnf;
 pub mod optimizer;
 pub mod parser;
 pub mod validator;
@@ -70,6 +71,17 @@ pub fn parse_and_optimize(
     Ok((defaults, optimizer::optimize(ast)))
 }
 
+/// Parses, validates, processes and optimizes the provided BNF grammar.
+pub fn parse_and_optimize_bnf(
+    grammar: &str,
+) -> Result<UsedBuiltinAndOptimized<'_>, Vec<Error<parser::Rule>>> {
+    let rules = bnf::parse(grammar)?;
+    let defaults = validator::validate_rules(&rules)?;
+    let ast = parser::convert_rules(rules)?;
+
+    Ok((defaults, optimizer::optimize(ast)))
+}
+
 #[doc(hidden)]
 #[deprecated(note = "use `pest::unicode::unicode_property_names` instead")]
 pub static UNICODE_PROPERTY_NAMES: Lazy<Vec<&str>> =
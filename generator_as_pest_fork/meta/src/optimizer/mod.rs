@@ -69,6 +69,11 @@ fn rule_to_optimized_rule(rule: Rule) -> OptimizedRule {
             }
             Expr::Opt(expr) => OptimizedExpr::Opt(Box::new(to_optimized(*expr))),
             Expr::Rep(expr) => OptimizedExpr::Rep(Box::new(to_optimized(*expr))),
+            Expr::Separated(expr, separator, separation) => OptimizedExpr::Separated(
+                Box::new(to_optimized(*expr)),
+                Box::new(to_optimized(*separator)),
+                separation,
+            ),
             Expr::Skip(strings) => OptimizedExpr::Skip(strings),
             Expr::Push(expr) => OptimizedExpr::Push(Box::new(to_optimized(*expr))),
             Expr::NodeTag(expr, tag) => OptimizedExpr::NodeTag(Box::new(to_optimized(*expr)), tag),
@@ -88,6 +93,29 @@ fn rule_to_optimized_rule(rule: Rule) -> OptimizedRule {
     }
 }
 
+/// Expands the separated list `expr % separator` into the equivalent
+/// `expr ~ (separator ~ expr)*`, followed by `separator?` if it allows a trailing
+/// separator and optional as a whole if it allows being empty.
+pub fn expand_separated(
+    expr: OptimizedExpr,
+    separator: OptimizedExpr,
+    separation: Separation,
+) -> OptimizedExpr {
+    let rest = OptimizedExpr::Rep(Box::new(OptimizedExpr::Seq(
+        Box::new(separator.clone()),
+        Box::new(expr.clone()),
+    )));
+    let mut list = OptimizedExpr::Seq(Box::new(expr), Box::new(rest));
+    if separation.allow_trailing {
+        let trailing = OptimizedExpr::Opt(Box::new(separator));
+        list = OptimizedExpr::Seq(Box::new(list), Box::new(trailing));
+    }
+    if separation.allow_empty {
+        list = OptimizedExpr::Opt(Box::new(list));
+    }
+    list
+}
+
 fn to_hash_map(rules: &[OptimizedRule]) -> HashMap<String, OptimizedExpr> {
     rules
         .iter()
@@ -138,6 +166,8 @@ pub enum OptimizedExpr {
     Opt(Box<OptimizedExpr>),
     /// Matches an expression zero or more times, e.g. `e*`
     Rep(Box<OptimizedExpr>),
+    /// Matches an expression one or more times, separated by another, e.g. `e % ","`
+    Separated(Box<OptimizedExpr>, Box<OptimizedExpr>, Separation),
     /// Continues to match expressions until one of the strings in the `Vec` is found
     Skip(Vec<String>),
     /// Matches an expression and pushes it to the stack, e.g. `push(e)`
@@ -190,6 +220,11 @@ impl OptimizedExpr {
                     let mapped = Box::new(map_internal(*expr, f));
                     OptimizedExpr::Rep(mapped)
                 }
+                OptimizedExpr::Separated(expr, separator, separation) => {
+                    let mapped = Box::new(map_internal(*expr, f));
+                    let mapped_separator = Box::new(map_internal(*separator, f));
+                    OptimizedExpr::Separated(mapped, mapped_separator, separation)
+                }
                 OptimizedExpr::Opt(expr) => {
                     let mapped = Box::new(map_internal(*expr, f));
                     OptimizedExpr::Opt(mapped)
@@ -237,6 +272,11 @@ impl OptimizedExpr {
                     let mapped = Box::new(map_internal(*expr, f));
                     OptimizedExpr::Rep(mapped)
                 }
+                OptimizedExpr::Separated(expr, separator, separation) => {
+                    let mapped = Box::new(map_internal(*expr, f));
+                    let mapped_separator = Box::new(map_internal(*separator, f));
+                    OptimizedExpr::Separated(mapped, mapped_separator, separation)
+                }
                 OptimizedExpr::Opt(expr) => {
                     let mapped = Box::new(map_internal(*expr, f));
                     OptimizedExpr::Opt(mapped)
@@ -285,6 +325,10 @@ impl OptimizedExprTopDownIterator {
                 self.right_branches.push(*rhs);
                 self.next = Some(*lhs);
             }
+            OptimizedExpr::Separated(expr, separator, _separation) => {
+                self.right_branches.push(*separator);
+                self.next = Some(*expr);
+            }
             OptimizedExpr::PosPred(expr)
             | OptimizedExpr::NegPred(expr)
             | OptimizedExpr::Rep(expr)
@@ -15,10 +15,32 @@ pub fn restore_on_err(
     rules: &HashMap<String, OptimizedExpr>,
 ) -> OptimizedRule {
     let OptimizedRule { name, ty, expr } = rule;
-    let expr = expr.map_bottom_up(|expr| wrap_branching_exprs(expr, rules));
+    let expr = expr
+        .map_top_down(|expr| expand_stateful_lists(expr, rules))
+        .map_bottom_up(|expr| wrap_branching_exprs(expr, rules));
     OptimizedRule { name, ty, expr }
 }
 
+/// Separated lists that modify the stack are expanded, so that their repetitions
+/// get restored like any other.
+fn expand_stateful_lists(
+    expr: OptimizedExpr,
+    rules: &HashMap<String, OptimizedExpr>,
+) -> OptimizedExpr {
+    match expr {
+        OptimizedExpr::Separated(expr, separator, separation) => {
+            if child_modifies_state(&expr, rules, &mut HashMap::new())
+                || child_modifies_state(&separator, rules, &mut HashMap::new())
+            {
+                expand_separated(*expr, *separator, separation)
+            } else {
+                OptimizedExpr::Separated(expr, separator, separation)
+            }
+        }
+        _ => expr,
+    }
+}
+
 fn wrap_branching_exprs(
     expr: OptimizedExpr,
     rules: &HashMap<String, OptimizedExpr>,
@@ -150,4 +172,31 @@ mod tests {
             restored
         );
     }
+
+    #[test]
+    fn restore_expands_separated_with_child_stack_ops() {
+        let rules = vec![OptimizedRule {
+            name: "rule".to_owned(),
+            ty: RuleType::Normal,
+            expr: Separated(
+                Box::new(Push(Box::new(Str("a".to_string())))),
+                Box::new(Str(",".to_string())),
+                Separation::default(),
+            ),
+        }];
+
+        let restored = OptimizedRule {
+            name: "rule".to_owned(),
+            ty: RuleType::Normal,
+            expr: box_tree!(Seq(
+                Push(Str("a".to_string())),
+                Rep(RestoreOnErr(Seq(Str(",".to_string()), Push(Str("a".to_string())))))
+            )),
+        };
+
+        assert_eq!(
+            restore_on_err(rules[0].clone(), &to_hash_map(&rules)),
+            restored
+        );
+    }
 }
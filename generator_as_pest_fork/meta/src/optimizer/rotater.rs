@@ -25,7 +25,9 @@ pub fn rotate(rule: Rule) -> Rule {
                 let lhs = *lhs;
                 match lhs {
                     Expr::Choice(ll, lr, (wll, wlr)) => {
-                        let wchanged = wrhs * (wll + wlr) / (wlhs + wrhs);
+                        // `wlhs` weighs the left choice against `rhs`, and `wll + wlr` weighs
+                        // the same choice from inside, so `rhs` is scaled to the inner units.
+                        let wchanged = (wrhs * (wll + wlr)).checked_div(wlhs).unwrap_or(wrhs);
                         rotate_internal(Expr::Choice(ll, Box::new(Expr::Choice(lr, rhs, (wlr, wchanged))), (wll, wlr + wchanged)))
                     }
                     lhs => Expr::Choice(Box::new(lhs), rhs, weights),
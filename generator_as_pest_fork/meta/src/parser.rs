@@ -17,7 +17,7 @@ use pest::iterators::{Pair, Pairs};
 use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest::{Parser, Position, Span};
 
-use crate::ast::{Expr, Rule as AstRule, RuleType};
+use crate::ast::{Expr, Rule as AstRule, RuleType, Separation};
 use crate::validator;
 
 /// TODO: fix the generator to at least add explicit lifetimes
@@ -103,6 +103,10 @@ impl<'i> ParserNode<'i> {
                 ParserExpr::RepMinMax(node, ..) => {
                     filter_internal(*node, f, result);
                 }
+                ParserExpr::Separated(node, separator, _) => {
+                    filter_internal(*node, f, result);
+                    filter_internal(*separator, f, result);
+                }
                 ParserExpr::Opt(node) => {
                     filter_internal(*node, f, result);
                 }
@@ -120,8 +124,39 @@ impl<'i> ParserNode<'i> {
         result
     }
 
+    /// The weight of a choice branch: its `W:n` marker, or else the weight that
+    /// the nested choices of the branch imply.
+    fn branch_weight(&self) -> Option<u64> {
+        self.find_weight().or_else(|| self.implied_weight())
+    }
+
+    fn implied_weight(&self) -> Option<u64> {
+        match self.expr {
+            ParserExpr::Seq(ref lhs, ref rhs) => {
+                match (lhs.implied_weight(), rhs.implied_weight()) {
+                    (Some(w0), Some(w1)) => {
+                        Some(w0 * w1)
+                    }
+                    (Some(w), None) | (None, Some(w)) => {
+                        Some(w)
+                    }
+                    _ => None
+                }
+            }
+            ParserExpr::Choice(ref lhs, ref rhs) => {
+                let w0 = lhs.branch_weight().unwrap_or(1);
+                let w1 = rhs.branch_weight().unwrap_or(1);
+                Some(w0 + w1)
+            }
+            _ => None
+        }
+    }
+
     fn find_weight(&self) -> Option<u64> {
         match self.expr {
+            ParserExpr::Weight(weight) => {
+                Some(weight)
+            }
             ParserExpr::PosPred(..) => {
                 None
             }
@@ -141,10 +176,8 @@ impl<'i> ParserNode<'i> {
                     _ => None
                 }
             }
-            ParserExpr::Choice(ref lhs, ref rhs) => {
-                let w0 = lhs.find_weight().unwrap_or(1);
-                let w1 = rhs.find_weight().unwrap_or(1);
-                Some(w0 + w1)
+            ParserExpr::Choice(..) => {
+                None
             }
             ParserExpr::Rep(..) => {
                 None
@@ -164,6 +197,9 @@ impl<'i> ParserNode<'i> {
             ParserExpr::RepMinMax(..) => {
                 None
             }
+            ParserExpr::Separated(..) => {
+                None
+            }
             ParserExpr::Opt(..) => {
                 None
             }
@@ -210,6 +246,8 @@ pub enum ParserExpr<'i> {
     RepMax(Box<ParserNode<'i>>, u32),
     /// Matches an expression a number of times within a range, e.g. `e{m, n}`
     RepMinMax(Box<ParserNode<'i>>, u32, u32),
+    /// Matches an expression one or more times, separated by another, e.g. `e % ","`
+    Separated(Box<ParserNode<'i>>, Box<ParserNode<'i>>, Separation),
     /// Matches an expression and pushes it to the stack, e.g. `push(e)`
     Push(Box<ParserNode<'i>>),
     /// Matches an expression and assigns a label to it, e.g. #label = exp
@@ -238,8 +276,8 @@ fn convert_node(node: ParserNode<'_>) -> Expr {
             Box::new(convert_node(*node2)),
         ),
         ParserExpr::Choice(node1, node2) => {
-            let w0 = node1.find_weight().unwrap_or(1);
-            let w1 = node2.find_weight().unwrap_or(1);
+            let w0 = node1.branch_weight().unwrap_or(1);
+            let w1 = node2.branch_weight().unwrap_or(1);
             Expr::Choice(
                 Box::new(convert_node(*node1)),
                 Box::new(convert_node(*node2)),
@@ -255,6 +293,11 @@ fn convert_node(node: ParserNode<'_>) -> Expr {
         ParserExpr::RepMinMax(node, min, max) => {
             Expr::RepMinMax(Box::new(convert_node(*node)), min, max)
         }
+        ParserExpr::Separated(node, separator, separation) => Expr::Separated(
+            Box::new(convert_node(*node)),
+            Box::new(convert_node(*separator)),
+            separation,
+        ),
         ParserExpr::Push(node) => Expr::Push(Box::new(convert_node(*node))),
         ParserExpr::NodeTag(node, tag) => Expr::NodeTag(Box::new(convert_node(*node)), tag),
         ParserExpr::Weight(_) => {
@@ -266,6 +309,11 @@ fn convert_node(node: ParserNode<'_>) -> Expr {
 /// Converts a parser's result (`Pairs`) to an AST
 pub fn consume_rules(pairs: Pairs<'_, Rule>) -> Result<Vec<AstRule>, Vec<Error<Rule>>> {
     let rules = consume_rules_with_spans(pairs)?;
+    convert_rules(rules)
+}
+
+/// Validates rules with spans, e.g. from [`crate::bnf::parse`], and converts them to an AST
+pub fn convert_rules(rules: Vec<ParserRule<'_>>) -> Result<Vec<AstRule>, Vec<Error<Rule>>> {
     let errors = validator::validate_ast(&rules);
     if errors.is_empty() {
         Ok(rules.into_iter().map(convert_rule).collect())
@@ -295,6 +343,9 @@ pub fn rename_meta_rule(rule: &Rule) -> String {
         Rule::negative_predicate_operator => "`!`".to_owned(),
         Rule::sequence_operator => "`&`".to_owned(),
         Rule::choice_operator => "`|`".to_owned(),
+        Rule::separator_operator => "`%`".to_owned(),
+        Rule::trailing_modifier => "`...`".to_owned(),
+        Rule::empty_modifier => "`*`".to_owned(),
         Rule::optional_operator => "`?`".to_owned(),
         Rule::repeat_operator => "`*`".to_owned(),
         Rule::repeat_once_operator => "`+`".to_owned(),
@@ -315,7 +366,8 @@ fn consume_rules_with_spans(
 ) -> Result<Vec<ParserRule<'_>>, Vec<Error<Rule>>> {
     let pratt = PrattParser::new()
         .op(Op::infix(Rule::choice_operator, Assoc::Left))
-        .op(Op::infix(Rule::sequence_operator, Assoc::Left));
+        .op(Op::infix(Rule::sequence_operator, Assoc::Left))
+        .op(Op::infix(Rule::separator_operator, Assoc::Left));
 
     pairs
         .filter(|pair| pair.as_rule() == Rule::grammar_rule)
@@ -748,13 +800,34 @@ fn consume_expr<'i>(
                 span: start.span(&end),
             })
         }
+        Rule::separator_operator => {
+            let lhs = lhs?;
+            let rhs = rhs?;
+
+            let start = lhs.span.start_pos();
+            let end = rhs.span.end_pos();
+
+            let mut separation = Separation::default();
+            for modifier in op.into_inner() {
+                match modifier.as_rule() {
+                    Rule::trailing_modifier => separation.allow_trailing = true,
+                    Rule::empty_modifier => separation.allow_empty = true,
+                    _ => unreachable!(),
+                }
+            }
+
+            Ok(ParserNode {
+                expr: ParserExpr::Separated(Box::new(lhs), Box::new(rhs), separation),
+                span: start.span(&end),
+            })
+        }
         _ => unreachable!("infix"),
     };
 
     pratt.map_primary(term).map_infix(infix).parse(pairs)
 }
 
-fn unescape(string: &str) -> Option<String> {
+pub(crate) fn unescape(string: &str) -> Option<String> {
     let mut result = String::new();
     let mut chars = string.chars();
 
@@ -1004,6 +1077,29 @@ mod tests {
         };
     }
 
+    #[test]
+    fn separator_operator() {
+        parses_to! {
+            parser: PestParser,
+            input: "a %...* b",
+            rule: Rule::expression,
+            tokens: [
+                expression(0, 9, [
+                    term(0, 2, [
+                        identifier(0, 1)
+                    ]),
+                    separator_operator(2, 7, [
+                        trailing_modifier(3, 6),
+                        empty_modifier(6, 7)
+                    ]),
+                    term(8, 9, [
+                        identifier(8, 9)
+                    ])
+                ])
+            ]
+        };
+    }
+
     #[test]
     fn push() {
         parses_to! {
@@ -1339,13 +1435,14 @@ mod tests {
     fn wrong_op() {
         fails_with! {
             parser: PestParser,
-            input: "a = { b % }",
+            input: "a = { b @ }",
             rule: Rule::grammar_rules,
             positives: vec![
                 Rule::opening_brace,
                 Rule::closing_brace,
                 Rule::sequence_operator,
                 Rule::choice_operator,
+                Rule::separator_operator,
                 Rule::optional_operator,
                 Rule::repeat_operator,
                 Rule::repeat_once_operator
@@ -1366,6 +1463,7 @@ mod tests {
                 Rule::closing_paren,
                 Rule::sequence_operator,
                 Rule::choice_operator,
+                Rule::separator_operator,
                 Rule::optional_operator,
                 Rule::repeat_operator,
                 Rule::repeat_once_operator
@@ -1456,6 +1554,7 @@ mod tests {
                 Rule::closing_brace,
                 Rule::sequence_operator,
                 Rule::choice_operator,
+                Rule::separator_operator,
                 Rule::optional_operator,
                 Rule::repeat_operator,
                 Rule::repeat_once_operator
@@ -1530,6 +1629,7 @@ mod tests {
                 Rule::closing_brace,
                 Rule::sequence_operator,
                 Rule::choice_operator,
+                Rule::separator_operator,
                 Rule::optional_operator,
                 Rule::repeat_operator,
                 Rule::repeat_once_operator
@@ -1604,6 +1704,121 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ast_weights() {
+        let input = r#"rule = { W:9 ~ "a" | "b" ~ W:3 | "c" }"#;
+
+        let pairs = PestParser::parse(Rule::grammar_rules, input).unwrap();
+        let ast = consume_rules_with_spans(pairs).unwrap();
+        let ast: Vec<_> = ast.into_iter().map(convert_rule).collect();
+
+        assert_eq!(
+            ast,
+            vec![AstRule {
+                name: "rule".to_owned(),
+                ty: RuleType::Normal,
+                expr: Expr::Choice(
+                    Box::new(Expr::Choice(
+                        Box::new(Expr::Seq(
+                            Box::new(Expr::Weight),
+                            Box::new(Expr::Str("a".to_owned())),
+                        )),
+                        Box::new(Expr::Seq(
+                            Box::new(Expr::Str("b".to_owned())),
+                            Box::new(Expr::Weight),
+                        )),
+                        (9, 3)
+                    )),
+                    Box::new(Expr::Str("c".to_owned())),
+                    (12, 1)
+                ),
+            }],
+        );
+    }
+
+    #[test]
+    fn ast_weight_with_nested_choice() {
+        let input = r#"rule = { W:3 ~ ("a" | "b") | "c" ~ ("d" | "e") ~ ("f" | "g") | "h" }"#;
+
+        let pairs = PestParser::parse(Rule::grammar_rules, input).unwrap();
+        let ast = consume_rules_with_spans(pairs).unwrap();
+        let ast: Vec<_> = ast.into_iter().map(convert_rule).collect();
+
+        let choice = |lhs: &str, rhs: &str| {
+            Box::new(Expr::Choice(
+                Box::new(Expr::Str(lhs.to_owned())),
+                Box::new(Expr::Str(rhs.to_owned())),
+                (1, 1),
+            ))
+        };
+        assert_eq!(
+            ast,
+            vec![AstRule {
+                name: "rule".to_owned(),
+                ty: RuleType::Normal,
+                expr: Expr::Choice(
+                    Box::new(Expr::Choice(
+                        Box::new(Expr::Seq(Box::new(Expr::Weight), choice("a", "b"))),
+                        Box::new(Expr::Seq(
+                            Box::new(Expr::Seq(Box::new(Expr::Str("c".to_owned())), choice("d", "e"))),
+                            choice("f", "g"),
+                        )),
+                        (3, 4)
+                    )),
+                    Box::new(Expr::Str("h".to_owned())),
+                    (7, 1)
+                ),
+            }],
+        );
+    }
+
+    #[test]
+    fn ast_separated() {
+        let input = r#"rule = { "(" ~ a % "," ~ ")" | b | c %...* (";" | ".") }"#;
+
+        let pairs = PestParser::parse(Rule::grammar_rules, input).unwrap();
+        let ast = consume_rules_with_spans(pairs).unwrap();
+        let ast: Vec<_> = ast.into_iter().map(convert_rule).collect();
+
+        assert_eq!(
+            ast,
+            vec![AstRule {
+                name: "rule".to_owned(),
+                ty: RuleType::Normal,
+                expr: Expr::Choice(
+                    Box::new(Expr::Choice(
+                        Box::new(Expr::Seq(
+                            Box::new(Expr::Seq(
+                                Box::new(Expr::Str("(".to_owned())),
+                                Box::new(Expr::Separated(
+                                    Box::new(Expr::Ident("a".to_owned())),
+                                    Box::new(Expr::Str(",".to_owned())),
+                                    Separation::default(),
+                                )),
+                            )),
+                            Box::new(Expr::Str(")".to_owned())),
+                        )),
+                        Box::new(Expr::Ident("b".to_owned())),
+                        (1, 1)
+                    )),
+                    Box::new(Expr::Separated(
+                        Box::new(Expr::Ident("c".to_owned())),
+                        Box::new(Expr::Choice(
+                            Box::new(Expr::Str(";".to_owned())),
+                            Box::new(Expr::Str(".".to_owned())),
+                            (1, 1)
+                        )),
+                        Separation {
+                            allow_trailing: true,
+                            allow_empty: true,
+                        },
+                    )),
+                    (2, 1)
+                ),
+            }],
+        );
+    }
+
     #[test]
     #[should_panic(expected = "grammar error
 
@@ -116,6 +116,68 @@ pub fn validate_pairs(pairs: Pairs<'_, Rule>) -> Result<Vec<&str>, Vec<Error<Rul
     Ok(defaults.cloned().collect())
 }
 
+/// It checks rules with spans, e.g. from [`crate::bnf::parse`], for the same mistakes
+/// as [`validate_pairs`] and returns the names of used builtin rules likewise.
+pub fn validate_rules(rules: &[ParserRule<'_>]) -> Result<Vec<&'static str>, Vec<Error<Rule>>> {
+    let mut errors = vec![];
+    let mut definitions = HashSet::new();
+
+    for rule in rules {
+        if PEST_KEYWORDS.contains(rule.name.as_str()) {
+            errors.push(Error::new_from_span(
+                ErrorVariant::CustomError {
+                    message: format!("{} is a pest keyword", rule.name),
+                },
+                rule.span,
+            ))
+        }
+
+        if !definitions.insert(rule.name.as_str()) {
+            errors.push(Error::new_from_span(
+                ErrorVariant::CustomError {
+                    message: format!("rule {} already defined", rule.name),
+                },
+                rule.span,
+            ))
+        }
+    }
+
+    let mut defaults = vec![];
+
+    for rule in rules {
+        let called_rules = rule
+            .node
+            .clone()
+            .filter_map_top_down(|node| match node.expr {
+                ParserExpr::Ident(name) => Some((name, node.span)),
+                _ => None,
+            });
+
+        for (name, span) in called_rules {
+            if definitions.contains(name.as_str()) {
+                continue;
+            }
+
+            match BUILTINS.get(name.as_str()) {
+                Some(builtin) if !defaults.contains(builtin) => defaults.push(*builtin),
+                Some(_) => (),
+                None => errors.push(Error::new_from_span(
+                    ErrorVariant::CustomError {
+                        message: format!("rule {} is undefined", name),
+                    },
+                    span,
+                )),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(defaults)
+    } else {
+        Err(errors)
+    }
+}
+
 /// Validates that the given `definitions` do not contain any Rust keywords.
 #[allow(clippy::ptr_arg)]
 #[deprecated = "Rust keywords are no longer restricted from the pest grammar"]
@@ -325,6 +387,9 @@ fn is_non_progressing<'i>(
         | ParserExpr::RepMinMax(ref inner, min, _) => {
             min == 0 || is_non_progressing(&inner.expr, rules, trace)
         }
+        ParserExpr::Separated(ref inner, _, separation) => {
+            separation.allow_empty || is_non_progressing(&inner.expr, rules, trace)
+        }
         ParserExpr::Push(ref inner) => is_non_progressing(&inner.expr, rules, trace),
         ParserExpr::RepOnce(ref inner) | ParserExpr::NodeTag(ref inner, _) => {
             is_non_progressing(&inner.expr, rules, trace)
@@ -418,6 +483,9 @@ fn is_non_failing<'i>(
         //     @{EOI ~ ANY | ANY ~ SOI | &("A") ~ &("B") | 'z'..'a'}
         ParserExpr::NegPred(_) => false,
         ParserExpr::RepOnce(ref inner) => is_non_failing(&inner.expr, rules, trace),
+        ParserExpr::Separated(ref inner, _, separation) => {
+            separation.allow_empty || is_non_failing(&inner.expr, rules, trace)
+        }
         ParserExpr::Push(ref inner)
         | ParserExpr::NodeTag(ref inner, _)
         | ParserExpr::PosPred(ref inner) => is_non_failing(&inner.expr, rules, trace),
@@ -460,6 +528,34 @@ fn validate_repetition<'a, 'i: 'a>(rules: &'a [ParserRule<'i>]) -> Vec<Error<Rul
                         None
                     }
                 }
+                // `e % s` repeats `s ~ e`
+                ParserExpr::Separated(ref other, ref separator, _) => {
+                    if is_non_failing(&separator.expr, &map, &mut vec![])
+                        && is_non_failing(&other.expr, &map, &mut vec![])
+                    {
+                        Some(Error::new_from_span(
+                            ErrorVariant::CustomError {
+                                message:
+                                    "separated list cannot fail and will repeat infinitely"
+                                        .to_owned()
+                            },
+                            node.span
+                        ))
+                    } else if is_non_progressing(&separator.expr, &map, &mut vec![])
+                        && is_non_progressing(&other.expr, &map, &mut vec![])
+                    {
+                        Some(Error::new_from_span(
+                            ErrorVariant::CustomError {
+                                message:
+                                    "separated list is non-progressing and will repeat infinitely"
+                                        .to_owned()
+                            },
+                            node.span
+                        ))
+                    } else {
+                        None
+                    }
+                }
                 _ => None
             });
 
@@ -604,6 +700,7 @@ fn left_recursion<'a, 'i: 'a>(rules: HashMap<String, &'a ParserNode<'i>>) -> Vec
             }
             ParserExpr::Rep(ref node) => check_expr(node, rules, trace),
             ParserExpr::RepOnce(ref node) => check_expr(node, rules, trace),
+            ParserExpr::Separated(ref node, ..) => check_expr(node, rules, trace),
             ParserExpr::Opt(ref node) => check_expr(node, rules, trace),
             ParserExpr::PosPred(ref node) => check_expr(node, rules, trace),
             ParserExpr::NegPred(ref node) => check_expr(node, rules, trace),
@@ -1627,6 +1724,22 @@ mod tests {
     #[test]
     #[should_panic(expected = "grammar error
 
+ --> 1:7
+  |
+1 | a = { \"a\"? % \"b\"? }
+  |       ^---------^
+  |
+  = separated list cannot fail and will repeat infinitely")]
+    fn non_failing_separated() {
+        let input = "a = { \"a\"? % \"b\"? }";
+        unwrap_or_report(consume_rules(
+            PestParser::parse(Rule::grammar_rules, input).unwrap(),
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "grammar error
+
  --> 1:7
   |
 1 | a = { (\"\" ~ &\"a\" ~ !\"a\" ~ (SOI | EOI))* }
@@ -0,0 +1,119 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Sampling of characters, including the Unicode property rules.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use cfg::generation::weighted::random::GenRange;
+
+use crate::unicode;
+
+/// All Unicode scalar values.
+const UNICODE: &[(char, char)] = &[('\0', '\u{d7ff}'), ('\u{e000}', char::MAX)];
+
+/// Picks one of the characters in `ranges`, all with the same probability.
+pub fn pick_char<R: GenRange>(ranges: &[(char, char)], rng: &mut R) -> char {
+    let len = |&(first, last): &(char, char)| last as u32 - first as u32 + 1;
+    let total: u32 = ranges.iter().map(len).sum();
+    let mut offset = pick_below(total, rng);
+    for range in ranges {
+        if offset < len(range) {
+            return char::from_u32(range.0 as u32 + offset).expect("incorrect char");
+        }
+        offset -= len(range);
+    }
+    ranges[ranges.len() - 1].1
+}
+
+/// Picks any character for `ANY`. Three in four characters are ASCII, so that the
+/// generated text does not drown in unassigned code points.
+pub fn pick_any<R: GenRange>(rng: &mut R) -> char {
+    if rng.gen(1.0) < 0.75 {
+        pick_char(&[('\0', '\x7f')], rng)
+    } else {
+        pick_char(UNICODE, rng)
+    }
+}
+
+/// Returns the characters of a Unicode property rule such as `XID_START`, as
+/// sorted ranges.
+pub fn property_ranges(name: &str) -> Option<Vec<(char, char)>> {
+    let property = unicode::by_name(name)?;
+    let mut ranges: Vec<(char, char)> = vec![];
+    for &(first, last) in UNICODE {
+        for ch in (first..=last).filter(|&ch| property(ch)) {
+            match ranges.last_mut() {
+                Some((_, last)) if *last as u32 + 1 == ch as u32 => *last = ch,
+                _ => ranges.push((ch, ch)),
+            }
+        }
+    }
+    Some(ranges)
+}
+
+/// Picks a number below `total`. Reads a single random byte for up to 256 numbers,
+/// and as many as needed to reach all of them otherwise.
+fn pick_below<R: GenRange>(total: u32, rng: &mut R) -> u32 {
+    if total <= 256 {
+        return rng.gen(f64::from(total - 1)) as u32;
+    }
+    let mut picked = 0u64;
+    let mut span = 1u64;
+    while span < u64::from(total) {
+        picked = picked * 256 + rng.gen(255.0) as u64;
+        span *= 256;
+    }
+    (picked % u64::from(total)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cfg::generation::weighted::random::ByteSource;
+
+    #[test]
+    fn small_ranges() {
+        let ranges = [('a', 'c'), ('x', 'x')];
+        let pick = |byte| pick_char(&ranges, &mut ByteSource::new(vec![byte].into_iter()));
+        assert_eq!(pick(0), 'a');
+        assert_eq!(pick(128), 'b');
+        assert_eq!(pick(255), 'x');
+    }
+
+    #[test]
+    fn large_ranges() {
+        let mut rng = ByteSource::new(vec![0x00, 0x01, 0x00].into_iter());
+        assert_eq!(pick_char(UNICODE, &mut rng), '\u{100}');
+        let mut rng = ByteSource::new(vec![0x0f, 0xff, 0xff].into_iter());
+        assert_eq!(pick_char(UNICODE, &mut rng), '\u{1007ff}');
+    }
+
+    #[test]
+    fn any() {
+        let pick = |bytes: &[u8]| pick_any(&mut ByteSource::new(bytes.iter().copied()));
+        assert!(pick(&[0, 0x61]).is_ascii());
+        assert_eq!(pick(&[255, 0, 0, 0xe9]), '\u{e9}');
+    }
+
+    #[test]
+    fn properties() {
+        let ranges = property_ranges("XID_START").unwrap();
+        let contains = |ch| {
+            ranges
+                .iter()
+                .any(|&(first, last)| first <= ch && ch <= last)
+        };
+        assert!(contains('a'));
+        assert!(contains('é'));
+        assert!(!contains('1'));
+        assert!(property_ranges("NOT_A_PROPERTY").is_none());
+    }
+}
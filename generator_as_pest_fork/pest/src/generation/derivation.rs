@@ -0,0 +1,179 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A generated string together with the rules that derived it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Derivation<R> {
+    /// The generated text.
+    pub string: String,
+    /// The outermost rules of the derivation, in order. Like `Pairs`, this is usually
+    /// a single tree for the start rule.
+    pub trees: Vec<DerivationTree<R>>,
+    /// The branches of choices that generation picked, in order, by their position in
+    /// the parser's list of alternatives.
+    pub alternatives: Vec<usize>,
+    /// How many times a generated continuation was rejected by a lookahead.
+    pub rejections: u64,
+}
+
+/// A rule in a derivation, with the byte span of the text it generated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DerivationTree<R> {
+    /// The rule.
+    pub rule: R,
+    /// The byte offset where the rule's text starts.
+    pub start: usize,
+    /// The byte offset where the rule's text ends.
+    pub end: usize,
+    /// The rules derived directly inside this one, in order.
+    pub children: Vec<DerivationTree<R>>,
+}
+
+impl<R: Copy> Derivation<R> {
+    /// Returns the rules whose text contains the byte offset `pos`, outermost first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pest::generation::{Derivation, DerivationTree};
+    /// let derivation = Derivation {
+    ///     string: "ab".to_owned(),
+    ///     trees: vec![DerivationTree {
+    ///         rule: "pair",
+    ///         start: 0,
+    ///         end: 2,
+    ///         children: vec![DerivationTree { rule: "b", start: 1, end: 2, children: vec![] }],
+    ///     }],
+    ///     alternatives: vec![],
+    ///     rejections: 0,
+    /// };
+    ///
+    /// assert_eq!(derivation.rules_at(1), vec!["pair", "b"]);
+    /// ```
+    pub fn rules_at(&self, pos: usize) -> Vec<R> {
+        let mut result = vec![];
+        let mut trees = &self.trees[..];
+        while let Some(tree) = trees
+            .iter()
+            .find(|tree| tree.start <= pos && pos < tree.end)
+        {
+            result.push(tree.rule);
+            trees = &tree.children[..];
+        }
+        result
+    }
+
+    /// Maps the rules of the derivation with `f`.
+    pub fn map<S, F: FnMut(R) -> S>(self, mut f: F) -> Derivation<S> {
+        Derivation {
+            string: self.string,
+            trees: self
+                .trees
+                .into_iter()
+                .map(|tree| tree.map(&mut f))
+                .collect(),
+            alternatives: self.alternatives,
+            rejections: self.rejections,
+        }
+    }
+}
+
+impl<R> DerivationTree<R> {
+    /// Returns the text that this rule generated.
+    pub fn as_str<'i>(&self, string: &'i str) -> &'i str {
+        &string[self.start..self.end]
+    }
+
+    fn map<S, F: FnMut(R) -> S>(self, f: &mut F) -> DerivationTree<S> {
+        DerivationTree {
+            rule: f(self.rule),
+            start: self.start,
+            end: self.end,
+            children: self.children.into_iter().map(|tree| tree.map(f)).collect(),
+        }
+    }
+}
+
+/// A rule entered or left during generation, at a character offset.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Event<R> {
+    Open(R, usize),
+    Close(usize),
+}
+
+/// Builds the derivation trees out of balanced events. `offsets` maps character
+/// offsets to byte offsets.
+pub(crate) fn build<R: Copy>(events: &[Event<R>], offsets: &[usize]) -> Vec<DerivationTree<R>> {
+    let mut open: Vec<DerivationTree<R>> = vec![];
+    let mut trees = vec![];
+    for &event in events {
+        match event {
+            Event::Open(rule, pos) => open.push(DerivationTree {
+                rule,
+                start: offsets[pos],
+                end: offsets[pos],
+                children: vec![],
+            }),
+            Event::Close(pos) => {
+                let mut tree = open.pop().expect("unbalanced derivation events");
+                tree.end = offsets[pos];
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(tree),
+                    None => trees.push(tree),
+                }
+            }
+        }
+    }
+    trees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let events = [
+            Event::Open('a', 0),
+            Event::Open('b', 0),
+            Event::Close(1),
+            Event::Open('c', 1),
+            Event::Close(1),
+            Event::Close(2),
+        ];
+        let trees = build(&events, &[0, 2, 3]);
+
+        assert_eq!(
+            trees,
+            vec![DerivationTree {
+                rule: 'a',
+                start: 0,
+                end: 3,
+                children: vec![
+                    DerivationTree {
+                        rule: 'b',
+                        start: 0,
+                        end: 2,
+                        children: vec![],
+                    },
+                    DerivationTree {
+                        rule: 'c',
+                        start: 2,
+                        end: 2,
+                        children: vec![],
+                    },
+                ],
+            }]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use cfg::generation::weighted::random::GenRange;
+
+/// Random choices read from a slice of bytes, e.g. fuzzer input.
+///
+/// This makes the same choices as `cfg`'s `ByteSource`, reading zeros once the bytes
+/// run out, but remembers whether that happened.
+#[derive(Clone, Debug)]
+pub struct Driver<'a> {
+    bytes: &'a [u8],
+    ahead: Vec<u8>,
+    exhausted: bool,
+}
+
+impl<'a> Driver<'a> {
+    /// Creates a driver that reads `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Driver {
+            bytes,
+            ahead: vec![],
+            exhausted: false,
+        }
+    }
+
+    /// Returns whether a byte was read past the end of the input.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        match self.bytes.split_first() {
+            Some((&byte, rest)) => {
+                self.bytes = rest;
+                byte
+            }
+            None => {
+                self.exhausted = true;
+                0
+            }
+        }
+    }
+}
+
+impl<'a> GenRange for Driver<'a> {
+    fn gen(&mut self, limit: f64) -> f64 {
+        let byte = match self.ahead.pop() {
+            Some(ahead) => ahead,
+            None => self.next_byte(),
+        };
+        byte as f64 * limit / 255.0
+    }
+
+    fn mutate_start(&mut self, attempt_number: u64) {
+        fn mix(byte: &mut u8) {
+            *byte ^= *byte >> 5;
+            *byte = byte.wrapping_mul(123);
+            *byte ^= *byte >> 5;
+            *byte = byte.wrapping_mul(34);
+            *byte ^= *byte >> 5;
+        }
+        let mut result = vec![];
+        for _ in 0..=attempt_number / 256 {
+            let mut byte = self.next_byte();
+            byte ^= attempt_number as u8;
+            mix(&mut byte);
+            result.push(byte);
+        }
+        result.reverse();
+        self.ahead.extend(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cfg::generation::weighted::random::ByteSource;
+
+    #[test]
+    fn same_as_byte_source() {
+        let bytes = [3, 250, 17, 128];
+        let mut driver = Driver::new(&bytes);
+        let mut byte_source = ByteSource::new(bytes.iter().cloned());
+        for attempt in 0..8 {
+            assert_eq!(driver.gen(10.0), byte_source.gen(10.0));
+            driver.mutate_start(attempt * 100);
+            byte_source.mutate_start(attempt * 100);
+        }
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut driver = Driver::new(&[255]);
+        assert_eq!(driver.gen(1.0), 1.0);
+        assert!(!driver.is_exhausted());
+        assert_eq!(driver.gen(1.0), 0.0);
+        assert!(driver.is_exhausted());
+    }
+}
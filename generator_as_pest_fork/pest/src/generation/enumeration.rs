@@ -0,0 +1,356 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use cfg::prelude::*;
+
+use super::{Generation, StackOp};
+
+/// What a symbol derives during enumeration, besides its productions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    /// The symbol derives its productions.
+    Rules,
+    /// The symbol derives the given string of at most one character.
+    Text(Option<char>),
+    /// The symbol derives nothing at all.
+    Nothing,
+}
+
+/// All strings derived from a symbol, in order of increasing length and then
+/// lexicographically, created by [`Generation::enumerate`].
+///
+/// Terminals that match more than one character derive a single one, the one
+/// that generation picks with a driver of zeros. Lookaheads derive the empty
+/// string without constraining what follows, so some of the strings may be
+/// rejected by the parser. Stack operations that generate text and expressions
+/// that fail generation derive nothing, so strings derived through them are
+/// left out.
+pub struct Enumeration {
+    start: Symbol,
+    max_len: usize,
+    kinds: Vec<Kind>,
+    rules: Vec<(Symbol, Vec<Symbol>)>,
+    /// The strings of each length derived from each symbol, for the lengths so far.
+    derived: Vec<Vec<BTreeSet<String>>>,
+    /// For each symbol, the symbols with a production where it may derive the whole
+    /// string, because the rest of the production derives the empty string.
+    units: Vec<Vec<Symbol>>,
+    /// For each symbol, the length of the longest string it derives that fits into a
+    /// string of `max_len` characters derived from `start`.
+    budgets: Vec<usize>,
+    current: vec::IntoIter<String>,
+}
+
+impl Enumeration {
+    pub(crate) fn new<F>(
+        generation: &Generation<'_>,
+        start: Symbol,
+        max_len: usize,
+        to_char: F,
+    ) -> Self
+    where
+        F: Fn(Symbol) -> Option<char>,
+    {
+        let grammar = generation.grammar;
+        let kinds: Vec<Kind> = (0..grammar.num_syms())
+            .map(Symbol::from)
+            .map(|sym| {
+                if generation.unsupported.contains_key(&sym) {
+                    Kind::Nothing
+                } else if let Some(&op) = generation.stack_ops.get(&sym) {
+                    match op {
+                        StackOp::PushStart | StackOp::Push | StackOp::Drop => Kind::Text(None),
+                        _ => Kind::Nothing,
+                    }
+                } else if generation.terminals.has_sym(sym) {
+                    Kind::Text(to_char(sym))
+                } else if generation.lookaheads.contains_key(&sym) {
+                    Kind::Text(None)
+                } else {
+                    Kind::Rules
+                }
+            })
+            .collect();
+        // Only the productions of symbols reachable from `start` matter.
+        let mut reachable = vec![false; grammar.num_syms()];
+        reachable[start.usize()] = true;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in grammar.rules() {
+                if reachable[rule.lhs().usize()] && kinds[rule.lhs().usize()] == Kind::Rules {
+                    for &sym in rule.rhs() {
+                        changed |= !reachable[sym.usize()];
+                        reachable[sym.usize()] = true;
+                    }
+                }
+            }
+        }
+        let rules: Vec<(Symbol, Vec<Symbol>)> = grammar
+            .rules()
+            .filter(|rule| {
+                reachable[rule.lhs().usize()] && kinds[rule.lhs().usize()] == Kind::Rules
+            })
+            .map(|rule| (rule.lhs(), rule.rhs().to_vec()))
+            .collect();
+        let budgets = budgets(&kinds, &rules, start, max_len);
+        Enumeration {
+            start,
+            max_len,
+            rules,
+            kinds,
+            derived: vec![vec![]; grammar.num_syms()],
+            units: vec![],
+            budgets,
+            current: vec![].into_iter(),
+        }
+    }
+
+    /// Computes the strings of the next length derived from each symbol, given
+    /// those of all shorter lengths.
+    fn derive_next_len(&mut self) {
+        let len = self.derived[self.start.usize()].len();
+        for (sym_idx, &kind) in self.kinds.iter().enumerate() {
+            let mut strings = BTreeSet::new();
+            if let Kind::Text(text) = kind {
+                if text.map_or(0, |_| 1) == len {
+                    strings.insert(text.into_iter().collect());
+                }
+            }
+            self.derived[sym_idx].push(strings);
+        }
+        if len == 0 {
+            self.derive_empty();
+            return;
+        }
+        // Strings of the production's symbols that are all shorter than `len` are
+        // concatenated once. The rest passes through `units` to other symbols.
+        let mut added: Vec<Vec<String>> = vec![vec![]; self.kinds.len()];
+        if len == 1 {
+            for (sym_idx, strings) in self.derived.iter().enumerate() {
+                added[sym_idx].extend(strings[len].iter().cloned());
+            }
+        }
+        for (lhs, rhs) in &self.rules {
+            if self.budgets[lhs.usize()] < len {
+                continue;
+            }
+            let mut strings = vec![];
+            concat(
+                &self.derived,
+                rhs,
+                len,
+                len - 1,
+                String::new(),
+                &mut strings,
+            );
+            let derived = &mut self.derived[lhs.usize()][len];
+            for string in strings {
+                if derived.insert(string.clone()) {
+                    added[lhs.usize()].push(string);
+                }
+            }
+        }
+        let mut pending: Vec<usize> = (0..added.len())
+            .filter(|&sym_idx| !added[sym_idx].is_empty())
+            .collect();
+        while let Some(sym_idx) = pending.pop() {
+            let strings = mem::take(&mut added[sym_idx]);
+            for &to in &self.units[sym_idx] {
+                if self.budgets[to.usize()] < len {
+                    continue;
+                }
+                let derived = &mut self.derived[to.usize()][len];
+                let was_pending = !added[to.usize()].is_empty();
+                for string in &strings {
+                    if derived.insert(string.clone()) {
+                        added[to.usize()].push(string.clone());
+                    }
+                }
+                if !was_pending && !added[to.usize()].is_empty() {
+                    pending.push(to.usize());
+                }
+            }
+        }
+    }
+
+    /// Finds the symbols that derive the empty string, and with them `units`.
+    fn derive_empty(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (lhs, rhs) in &self.rules {
+                if rhs
+                    .iter()
+                    .all(|sym| !self.derived[sym.usize()][0].is_empty())
+                {
+                    changed |= self.derived[lhs.usize()][0].insert(String::new());
+                }
+            }
+        }
+        self.units = vec![vec![]; self.kinds.len()];
+        for (lhs, rhs) in &self.rules {
+            for (idx, sym) in rhs.iter().enumerate() {
+                let rest_empty = rhs
+                    .iter()
+                    .enumerate()
+                    .all(|(other, sym)| other == idx || !self.derived[sym.usize()][0].is_empty());
+                if rest_empty && !self.units[sym.usize()].contains(lhs) {
+                    self.units[sym.usize()].push(*lhs);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the length of the longest string each symbol may derive inside a string of
+/// `max_len` characters derived from `start`, i.e. `max_len` less the fewest
+/// characters that the rest of such a string needs.
+fn budgets(
+    kinds: &[Kind],
+    rules: &[(Symbol, Vec<Symbol>)],
+    start: Symbol,
+    max_len: usize,
+) -> Vec<usize> {
+    let mut min_lens: Vec<Option<usize>> = kinds
+        .iter()
+        .map(|&kind| match kind {
+            Kind::Text(text) => Some(text.map_or(0, |_| 1)),
+            Kind::Rules | Kind::Nothing => None,
+        })
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (lhs, rhs) in rules {
+            let len = rhs
+                .iter()
+                .map(|sym| min_lens[sym.usize()])
+                .sum::<Option<usize>>();
+            if let Some(len) = len {
+                if min_lens[lhs.usize()].map_or(true, |min_len| len < min_len) {
+                    min_lens[lhs.usize()] = Some(len);
+                    changed = true;
+                }
+            }
+        }
+    }
+    let mut budgets: Vec<Option<usize>> = vec![None; kinds.len()];
+    budgets[start.usize()] = Some(max_len);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (lhs, rhs) in rules {
+            let (budget, min_len) = match (
+                budgets[lhs.usize()],
+                rhs.iter()
+                    .map(|sym| min_lens[sym.usize()])
+                    .sum::<Option<usize>>(),
+            ) {
+                (Some(budget), Some(min_len)) => (budget, min_len),
+                _ => continue,
+            };
+            for sym in rhs {
+                let rest = min_len - min_lens[sym.usize()].unwrap();
+                if let Some(sym_budget) = budget.checked_sub(rest) {
+                    if budgets[sym.usize()].map_or(true, |old| sym_budget > old) {
+                        budgets[sym.usize()] = Some(sym_budget);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+    budgets
+        .into_iter()
+        .map(|budget| budget.unwrap_or(0))
+        .collect()
+}
+
+/// Appends to `out` every string of `len` characters derived from `rhs` where each
+/// symbol derives at most `max_part` characters, each one prefixed with `prefix`.
+fn concat(
+    derived: &[Vec<BTreeSet<String>>],
+    rhs: &[Symbol],
+    len: usize,
+    max_part: usize,
+    prefix: String,
+    out: &mut Vec<String>,
+) {
+    let (first, rest) = match rhs.split_first() {
+        Some(split) => split,
+        None => {
+            if len == 0 {
+                out.push(prefix);
+            }
+            return;
+        }
+    };
+    for first_len in 0..=len.min(max_part) {
+        for string in &derived[first.usize()][first_len] {
+            let mut prefixed = prefix.clone();
+            prefixed.push_str(string);
+            concat(derived, rest, len - first_len, max_part, prefixed, out);
+        }
+    }
+}
+
+impl Iterator for Enumeration {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some(string) = self.current.next() {
+                return Some(string);
+            }
+            if self.derived[self.start.usize()].len() > self.max_len {
+                return None;
+            }
+            self.derive_next_len();
+            let strings = self.derived[self.start.usize()].last().unwrap();
+            self.current = strings.iter().cloned().collect::<Vec<_>>().into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_increasing_length() {
+        let mut grammar = Cfg::new();
+        let (start, list, a, b, empty) = grammar.sym();
+        grammar
+            .rule(start)
+            .rhs([list])
+            .rule(list)
+            .rhs([a])
+            .rhs([list, empty, b, list])
+            .rule(empty)
+            .rhs([]);
+        let binarized = grammar.binarize();
+        let generation = Generation::new(&binarized);
+        let to_char = |sym| {
+            if sym == a {
+                Some('a')
+            } else {
+                Some('b')
+            }
+        };
+        let strings: Vec<String> = generation.enumerate(start, 5, to_char).collect();
+        assert_eq!(strings, ["a", "aba", "ababa"]);
+    }
+}
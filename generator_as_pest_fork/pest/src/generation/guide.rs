@@ -0,0 +1,62 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use cfg::prelude::*;
+
+use super::Alternative;
+
+/// Hit counts of productions over many generations, for coverage-guided generation.
+///
+/// A guide counts the productions picked for symbols that have several: the
+/// branches of choices, whether optional expressions are present, and whether
+/// repetitions go on, since repetitions are rewritten into recursive rules.
+/// Generation with a guide gives each production that was never picked as much
+/// weight as all productions of its symbol together, as soon as another production
+/// of the symbol was picked. Symbols whose productions were all picked, or none,
+/// are picked as without a guide, so that repetitions keep their usual length.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Guide {
+    hits: BTreeMap<Alternative, u64>,
+}
+
+impl Guide {
+    /// Creates a guide that has seen nothing yet.
+    pub fn new() -> Self {
+        Guide::default()
+    }
+
+    /// Returns how many times generation picked `alternative` so far.
+    pub fn hits(&self, alternative: Alternative) -> u64 {
+        self.hits.get(&alternative).cloned().unwrap_or(0)
+    }
+
+    /// Returns how many productions have been picked at least once.
+    pub fn covered(&self) -> usize {
+        self.hits.len()
+    }
+
+    pub(crate) fn record(&mut self, alternative: Alternative) {
+        *self.hits.entry(alternative).or_insert(0) += 1;
+    }
+
+    /// Returns which of the `len` productions of `sym` were never picked, if some
+    /// of them were.
+    pub(crate) fn unvisited(&self, sym: Symbol, len: usize) -> Option<Vec<bool>> {
+        let unvisited: Vec<bool> = (0..len)
+            .map(|index| self.hits(Alternative { sym, index }) == 0)
+            .collect();
+        if unvisited.iter().all(|&unvisited| unvisited) || !unvisited.contains(&true) {
+            return None;
+        }
+        Some(unvisited)
+    }
+}
@@ -0,0 +1,421 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Lookahead constraints and the automata that check them.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use cfg::prelude::*;
+
+/// A regular pattern over characters that a lookahead expects (or forbids) ahead.
+///
+/// Patterns are matched against prefixes of the text that follows the lookahead, so
+/// PEG features such as ordered choice and greedy repetition are approximated by their
+/// regular counterparts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Pattern {
+    /// Matches an exact string.
+    Str(String),
+    /// Matches an exact string, case insensitively (ASCII only).
+    Insens(String),
+    /// Matches one character in the inclusive range.
+    Range(char, char),
+    /// Matches patterns one after another.
+    Seq(Vec<Pattern>),
+    /// Matches any one of the patterns.
+    Choice(Vec<Pattern>),
+    /// Optionally matches a pattern.
+    Opt(Box<Pattern>),
+    /// Matches a pattern zero or more times.
+    Rep(Box<Pattern>),
+    /// Matches nothing if the text ahead starts with a match of the pattern.
+    PosPred(Box<Pattern>),
+    /// Matches nothing if the text ahead does not start with a match of the pattern.
+    NegPred(Box<Pattern>),
+}
+
+/// Negative lookahead; the text generated after `sym` must not start with a match
+/// of `pattern`.
+#[derive(Clone, Debug)]
+pub struct NegativeRule {
+    /// The nulling symbol that marks the position of the lookahead.
+    pub sym: Symbol,
+    /// The forbidden pattern.
+    pub pattern: Pattern,
+}
+
+/// Positive lookahead; the text generated after `sym` must start with a match
+/// of `pattern`.
+#[derive(Clone, Debug)]
+pub struct PositiveRule {
+    /// The nulling symbol that marks the position of the lookahead.
+    pub sym: Symbol,
+    /// The expected pattern.
+    pub pattern: Pattern,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Check {
+    Pending,
+    Satisfied,
+    Violated,
+}
+
+/// A compiled lookahead.
+pub(crate) struct Lookahead {
+    positive: bool,
+    nfa: Nfa,
+}
+
+impl Lookahead {
+    pub(crate) fn negative(pattern: &Pattern) -> Self {
+        Lookahead {
+            positive: false,
+            nfa: Nfa::new(pattern),
+        }
+    }
+
+    pub(crate) fn positive(pattern: &Pattern) -> Self {
+        Lookahead {
+            positive: true,
+            nfa: Nfa::new(pattern),
+        }
+    }
+}
+
+/// A path through the automaton, together with the nested lookaheads it passed
+/// that are not resolved yet, as `(lookahead, offset)` pairs.
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+struct Thread {
+    state: usize,
+    pending: Vec<(usize, usize)>,
+}
+
+/// The progress of a lookahead over the text that follows it.
+pub(crate) struct Progress<'a> {
+    lookahead: &'a Lookahead,
+    checked: usize,
+    threads: Vec<Thread>,
+}
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(lookahead: &'a Lookahead) -> Self {
+        Progress {
+            lookahead,
+            checked: 0,
+            threads: lookahead.nfa.start(),
+        }
+    }
+
+    /// Forgets the progress past `len` characters, after backtracking.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if self.checked > len {
+            self.checked = 0;
+            self.threads = self.lookahead.nfa.start();
+        }
+    }
+
+    /// Checks the lookahead against `text`, continuing from the previous check.
+    pub(crate) fn check(&mut self, text: &[char], at_end: bool) -> Check {
+        let nfa = &self.lookahead.nfa;
+        loop {
+            self.threads = nfa.resolve(&self.threads, text, at_end);
+            let check = self.current();
+            if check != Check::Pending {
+                return check;
+            }
+            if self.checked == text.len() {
+                break;
+            }
+            self.threads = nfa.step(&self.threads, text[self.checked], self.checked + 1);
+            self.checked += 1;
+        }
+        match (at_end, self.lookahead.positive) {
+            (false, _) => Check::Pending,
+            (true, true) => Check::Violated,
+            (true, false) => Check::Satisfied,
+        }
+    }
+
+    fn current(&self) -> Check {
+        let accept = self.lookahead.nfa.accept;
+        let matched = self
+            .threads
+            .iter()
+            .any(|thread| thread.state == accept && thread.pending.is_empty());
+        match (self.lookahead.positive, matched, self.threads.is_empty()) {
+            (true, true, _) => Check::Satisfied,
+            (false, true, _) => Check::Violated,
+            (true, false, true) => Check::Violated,
+            (false, false, true) => Check::Satisfied,
+            (_, false, false) => Check::Pending,
+        }
+    }
+}
+
+struct State {
+    ranges: Vec<(char, char, usize)>,
+    epsilon: Vec<usize>,
+    /// Epsilon transitions that place a nested lookahead, as `(lookahead, next)` pairs.
+    lookaheads: Vec<(usize, usize)>,
+}
+
+/// A Thompson automaton built from a `Pattern`.
+struct Nfa {
+    states: Vec<State>,
+    lookaheads: Vec<Lookahead>,
+    start: usize,
+    accept: usize,
+}
+
+impl Nfa {
+    fn new(pattern: &Pattern) -> Self {
+        let mut nfa = Nfa {
+            states: vec![],
+            lookaheads: vec![],
+            start: 0,
+            accept: 0,
+        };
+        let (start, accept) = nfa.fragment(pattern);
+        nfa.start = start;
+        nfa.accept = accept;
+        nfa
+    }
+
+    fn state(&mut self) -> usize {
+        self.states.push(State {
+            ranges: vec![],
+            epsilon: vec![],
+            lookaheads: vec![],
+        });
+        self.states.len() - 1
+    }
+
+    /// Builds the automaton for `pattern` and returns its start and end states.
+    fn fragment(&mut self, pattern: &Pattern) -> (usize, usize) {
+        match pattern {
+            Pattern::Str(string) => {
+                let start = self.state();
+                let mut end = start;
+                for ch in string.chars() {
+                    let next = self.state();
+                    self.states[end].ranges.push((ch, ch, next));
+                    end = next;
+                }
+                (start, end)
+            }
+            Pattern::Insens(string) => {
+                let start = self.state();
+                let mut end = start;
+                for ch in string.chars() {
+                    let next = self.state();
+                    let lower = ch.to_ascii_lowercase();
+                    let upper = ch.to_ascii_uppercase();
+                    self.states[end].ranges.push((lower, lower, next));
+                    if upper != lower {
+                        self.states[end].ranges.push((upper, upper, next));
+                    }
+                    end = next;
+                }
+                (start, end)
+            }
+            &Pattern::Range(first, last) => {
+                let start = self.state();
+                let end = self.state();
+                self.states[start].ranges.push((first, last, end));
+                (start, end)
+            }
+            Pattern::Seq(patterns) => {
+                let start = self.state();
+                let mut end = start;
+                for pattern in patterns {
+                    let (inner_start, inner_end) = self.fragment(pattern);
+                    self.states[end].epsilon.push(inner_start);
+                    end = inner_end;
+                }
+                (start, end)
+            }
+            Pattern::Choice(patterns) => {
+                let start = self.state();
+                let end = self.state();
+                for pattern in patterns {
+                    let (inner_start, inner_end) = self.fragment(pattern);
+                    self.states[start].epsilon.push(inner_start);
+                    self.states[inner_end].epsilon.push(end);
+                }
+                (start, end)
+            }
+            Pattern::Opt(pattern) => {
+                let (start, end) = self.fragment(pattern);
+                self.states[start].epsilon.push(end);
+                (start, end)
+            }
+            Pattern::Rep(pattern) => {
+                let start = self.state();
+                let (inner_start, inner_end) = self.fragment(pattern);
+                self.states[start].epsilon.push(inner_start);
+                self.states[inner_end].epsilon.push(start);
+                (start, start)
+            }
+            Pattern::PosPred(pattern) => self.nested(Lookahead::positive(pattern)),
+            Pattern::NegPred(pattern) => self.nested(Lookahead::negative(pattern)),
+        }
+    }
+
+    fn nested(&mut self, lookahead: Lookahead) -> (usize, usize) {
+        self.lookaheads.push(lookahead);
+        let start = self.state();
+        let end = self.state();
+        self.states[start]
+            .lookaheads
+            .push((self.lookaheads.len() - 1, end));
+        (start, end)
+    }
+
+    fn start(&self) -> Vec<Thread> {
+        let thread = Thread {
+            state: self.start,
+            pending: vec![],
+        };
+        self.closure(vec![thread], 0)
+    }
+
+    fn step(&self, threads: &[Thread], ch: char, offset: usize) -> Vec<Thread> {
+        let next = threads
+            .iter()
+            .flat_map(|thread| {
+                self.states[thread.state]
+                    .ranges
+                    .iter()
+                    .filter(move |&&(first, last, _)| first <= ch && ch <= last)
+                    .map(move |&(_, _, next)| Thread {
+                        state: next,
+                        pending: thread.pending.clone(),
+                    })
+            })
+            .collect();
+        self.closure(next, offset)
+    }
+
+    /// Checks the nested lookaheads of all threads, dropping the threads that
+    /// violate one.
+    fn resolve(&self, threads: &[Thread], text: &[char], at_end: bool) -> Vec<Thread> {
+        let mut result = vec![];
+        'threads: for thread in threads {
+            let mut pending = vec![];
+            for &(lookahead, offset) in &thread.pending {
+                let mut progress = Progress::new(&self.lookaheads[lookahead]);
+                match progress.check(&text[offset..], at_end) {
+                    Check::Violated => continue 'threads,
+                    Check::Pending => pending.push((lookahead, offset)),
+                    Check::Satisfied => {}
+                }
+            }
+            result.push(Thread {
+                state: thread.state,
+                pending,
+            });
+        }
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    fn closure(&self, mut work: Vec<Thread>, offset: usize) -> Vec<Thread> {
+        let mut result: Vec<Thread> = vec![];
+        while let Some(thread) = work.pop() {
+            if result.contains(&thread) {
+                continue;
+            }
+            let state = &self.states[thread.state];
+            for &next in &state.epsilon {
+                work.push(Thread {
+                    state: next,
+                    pending: thread.pending.clone(),
+                });
+            }
+            for &(lookahead, next) in &state.lookaheads {
+                let mut pending = thread.pending.clone();
+                if !pending.contains(&(lookahead, offset)) {
+                    pending.push((lookahead, offset));
+                    pending.sort();
+                }
+                work.push(Thread {
+                    state: next,
+                    pending,
+                });
+            }
+            result.push(thread);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(lookahead: &Lookahead, text: &str, at_end: bool) -> Check {
+        let text: Vec<char> = text.chars().collect();
+        Progress::new(lookahead).check(&text, at_end)
+    }
+
+    #[test]
+    fn negative_keyword() {
+        let pattern = Pattern::Choice(vec![
+            Pattern::Str("as".into()),
+            Pattern::Str("assert".into()),
+        ]);
+        let lookahead = Lookahead::negative(&pattern);
+        assert_eq!(check(&lookahead, "a", false), Check::Pending);
+        assert_eq!(check(&lookahead, "as", false), Check::Violated);
+        assert_eq!(check(&lookahead, "ab", false), Check::Satisfied);
+        assert_eq!(check(&lookahead, "a", true), Check::Satisfied);
+    }
+
+    #[test]
+    fn positive_regular() {
+        let pattern = Pattern::Seq(vec![
+            Pattern::Rep(Box::new(Pattern::Range('0', '9'))),
+            Pattern::Insens("x".into()),
+        ]);
+        let lookahead = Lookahead::positive(&pattern);
+        assert_eq!(check(&lookahead, "12", false), Check::Pending);
+        assert_eq!(check(&lookahead, "12X", false), Check::Satisfied);
+        assert_eq!(check(&lookahead, "12y", false), Check::Violated);
+        assert_eq!(check(&lookahead, "12", true), Check::Violated);
+    }
+
+    #[test]
+    fn progress_after_backtracking() {
+        let lookahead = Lookahead::negative(&Pattern::Str("ab".into()));
+        let mut progress = Progress::new(&lookahead);
+        let text: Vec<char> = "ab".chars().collect();
+        assert_eq!(progress.check(&text[..1], false), Check::Pending);
+        assert_eq!(progress.check(&text, false), Check::Violated);
+        progress.truncate(0);
+        assert_eq!(progress.check(&text[..1], false), Check::Pending);
+    }
+
+    #[test]
+    fn nested_lookahead() {
+        let keyword = Pattern::Seq(vec![
+            Pattern::Str("fn".into()),
+            Pattern::NegPred(Box::new(Pattern::Range('a', 'z'))),
+        ]);
+        let lookahead = Lookahead::negative(&keyword);
+        assert_eq!(check(&lookahead, "fn", false), Check::Pending);
+        assert_eq!(check(&lookahead, "fn", true), Check::Violated);
+        assert_eq!(check(&lookahead, "fn(", false), Check::Violated);
+        assert_eq!(check(&lookahead, "fnx", false), Check::Satisfied);
+    }
+}
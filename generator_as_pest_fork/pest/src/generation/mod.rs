@@ -0,0 +1,542 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Random generation of strings from the context-free grammar that `pest_derive`
+//! builds out of a pest grammar.
+//!
+//! This follows `cfg::generation::weighted::Random`, but also knows about pest's
+//! lookahead. Lookahead symbols derive nothing; instead, each one places a constraint
+//! on the text generated after it. Constraints are checked as the string grows, and
+//! a violated constraint backtracks generation to the point where the constraint was
+//! placed and retries with a mutated random source.
+//!
+//! Stack operations such as `PUSH(e)` and `POP` are marked by symbols as well;
+//! they replay previously generated text the same way the parser matches it.
+//!
+//! Symbols registered with [`Generation::rule_symbols`] are recorded in the
+//! derivation trees of the output, with the span of text each one derived.
+//! Branches of choices registered with [`Generation::alternatives`] are recorded
+//! whenever they are picked, for grammar coverage. Generation with a [`Guide`]
+//! uses counts across runs to favor the productions never picked so far.
+//!
+//! Besides random generation, [`Generation::enumerate`] lists all strings derived
+//! from a symbol up to a length, shortest first.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use cfg::generation::weighted::random::GenRange;
+use cfg::prelude::*;
+use cfg::symbol::SymbolBitSet;
+use log::debug;
+use rpds::List;
+
+mod chars;
+mod derivation;
+mod driver;
+mod enumeration;
+mod guide;
+mod lookahead;
+mod stack;
+mod weighted;
+
+pub use self::chars::{pick_any, pick_char, property_ranges};
+use self::derivation::Event;
+pub use self::derivation::{Derivation, DerivationTree};
+pub use self::driver::Driver;
+pub use self::enumeration::Enumeration;
+pub use self::guide::Guide;
+use self::lookahead::{Check, Lookahead, Progress};
+pub use self::lookahead::{NegativeRule, Pattern, PositiveRule};
+use self::stack::Stack;
+pub use self::stack::{StackOp, StackRule};
+use self::weighted::Weighted;
+
+/// How many times generation may backtrack to a single lookahead before giving up.
+const MAX_ATTEMPTS: u64 = 256 * 64;
+
+/// An error that stopped generation.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum GenerationError {
+    /// The derivation produced more terminals than allowed.
+    LimitExceeded,
+    /// A lookahead was violated by too many attempts in a row.
+    LookaheadAttemptsExceeded,
+    /// The driver ran out of bytes before generation failed, so a longer driver
+    /// may succeed.
+    DriverExhausted,
+    /// A stack operation found the stack with too few entries.
+    StackUnderflow,
+    /// The start rule is not in the grammar.
+    UnknownStartRule(String),
+    /// The derivation reached an expression that can't be generated faithfully.
+    UnsupportedExpression {
+        /// The rule containing the expression
+        rule: String,
+        /// The expression
+        expr: String,
+    },
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerationError::LimitExceeded => write!(f, "generation exceeded the limit"),
+            GenerationError::LookaheadAttemptsExceeded => {
+                write!(f, "too many attempts to satisfy a lookahead")
+            }
+            GenerationError::DriverExhausted => write!(f, "the driver ran out of bytes"),
+            GenerationError::StackUnderflow => write!(f, "the stack has too few entries"),
+            GenerationError::UnknownStartRule(rule) => write!(f, "unknown start rule {}", rule),
+            GenerationError::UnsupportedExpression { rule, expr } => {
+                write!(f, "unsupported expression in rule {}: {}", rule, expr)
+            }
+        }
+    }
+}
+
+/// An expression that generation can't handle, marked by a symbol that fails
+/// generation once reached.
+#[derive(Clone, Debug)]
+pub struct UnsupportedRule {
+    /// The symbol that marks the expression.
+    pub sym: Symbol,
+    /// The rule containing the expression.
+    pub rule: String,
+    /// The expression.
+    pub expr: String,
+}
+
+/// A branch of a choice in a pest rule: the production `index` of `sym` in the grammar.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Alternative {
+    /// The symbol of the choice.
+    pub sym: Symbol,
+    /// The index of the branch among the productions of `sym`.
+    pub index: usize,
+}
+
+/// The output of a successful generation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Generated {
+    /// All terminal symbols in the derivation, in order.
+    pub symbols: Vec<Symbol>,
+    /// The generated text.
+    pub string: String,
+    /// How many times a generated continuation was rejected by a lookahead.
+    pub rejections: u64,
+    /// The derivation trees of the rule symbols, with byte spans into `string`.
+    pub trees: Vec<DerivationTree<Symbol>>,
+    /// The positions of the picked alternatives in the list given to
+    /// [`Generation::alternatives`], in order.
+    pub alternatives: Vec<usize>,
+}
+
+/// An entry of the work list: a symbol left to derive, or the end of a rule
+/// symbol's derivation.
+#[derive(Copy, Clone, Debug)]
+enum Work {
+    Derive(Symbol),
+    Close,
+}
+
+/// The state of generation right after a lookahead symbol was processed.
+struct Snapshot<R> {
+    rng: R,
+    work: List<Work>,
+    symbols_len: usize,
+    events_len: usize,
+    alternatives_len: usize,
+    string_len: usize,
+    stack: Stack,
+}
+
+/// The lookaheads of a single symbol that was reached during generation.
+struct Placed<'a, R> {
+    progress: Vec<Progress<'a>>,
+    start: usize,
+    resolved_at: Option<usize>,
+    attempts: u64,
+    snapshot: Snapshot<R>,
+}
+
+impl<'a, R> Placed<'a, R> {
+    fn truncate(&mut self, len: usize) {
+        if self.resolved_at.map_or(false, |at| at > len) {
+            self.resolved_at = None;
+        }
+        for progress in &mut self.progress {
+            progress.truncate(len - self.start);
+        }
+    }
+
+    fn check(&mut self, string: &[char], at_end: bool) -> Check {
+        let text = &string[self.start..];
+        let mut result = Check::Satisfied;
+        for progress in &mut self.progress {
+            match progress.check(text, at_end) {
+                Check::Violated => return Check::Violated,
+                Check::Pending => result = Check::Pending,
+                Check::Satisfied => {}
+            }
+        }
+        result
+    }
+}
+
+/// Random generation with support for lookahead.
+pub struct Generation<'g> {
+    grammar: &'g BinarizedCfg,
+    weighted: Weighted,
+    terminals: SymbolBitSet,
+    lookaheads: BTreeMap<Symbol, Vec<Lookahead>>,
+    stack_ops: BTreeMap<Symbol, StackOp>,
+    rule_symbols: BTreeSet<Symbol>,
+    unsupported: BTreeMap<Symbol, UnsupportedRule>,
+    alternatives: BTreeMap<Alternative, usize>,
+}
+
+impl<'g> Generation<'g> {
+    /// Prepares generation from the given grammar.
+    pub fn new(grammar: &'g BinarizedCfg) -> Self {
+        Generation {
+            grammar,
+            weighted: Weighted::new(grammar),
+            terminals: SymbolBitSet::terminal_set(grammar),
+            lookaheads: BTreeMap::new(),
+            stack_ops: BTreeMap::new(),
+            rule_symbols: BTreeSet::new(),
+            unsupported: BTreeMap::new(),
+            alternatives: BTreeMap::new(),
+        }
+    }
+
+    /// Adds negative lookahead constraints.
+    pub fn negative_rules(mut self, rules: &[NegativeRule]) -> Self {
+        for rule in rules {
+            self.lookaheads
+                .entry(rule.sym)
+                .or_default()
+                .push(Lookahead::negative(&rule.pattern));
+        }
+        self
+    }
+
+    /// Adds positive lookahead constraints.
+    pub fn positive_rules(mut self, rules: &[PositiveRule]) -> Self {
+        for rule in rules {
+            self.lookaheads
+                .entry(rule.sym)
+                .or_default()
+                .push(Lookahead::positive(&rule.pattern));
+        }
+        self
+    }
+
+    /// Adds stack operations.
+    pub fn stack_rules(mut self, rules: &[StackRule]) -> Self {
+        for rule in rules {
+            self.stack_ops.insert(rule.sym, rule.op);
+        }
+        self
+    }
+
+    /// Adds expressions that fail generation.
+    pub fn unsupported_rules(mut self, rules: &[UnsupportedRule]) -> Self {
+        for rule in rules {
+            self.unsupported.insert(rule.sym, rule.clone());
+        }
+        self
+    }
+
+    /// Adds symbols whose derivations are recorded in the output.
+    pub fn rule_symbols(mut self, syms: &[Symbol]) -> Self {
+        self.rule_symbols.extend(syms.iter().cloned());
+        self
+    }
+
+    /// Adds alternatives whose picks are recorded in the output, by their position
+    /// in `alternatives`.
+    pub fn alternatives(mut self, alternatives: &[Alternative]) -> Self {
+        let offset = self.alternatives.len();
+        for (idx, &alternative) in alternatives.iter().enumerate() {
+            self.alternatives.insert(alternative, offset + idx);
+        }
+        self
+    }
+
+    /// Returns the grammar that this generation runs on.
+    pub fn grammar(&self) -> &'g BinarizedCfg {
+        self.grammar
+    }
+
+    /// Generates a string derived from `start`, choosing alternatives with the bytes
+    /// of `driver`. Failures after the driver ran out of bytes are reported as
+    /// `DriverExhausted`.
+    pub fn drive<'d, F>(
+        &self,
+        start: Symbol,
+        limit: Option<u64>,
+        driver: &mut Driver<'d>,
+        to_char: F,
+    ) -> Result<Generated, GenerationError>
+    where
+        F: Fn(Symbol, &mut Driver<'d>) -> Option<char>,
+    {
+        self.drive_with(start, limit, driver, None, to_char)
+    }
+
+    /// Like [`Generation::drive`], but favors the productions that `guide` hasn't
+    /// seen, and records every production picked in `guide`, including those of
+    /// continuations that are backtracked or fail.
+    pub fn drive_guided<'d, F>(
+        &self,
+        start: Symbol,
+        limit: Option<u64>,
+        driver: &mut Driver<'d>,
+        guide: &mut Guide,
+        to_char: F,
+    ) -> Result<Generated, GenerationError>
+    where
+        F: Fn(Symbol, &mut Driver<'d>) -> Option<char>,
+    {
+        self.drive_with(start, limit, driver, Some(guide), to_char)
+    }
+
+    fn drive_with<'d, F>(
+        &self,
+        start: Symbol,
+        limit: Option<u64>,
+        driver: &mut Driver<'d>,
+        guide: Option<&mut Guide>,
+        to_char: F,
+    ) -> Result<Generated, GenerationError>
+    where
+        F: Fn(Symbol, &mut Driver<'d>) -> Option<char>,
+    {
+        self.generate(start, limit, driver, guide, to_char)
+            .map_err(|err| match err {
+                GenerationError::LimitExceeded | GenerationError::LookaheadAttemptsExceeded
+                    if driver.is_exhausted() =>
+                {
+                    GenerationError::DriverExhausted
+                }
+                err => err,
+            })
+    }
+
+    /// Generates a string derived from `start`, choosing alternatives with `rng`.
+    /// `to_char` maps terminal symbols to characters.
+    pub fn random<R, F>(
+        &self,
+        start: Symbol,
+        limit: Option<u64>,
+        rng: &mut R,
+        to_char: F,
+    ) -> Result<Generated, GenerationError>
+    where
+        R: GenRange + Clone,
+        F: Fn(Symbol, &mut R) -> Option<char>,
+    {
+        self.generate(start, limit, rng, None, to_char)
+    }
+
+    fn generate<R, F>(
+        &self,
+        start: Symbol,
+        limit: Option<u64>,
+        rng: &mut R,
+        mut guide: Option<&mut Guide>,
+        to_char: F,
+    ) -> Result<Generated, GenerationError>
+    where
+        R: GenRange + Clone,
+        F: Fn(Symbol, &mut R) -> Option<char>,
+    {
+        let mut work = List::new();
+        work.push_front_mut(Work::Derive(start));
+        let mut symbols = vec![];
+        let mut events = vec![];
+        let mut alternatives = vec![];
+        let mut string = vec![];
+        let mut placed: Vec<Placed<'_, R>> = vec![];
+        let mut stack = Stack::default();
+        let mut rejections = 0;
+
+        loop {
+            let violated = if let Some(&next) = work.first() {
+                work.drop_first_mut();
+                let sym = match next {
+                    Work::Derive(sym) => sym,
+                    Work::Close => {
+                        events.push(Event::Close(string.len()));
+                        continue;
+                    }
+                };
+                if let Some(unsupported) = self.unsupported.get(&sym) {
+                    return Err(GenerationError::UnsupportedExpression {
+                        rule: unsupported.rule.clone(),
+                        expr: unsupported.expr.clone(),
+                    });
+                }
+                let is_rule = self.rule_symbols.contains(&sym);
+                if is_rule {
+                    events.push(Event::Open(sym, string.len()));
+                }
+                if let Some(&op) = self.stack_ops.get(&sym) {
+                    let text = stack
+                        .apply(op, &string)
+                        .ok_or(GenerationError::StackUnderflow)?;
+                    debug!("STACK: {:?} generates {:?}", op, text);
+                    string.extend(text);
+                    if is_rule {
+                        events.push(Event::Close(string.len()));
+                    }
+                    self.first_violated(&mut placed, &string, false)
+                } else if self.terminals.has_sym(sym) {
+                    symbols.push(sym);
+                    if let Some(ch) = to_char(sym, rng) {
+                        string.push(ch);
+                        debug!("TERMINAL: string: {:?}, result: {:?}", ch, sym);
+                    } else {
+                        debug!("TERMINAL: result: {:?}", sym);
+                    }
+                    if is_rule {
+                        events.push(Event::Close(string.len()));
+                    }
+                    if let Some(max_terminals) = limit {
+                        if symbols.len() as u64 > max_terminals {
+                            return Err(GenerationError::LimitExceeded);
+                        }
+                    }
+                    self.first_violated(&mut placed, &string, false)
+                } else if let Some(lookaheads) = self.lookaheads.get(&sym) {
+                    debug!("LOOKAHEAD: {:?} at {:?}", sym, string.len());
+                    if is_rule {
+                        events.push(Event::Close(string.len()));
+                    }
+                    placed.push(Placed {
+                        progress: lookaheads.iter().map(Progress::new).collect(),
+                        start: string.len(),
+                        resolved_at: None,
+                        attempts: 0,
+                        snapshot: Snapshot {
+                            rng: rng.clone(),
+                            work: work.clone(),
+                            symbols_len: symbols.len(),
+                            events_len: events.len(),
+                            alternatives_len: alternatives.len(),
+                            string_len: string.len(),
+                            stack: stack.clone(),
+                        },
+                    });
+                    self.first_violated(&mut placed, &string, false)
+                } else {
+                    let (index, rhs) = self.weighted.pick(sym, rng, guide.as_deref());
+                    debug!("PICK RHS: from {:?} at {:?}", rhs, string.len());
+                    if let Some(&alternative) = self.alternatives.get(&Alternative { sym, index }) {
+                        alternatives.push(alternative);
+                    }
+                    if let Some(guide) = guide.as_mut() {
+                        if self.weighted.is_choice(sym) {
+                            guide.record(Alternative { sym, index });
+                        }
+                    }
+                    if is_rule {
+                        work.push_front_mut(Work::Close);
+                    }
+                    for &sym in rhs.iter().rev() {
+                        work.push_front_mut(Work::Derive(sym));
+                    }
+                    None
+                }
+            } else {
+                match self.first_violated(&mut placed, &string, true) {
+                    Some(idx) => Some(idx),
+                    None => break,
+                }
+            };
+
+            if let Some(idx) = violated {
+                rejections += 1;
+                placed.truncate(idx + 1);
+                let back = &mut placed[idx];
+                back.attempts += 1;
+                if back.attempts > MAX_ATTEMPTS {
+                    return Err(GenerationError::LookaheadAttemptsExceeded);
+                }
+                debug!(
+                    "BACKTRACK: to {:?}, attempt {:?}",
+                    back.start, back.attempts
+                );
+                *rng = back.snapshot.rng.clone();
+                rng.mutate_start(back.attempts);
+                work = back.snapshot.work.clone();
+                symbols.truncate(back.snapshot.symbols_len);
+                events.truncate(back.snapshot.events_len);
+                alternatives.truncate(back.snapshot.alternatives_len);
+                string.truncate(back.snapshot.string_len);
+                stack = back.snapshot.stack.clone();
+                let len = string.len();
+                for earlier in &mut placed {
+                    earlier.truncate(len);
+                }
+            }
+        }
+
+        let mut offsets = Vec::with_capacity(string.len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for ch in &string {
+            offset += ch.len_utf8();
+            offsets.push(offset);
+        }
+        Ok(Generated {
+            symbols,
+            string: string.into_iter().collect(),
+            rejections,
+            trees: derivation::build(&events, &offsets),
+            alternatives,
+        })
+    }
+
+    /// Returns all strings derived from `start` with at most `max_len` characters, in
+    /// order of increasing length. `to_char` maps terminal symbols to characters.
+    pub fn enumerate<F>(&self, start: Symbol, max_len: usize, to_char: F) -> Enumeration
+    where
+        F: Fn(Symbol) -> Option<char>,
+    {
+        Enumeration::new(self, start, max_len, to_char)
+    }
+
+    /// Updates the constraints placed so far and returns the index of the earliest
+    /// violated one.
+    fn first_violated<R>(
+        &self,
+        placed: &mut [Placed<'_, R>],
+        string: &[char],
+        at_end: bool,
+    ) -> Option<usize> {
+        for (idx, p) in placed.iter_mut().enumerate() {
+            if p.resolved_at.is_some() {
+                continue;
+            }
+            match p.check(string, at_end) {
+                Check::Pending => {}
+                Check::Satisfied => p.resolved_at = Some(string.len()),
+                Check::Violated => return Some(idx),
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,136 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! The pest stack during generation.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+use core::ops::Range;
+
+use cfg::prelude::*;
+
+use crate::parser_state::constrain_idxs;
+
+/// An operation on the stack, performed when generation reaches `sym`.
+#[derive(Copy, Clone, Debug)]
+pub struct StackRule {
+    /// The nulling symbol that marks the operation.
+    pub sym: Symbol,
+    /// The operation.
+    pub op: StackOp,
+}
+
+/// An operation on the stack.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StackOp {
+    /// Starts capturing the text of a `PUSH(e)`.
+    PushStart,
+    /// Pushes the text generated since the matching `PushStart`.
+    Push,
+    /// Generates the text at the top of the stack, i.e. `PEEK`.
+    Peek,
+    /// Generates the whole stack from top to bottom, i.e. `PEEK_ALL`.
+    PeekAll,
+    /// Generates a slice of the stack from bottom to top, i.e. `PEEK[start..end]`.
+    PeekSlice(i32, Option<i32>),
+    /// Generates the text at the top of the stack and pops it, i.e. `POP`.
+    Pop,
+    /// Generates the whole stack from top to bottom and clears it, i.e. `POP_ALL`.
+    PopAll,
+    /// Pops the top of the stack without generating anything, i.e. `DROP`.
+    Drop,
+}
+
+/// The stack, with entries stored as spans of the generated text.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stack {
+    entries: Vec<Range<usize>>,
+    pushing: Vec<usize>,
+}
+
+impl Stack {
+    /// Performs `op` at the end of `string` and returns the text to generate, or
+    /// `None` if the stack has too few entries.
+    pub(crate) fn apply(&mut self, op: StackOp, string: &[char]) -> Option<Vec<char>> {
+        let text = |spans: &mut dyn Iterator<Item = &Range<usize>>| {
+            spans
+                .flat_map(|span| string[span.clone()].iter().cloned())
+                .collect()
+        };
+        let result = match op {
+            StackOp::PushStart => {
+                self.pushing.push(string.len());
+                vec![]
+            }
+            StackOp::Push => {
+                let start = self.pushing.pop()?;
+                self.entries.push(start..string.len());
+                vec![]
+            }
+            StackOp::Peek => text(&mut iter::once(self.entries.last()?)),
+            StackOp::PeekAll => text(&mut self.entries.iter().rev()),
+            StackOp::PeekSlice(start, end) => {
+                let range = constrain_idxs(start, end, self.entries.len())?;
+                if range.end <= range.start {
+                    vec![]
+                } else {
+                    text(&mut self.entries[range].iter())
+                }
+            }
+            StackOp::Pop => {
+                let top = self.entries.pop()?;
+                string[top].to_vec()
+            }
+            StackOp::PopAll => {
+                let result = text(&mut self.entries.iter().rev());
+                self.entries.clear();
+                result
+            }
+            StackOp::Drop => {
+                self.entries.pop()?;
+                vec![]
+            }
+        };
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    fn apply(stack: &mut Stack, op: StackOp, string: &mut Vec<char>) -> Option<()> {
+        let text = stack.apply(op, string)?;
+        string.extend(text);
+        Some(())
+    }
+
+    #[test]
+    fn push_peek_pop() {
+        let mut stack = Stack::default();
+        let mut string = vec![];
+        apply(&mut stack, StackOp::PushStart, &mut string).unwrap();
+        string.extend("ab".chars());
+        apply(&mut stack, StackOp::Push, &mut string).unwrap();
+        apply(&mut stack, StackOp::PushStart, &mut string).unwrap();
+        string.push('c');
+        apply(&mut stack, StackOp::Push, &mut string).unwrap();
+        apply(&mut stack, StackOp::Peek, &mut string).unwrap();
+        apply(&mut stack, StackOp::PeekAll, &mut string).unwrap();
+        apply(&mut stack, StackOp::PeekSlice(0, Some(-1)), &mut string).unwrap();
+        apply(&mut stack, StackOp::Pop, &mut string).unwrap();
+        apply(&mut stack, StackOp::Drop, &mut string).unwrap();
+        assert_eq!(string.iter().collect::<String>(), "abcccababc");
+        assert_eq!(apply(&mut stack, StackOp::Peek, &mut string), None);
+        assert_eq!(apply(&mut stack, StackOp::Pop, &mut string), None);
+        assert_eq!(apply(&mut stack, StackOp::Drop, &mut string), None);
+    }
+}
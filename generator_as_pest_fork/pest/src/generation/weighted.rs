@@ -0,0 +1,211 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use cfg::generation::weighted::random::GenRange;
+use cfg::history::LinkedHistoryNode;
+use cfg::prelude::*;
+
+#[cfg(test)]
+use super::Alternative;
+use super::Guide;
+
+/// A production with its weight and the sum of the weights of the productions
+/// before it.
+#[derive(Clone, Debug)]
+struct WeightedRhs {
+    before: f64,
+    weight: f64,
+    rhs: Vec<Symbol>,
+}
+
+/// The productions of a single symbol.
+#[derive(Clone, Debug, Default)]
+struct RhsList {
+    total_weight: f64,
+    rhs_list: Vec<WeightedRhs>,
+}
+
+/// Weighted choice of productions.
+///
+/// This makes the same choices as `cfg`'s `WeightedRhsByLhs`, but tells which
+/// production of the symbol was picked.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Weighted {
+    rhs_by_lhs: BTreeMap<Symbol, RhsList>,
+}
+
+impl Weighted {
+    pub(crate) fn new(grammar: &BinarizedCfg) -> Self {
+        let mut weighted = Weighted::default();
+        for rule in grammar.rules() {
+            let mut history_id = rule.history_id();
+            let mut weight = 1.0;
+            while let HistoryNode::Linked { prev, ref node } =
+                grammar.history_graph()[history_id.get()]
+            {
+                if let LinkedHistoryNode::Weight { weight: linked } = *node {
+                    weight = linked;
+                    break;
+                }
+                history_id = prev;
+            }
+            let rhs_list = weighted.rhs_by_lhs.entry(rule.lhs()).or_default();
+            rhs_list.rhs_list.push(WeightedRhs {
+                before: rhs_list.total_weight,
+                weight,
+                rhs: rule.rhs().to_vec(),
+            });
+            rhs_list.total_weight += weight;
+        }
+        weighted
+    }
+
+    /// Returns whether `lhs` has more than one production to pick from.
+    pub(crate) fn is_choice(&self, lhs: Symbol) -> bool {
+        self.rhs_by_lhs
+            .get(&lhs)
+            .map_or(false, |rhs_list| rhs_list.rhs_list.len() > 1)
+    }
+
+    /// Picks a production of `lhs`, returning its index among the productions
+    /// of `lhs` in the grammar and its right-hand side. With a `guide` that has
+    /// seen some productions of `lhs`, the ones it hasn't seen are more likely.
+    pub(crate) fn pick<R: GenRange>(
+        &self,
+        lhs: Symbol,
+        rng: &mut R,
+        guide: Option<&Guide>,
+    ) -> (usize, &[Symbol]) {
+        let rhs_list = match self.rhs_by_lhs.get(&lhs) {
+            Some(rhs_list) => rhs_list,
+            None => return (0, &[]),
+        };
+        if rhs_list.rhs_list.len() == 1 {
+            return (0, &rhs_list.rhs_list[0].rhs[..]);
+        }
+        if let Some(unvisited) =
+            guide.and_then(|guide| guide.unvisited(lhs, rhs_list.rhs_list.len()))
+        {
+            let weights: Vec<f64> = rhs_list
+                .rhs_list
+                .iter()
+                .zip(unvisited)
+                .map(|(weighted_rhs, unvisited)| {
+                    if unvisited {
+                        rhs_list.total_weight
+                    } else {
+                        weighted_rhs.weight
+                    }
+                })
+                .collect();
+            let mut value = rng.gen(weights.iter().sum());
+            let mut idx = 0;
+            for (i, &weight) in weights.iter().enumerate() {
+                if weight > 0.0 {
+                    idx = i;
+                    if value < weight {
+                        break;
+                    }
+                    value -= weight;
+                }
+            }
+            return (idx, &rhs_list.rhs_list[idx].rhs[..]);
+        }
+        let value = rng.gen(rhs_list.total_weight);
+        let idx = match rhs_list.rhs_list.binary_search_by(|weighted_rhs| {
+            weighted_rhs
+                .before
+                .partial_cmp(&value)
+                .expect("invalid float")
+        }) {
+            Ok(idx) | Err(idx) => idx.saturating_sub(1),
+        };
+        (idx, &rhs_list.rhs_list[idx].rhs[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cfg::generation::weighted::random::ByteSource;
+
+    #[test]
+    fn same_as_weighted_rhs_by_lhs() {
+        let mut grammar = Cfg::new();
+        let (start, a, b, c) = grammar.sym();
+        grammar
+            .rule(start)
+            .rhs_with_linked_history([a], LinkedHistoryNode::Weight { weight: 3.0 })
+            .rhs([b])
+            .rhs_with_linked_history([c, a], LinkedHistoryNode::Weight { weight: 0.5 });
+        let binarized = grammar.binarize();
+        let weighted = Weighted::new(&binarized);
+        let expected = binarized.weighted();
+        let bytes: Vec<u8> = (0..=255).collect();
+        let mut rng = ByteSource::new(bytes.iter().cloned());
+        let mut expected_rng = ByteSource::new(bytes.iter().cloned());
+        let mut picked = [false; 3];
+        for _ in 0..=255 {
+            let (idx, rhs) = weighted.pick(start, &mut rng, None);
+            assert_eq!(rhs, expected.pick_rhs(start, &mut expected_rng));
+            picked[idx] = true;
+        }
+        assert_eq!(picked, [true; 3]);
+    }
+
+    #[test]
+    fn guided_favors_unvisited() {
+        let mut grammar = Cfg::new();
+        let (start, a, b) = grammar.sym();
+        grammar
+            .rule(start)
+            .rhs_with_linked_history([a], LinkedHistoryNode::Weight { weight: 8.0 })
+            .rhs([b]);
+        let binarized = grammar.binarize();
+        let weighted = Weighted::new(&binarized);
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        let mut guide = Guide::new();
+        let mut rng = ByteSource::new(bytes.iter().cloned());
+        let mut expected_rng = ByteSource::new(bytes.iter().cloned());
+        for _ in 0..=255 {
+            assert_eq!(
+                weighted.pick(start, &mut rng, Some(&guide)),
+                weighted.pick(start, &mut expected_rng, None)
+            );
+        }
+
+        guide.record(Alternative {
+            sym: start,
+            index: 0,
+        });
+        let mut rng = ByteSource::new(bytes.iter().cloned());
+        let picked_b = (0..=255)
+            .filter(|_| weighted.pick(start, &mut rng, Some(&guide)).0 == 1)
+            .count();
+        assert!(picked_b > 128, "{}", picked_b);
+
+        guide.record(Alternative {
+            sym: start,
+            index: 1,
+        });
+        let mut rng = ByteSource::new(bytes.iter().cloned());
+        let mut expected_rng = ByteSource::new(bytes.iter().cloned());
+        for _ in 0..=255 {
+            assert_eq!(
+                weighted.pick(start, &mut rng, Some(&guide)),
+                weighted.pick(start, &mut expected_rng, None)
+            );
+        }
+    }
+}
@@ -358,6 +358,7 @@ use core::fmt::Debug;
 use core::hash::Hash;
 
 pub mod error;
+pub mod generation;
 pub mod iterators;
 mod macros;
 mod parser;
@@ -372,6 +373,7 @@ While prec_climber is going to be kept in 2.x minor and patch releases, it may b
 pub mod prec_climber;
 mod span;
 mod stack;
+pub mod syntax;
 mod token;
 
 #[doc(hidden)]
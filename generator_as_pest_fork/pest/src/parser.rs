@@ -8,8 +8,10 @@
 // modified, or distributed except according to those terms.
 
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use crate::error::Error;
+use crate::generation::{Derivation, GenerationError, Guide};
 use crate::iterators::Pairs;
 use crate::RuleType;
 
@@ -19,7 +21,51 @@ pub trait Parser<R: RuleType> {
     #[allow(clippy::perf)]
     fn parse(rule: R, input: &str) -> Result<Pairs<'_, R>, Error<R>>;
 
-    fn generate(start_sym: &str, driver: &[u8], limit: Option<u64>) -> Result<String, ()> {
-        unimplemented!()
+    /// Generates a string derived from the rule named `start_sym`, driven by the bytes
+    /// of `driver`, with at most `limit` terminals. Parsers without generation know no
+    /// start rules.
+    fn generate(
+        start_sym: &str,
+        _driver: &[u8],
+        _limit: Option<u64>,
+    ) -> Result<Derivation<R>, GenerationError> {
+        Err(GenerationError::UnknownStartRule(start_sym.into()))
+    }
+
+    /// Like [`Parser::generate`], but favors the choices that `guide` hasn't seen and
+    /// records the choices made in `guide`. Parsers without generation know no start
+    /// rules.
+    fn generate_guided(
+        start_sym: &str,
+        _driver: &[u8],
+        _limit: Option<u64>,
+        _guide: &mut Guide,
+    ) -> Result<Derivation<R>, GenerationError> {
+        Err(GenerationError::UnknownStartRule(start_sym.into()))
+    }
+
+    /// Returns all strings that the rule named `start_sym` matches with at most
+    /// `max_len` characters, shortest first, as far as generation can derive them.
+    /// Characters from ranges and classes are represented by a single one. Parsers
+    /// without generation know no start rules.
+    fn generate_all(start_sym: &str, _max_len: usize) -> Result<Vec<String>, GenerationError> {
+        Err(GenerationError::UnknownStartRule(start_sym.into()))
+    }
+
+    /// Returns the strings that the grammar matches literally, e.g. keywords and
+    /// punctuation, in order.
+    fn literals() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the names of the rules that derivations record, in order.
+    fn rule_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the branches of choices in the grammar as the name of the rule and
+    /// the branch's expression. `Derivation::alternatives` indexes into these.
+    fn alternatives() -> &'static [(&'static str, &'static str)] {
+        &[]
     }
 }
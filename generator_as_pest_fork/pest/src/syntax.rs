@@ -0,0 +1,187 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Typed syntax trees over [`Pair`]s, in the style of rust-analyzer's AST.
+//!
+//! `pest_derive` generates a module of nodes with the `#[syntax_tree = "name"]`
+//! attribute: a struct for each rule that produces pairs, and an enum for each
+//! silent rule that is a choice between such rules. Each node implements
+//! [`AstNode`] and has accessors for the rules and node tags it contains, which
+//! are built on [`child`], [`children`] and [`tagged_child`].
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::iterators::{Pair, Pairs};
+use crate::RuleType;
+
+/// A typed node of a syntax tree, wrapping the pair of a rule.
+pub trait AstNode<'i, R: RuleType>: Sized {
+    /// Returns whether the pairs of `rule` can be wrapped into this node.
+    fn can_cast(rule: R) -> bool;
+
+    /// Wraps `pair` into this node, if its rule can be.
+    fn cast(pair: Pair<'i, R>) -> Option<Self>;
+
+    /// Returns the wrapped pair.
+    fn pair(&self) -> &Pair<'i, R>;
+
+    /// Returns the text that the node spans.
+    fn text(&self) -> &'i str {
+        self.pair().as_str()
+    }
+}
+
+/// An iterator over the inner pairs of a pair that can be wrapped into `N`, created
+/// by [`children`].
+pub struct AstChildren<'i, R, N> {
+    inner: Pairs<'i, R>,
+    phantom: PhantomData<N>,
+}
+
+impl<'i, R: RuleType, N: AstNode<'i, R>> Iterator for AstChildren<'i, R, N> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        self.inner.by_ref().find_map(N::cast)
+    }
+}
+
+impl<'i, R: RuleType, N> Clone for AstChildren<'i, R, N> {
+    fn clone(&self) -> Self {
+        AstChildren {
+            inner: self.inner.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'i, R: RuleType, N> fmt::Debug for AstChildren<'i, R, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AstChildren")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Returns the inner pairs of `pair` that can be wrapped into `N`.
+pub fn children<'i, R: RuleType, N: AstNode<'i, R>>(pair: &Pair<'i, R>) -> AstChildren<'i, R, N> {
+    AstChildren {
+        inner: pair.clone().into_inner(),
+        phantom: PhantomData,
+    }
+}
+
+/// Returns the first inner pair of `pair` that can be wrapped into `N`.
+pub fn child<'i, R: RuleType, N: AstNode<'i, R>>(pair: &Pair<'i, R>) -> Option<N> {
+    children(pair).next()
+}
+
+/// Returns the first inner pair of `pair` tagged with `tag`, if it can be wrapped
+/// into `N`.
+pub fn tagged_child<'i, R: RuleType, N: AstNode<'i, R>>(
+    pair: &Pair<'i, R>,
+    tag: &str,
+) -> Option<N> {
+    pair.clone()
+        .into_inner()
+        .find(|inner| inner.as_node_tag() == Some(tag))
+        .and_then(N::cast)
+}
+
+/// Any pair, which is how nodes refer to a tagged expression that is not a single
+/// rule.
+impl<'i, R: RuleType> AstNode<'i, R> for Pair<'i, R> {
+    fn can_cast(_rule: R) -> bool {
+        true
+    }
+
+    fn cast(pair: Pair<'i, R>) -> Option<Self> {
+        Some(pair)
+    }
+
+    fn pair(&self) -> &Pair<'i, R> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+    use alloc::borrow::Cow;
+    use alloc::vec::Vec;
+
+    #[allow(non_camel_case_types)]
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    enum Rule {
+        list,
+        digit,
+        letter,
+    }
+
+    struct Digit<'i>(Pair<'i, Rule>);
+
+    impl<'i> AstNode<'i, Rule> for Digit<'i> {
+        fn can_cast(rule: Rule) -> bool {
+            rule == Rule::digit
+        }
+
+        fn cast(pair: Pair<'i, Rule>) -> Option<Self> {
+            if Self::can_cast(pair.as_rule()) {
+                Some(Digit(pair))
+            } else {
+                None
+            }
+        }
+
+        fn pair(&self) -> &Pair<'i, Rule> {
+            &self.0
+        }
+    }
+
+    fn list(input: &str) -> Pair<'_, Rule> {
+        state(input, |state| {
+            state.rule(Rule::list, |state| {
+                state.repeat(|state| {
+                    state
+                        .rule(Rule::digit, |state| state.match_range('0'..'4'))
+                        .or_else(|state| {
+                            state.rule(Rule::letter, |state| state.match_range('a'..'b'))
+                        })
+                        .and_then(|state| {
+                            if state.position().pos() == 3 {
+                                state.tag_node(Cow::Borrowed("third"))
+                            } else {
+                                Ok(state)
+                            }
+                        })
+                })
+            })
+        })
+        .unwrap()
+        .next()
+        .unwrap()
+    }
+
+    #[test]
+    fn typed_children() {
+        let list = list("1a23");
+        let digits: Vec<&str> = children::<_, Digit<'_>>(&list).map(|d| d.text()).collect();
+        assert_eq!(digits, ["1", "2", "3"]);
+        assert_eq!(child::<_, Digit<'_>>(&list).unwrap().text(), "1");
+        assert_eq!(
+            tagged_child::<_, Digit<'_>>(&list, "third").unwrap().text(),
+            "2"
+        );
+        assert!(tagged_child::<_, Digit<'_>>(&list, "first").is_none());
+        let third: Pair<'_, Rule> = tagged_child(&list, "third").unwrap();
+        assert_eq!(third.as_rule(), Rule::digit);
+    }
+}
@@ -11,6 +11,7 @@
 extern crate pest;
 
 use pest::error::Error;
+use pest::generation::{GenerationError, Guide};
 use pest::iterators::{Pair, Pairs};
 use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest::{state, ParseResult, Parser, ParserState};
@@ -241,3 +242,28 @@ fn pratt_parse() {
         )
     );
 }
+
+#[test]
+fn generate_without_generation() {
+    assert_eq!(
+        CalculatorParser::generate("expression", &[0], None).err(),
+        Some(GenerationError::UnknownStartRule("expression".to_string()))
+    );
+}
+
+#[test]
+fn generate_guided_without_generation() {
+    let mut guide = Guide::new();
+    assert_eq!(
+        CalculatorParser::generate_guided("expression", &[0], None, &mut guide).err(),
+        Some(GenerationError::UnknownStartRule("expression".to_string()))
+    );
+}
+
+#[test]
+fn generate_all_without_generation() {
+    assert_eq!(
+        CalculatorParser::generate_all("expression", 4),
+        Err(GenerationError::UnknownStartRule("expression".to_string()))
+    );
+}
@@ -17,10 +17,12 @@
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
 
 use pest::error::Error;
+use pest::generation::{Derivation, GenerationError};
 use pest::iterators::Pairs;
 use pest::{unicode, Position};
 use pest::{Atomicity, MatchDir, ParseResult, ParserState};
 use pest_meta::ast::RuleType;
+use pest_generator::CfgGenerator;
 use pest_meta::optimizer::{OptimizedExpr, OptimizedRule};
 
 use std::collections::HashMap;
@@ -38,15 +40,19 @@ type ListenerFn =
 /// A virtual machine-like construct that runs an AST on-the-fly
 pub struct Vm {
     rules: HashMap<String, OptimizedRule>,
+    /// Rule names in the order of the grammar, which generation follows.
+    rule_names: Vec<String>,
     listener: Option<ListenerFn>,
 }
 
 impl Vm {
     /// Creates a new `Vm` from optimized rules
     pub fn new(rules: Vec<OptimizedRule>) -> Vm {
+        let rule_names = rules.iter().map(|r| r.name.clone()).collect();
         let rules = rules.into_iter().map(|r| (r.name.clone(), r)).collect();
         Vm {
             rules,
+            rule_names,
             listener: None,
         }
     }
@@ -55,9 +61,11 @@ impl Vm {
     /// and a listener function that is called when a rule is matched.
     /// (used by the `pest_debugger` crate)
     pub fn new_with_listener(rules: Vec<OptimizedRule>, listener: ListenerFn) -> Vm {
+        let rule_names = rules.iter().map(|r| r.name.clone()).collect();
         let rules = rules.into_iter().map(|r| (r.name.clone(), r)).collect();
         Vm {
             rules,
+            rule_names,
             listener: Some(listener),
         }
     }
@@ -72,6 +80,46 @@ impl Vm {
         pest::state(input, |state| self.parse_rule(rule, state))
     }
 
+    /// Generates a string derived from `start_rule`, driven by the bytes of `driver`,
+    /// like the `generate` function of a derived parser for the same grammar
+    pub fn generate(
+        &self,
+        start_rule: &str,
+        driver: &[u8],
+        limit: Option<u64>,
+    ) -> Result<Derivation<String>, GenerationError> {
+        let rules: Vec<OptimizedRule> = self
+            .rule_names
+            .iter()
+            .map(|name| self.rules[name].clone())
+            .collect();
+        CfgGenerator::new(&rules).generate(start_rule, driver, limit)
+    }
+
+    /// Returns all strings that `start_rule` matches with at most `max_len` characters,
+    /// shortest first, like the `generate_all` function of a derived parser for the same grammar
+    pub fn generate_all(
+        &self,
+        start_rule: &str,
+        max_len: usize,
+    ) -> Result<Vec<String>, GenerationError> {
+        let rules: Vec<OptimizedRule> = self
+            .rule_names
+            .iter()
+            .map(|name| self.rules[name].clone())
+            .collect();
+        let strings = CfgGenerator::new(&rules).enumerate(start_rule, max_len)?;
+        Ok(strings
+            .into_iter()
+            .filter(|string| match self.parse(start_rule, string) {
+                Ok(pairs) => pairs
+                    .last()
+                    .map_or(string.is_empty(), |pair| pair.as_span().end() == string.len()),
+                Err(_) => false,
+            })
+            .collect())
+    }
+
     #[allow(clippy::suspicious)]
     fn parse_rule<'a, 'i>(
         &'a self,
@@ -224,6 +272,41 @@ impl Vm {
                     })
                 })
             }),
+            OptimizedExpr::Separated(ref expr, ref separator, separation) => {
+                let separated = |state: Box<ParserState<'i, &'a str>>| {
+                    state.sequence(|state| {
+                        self.parse_expr(expr, state)
+                            .and_then(|state| {
+                                state.repeat(|state| {
+                                    state.sequence(|state| {
+                                        self.skip(state)
+                                            .and_then(|state| self.parse_expr(separator, state))
+                                            .and_then(|state| self.skip(state))
+                                            .and_then(|state| self.parse_expr(expr, state))
+                                    })
+                                })
+                            })
+                            .and_then(|state| {
+                                if separation.allow_trailing {
+                                    state.optional(|state| {
+                                        state.sequence(|state| {
+                                            self.skip(state)
+                                                .and_then(|state| self.parse_expr(separator, state))
+                                        })
+                                    })
+                                } else {
+                                    Ok(state)
+                                }
+                            })
+                    })
+                };
+
+                if separation.allow_empty {
+                    state.optional(separated)
+                } else {
+                    separated(state)
+                }
+            }
             OptimizedExpr::Push(ref expr) => state.stack_push(|state| self.parse_expr(expr, state)),
             OptimizedExpr::Skip(ref strings) => state.skip_until(
                 &strings
@@ -0,0 +1,73 @@
+// pest. The Elegant Parser
+// Copyright (c) 2018 Dragoș Tiselice
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+extern crate pest;
+extern crate pest_meta;
+extern crate pest_vm;
+
+use pest::generation::GenerationError;
+use pest_meta::parser::Rule;
+use pest_meta::{optimizer, parser};
+use pest_vm::Vm;
+
+const GRAMMAR: &str = include_str!("lists.pest");
+
+fn vm(grammar: &str) -> Vm {
+    let pairs = parser::parse(Rule::grammar_rules, grammar).unwrap();
+    let ast = parser::consume_rules(pairs).unwrap();
+    Vm::new(optimizer::optimize(ast))
+}
+
+#[test]
+fn generated_lists_parse() {
+    let vm = vm(GRAMMAR);
+    for byte in 0..=255u8 {
+        let driver: Vec<u8> = (0..64u8).map(|i| byte.wrapping_mul(i | 1)).collect();
+        let generated = vm.generate("lists", &driver, Some(1_000)).unwrap().string;
+        assert!(vm.parse("lists", &generated).is_ok(), "{:?}", generated);
+    }
+}
+
+#[test]
+fn weights_and_lookahead() {
+    let vm = vm(r#"
+        weighted = { W:9 ~ "x" | "y" }
+        keyword = @{ ("fn" | "let") ~ !ASCII_ALPHANUMERIC }
+        identifier = @{ !keyword ~ ("f" | "n" | "l" | "e" | "t")+ }
+    "#);
+    let string = |driver: &[u8]| vm.generate("weighted", driver, None).map(|d| d.string);
+    assert_eq!(string(&[200]), Ok("x".to_owned()));
+    assert_eq!(string(&[250]), Ok("y".to_owned()));
+    for byte in 0..=255u8 {
+        let driver: Vec<u8> = (0..16u8).map(|i| byte.wrapping_mul(i | 1)).collect();
+        let generated = vm.generate("identifier", &driver, None).unwrap().string;
+        assert!(vm.parse("identifier", &generated).is_ok(), "{:?}", generated);
+    }
+}
+
+#[test]
+fn unknown_start_rule() {
+    let vm = vm(GRAMMAR);
+    assert_eq!(
+        vm.generate("nope", &[], None),
+        Err(GenerationError::UnknownStartRule("nope".to_owned()))
+    );
+}
+
+#[test]
+fn generate_all_by_increasing_length() {
+    let vm = vm(r#"
+        keyword = @{ ("fn" | "let") ~ !ASCII_ALPHANUMERIC }
+        identifier = @{ !keyword ~ ("f" | "n" | "l" | "e" | "t")+ }
+    "#);
+    let strings = vm.generate_all("identifier", 2).unwrap();
+    assert_eq!(strings.len(), 5 + 25 - 1, "{:?}", strings);
+    assert_eq!(&strings[..5], ["e", "f", "l", "n", "t"]);
+    assert!(!strings.contains(&"fn".to_owned()));
+}
@@ -656,6 +656,98 @@ fn repeat_max_atomic_space() {
     };
 }
 
+#[test]
+fn separated_strings() {
+    parses_to! {
+        parser: vm(),
+        input: "abc , abc",
+        rule: "separated",
+        tokens: [
+            separated(0, 9, [
+                string(0, 3),
+                string(6, 9)
+            ])
+        ]
+    };
+}
+
+#[test]
+fn separated_trailing() {
+    parses_to! {
+        parser: vm(),
+        input: "abc,abc,",
+        rule: "separated",
+        tokens: [
+            separated(0, 7, [
+                string(0, 3),
+                string(4, 7)
+            ])
+        ]
+    };
+}
+
+#[test]
+fn separated_atomic_trailing() {
+    parses_to! {
+        parser: vm(),
+        input: "abc,abc,",
+        rule: "separated_atomic",
+        tokens: [
+            separated_atomic(0, 8)
+        ]
+    };
+}
+
+#[test]
+#[should_panic]
+fn separated_atomic_space() {
+    parses_to! {
+        parser: vm(),
+        input: "abc, abc",
+        rule: "separated_atomic",
+        tokens: []
+    };
+}
+
+#[test]
+fn separated_empty_empty() {
+    parses_to! {
+        parser: vm(),
+        input: "",
+        rule: "separated_empty",
+        tokens: [
+            separated_empty(0, 0)
+        ]
+    };
+}
+
+#[test]
+fn separated_empty_trailing() {
+    parses_to! {
+        parser: vm(),
+        input: "abc, abc ,",
+        rule: "separated_empty",
+        tokens: [
+            separated_empty(0, 10, [
+                string(0, 3),
+                string(5, 8)
+            ])
+        ]
+    };
+}
+
+#[test]
+fn separated_mutate_stack() {
+    parses_to! {
+        parser: vm(),
+        input: "a!,ba",
+        rule: "separated_mutate_stack",
+        tokens: [
+            separated_mutate_stack(0, 5)
+        ]
+    };
+}
+
 #[test]
 fn repeat_comment() {
     parses_to! {
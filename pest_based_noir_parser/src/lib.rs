@@ -5,9 +5,12 @@ extern crate pest_derive;
 use pest::Parser;
 
 #[derive(Parser)]
-#[grammar = "../../grammar.pest"]
+#[grammar_bnf = "../../grammar.bnf"]
+#[syntax_tree = "syntax"]
 pub struct NoirParser;
 
+pub mod noir_ast;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,10 +45,8 @@ r#"for i in 0 {
 
     #[test]
     fn test_generate() {
-        // let code = r#"0..(C1-1)"#;
-        let code = NoirParser::generate("module", &[200, 200, 200, 200]);
-        eprintln!("{:?}", code);
-        assert_eq!(code, "    fn a  (   )  {   }    fn a  (   )  {   }    fn a  (   )  {   }");
+        let code = NoirParser::generate("module", &[200, 200, 200, 200], None).unwrap().string;
+        assert_eq!(code, "   fn a  (  )  {   }   fn a  (  )  {   }   fn a  (  )  {   }   fn a  (  )  {   }");
     }
 
     // #[test]
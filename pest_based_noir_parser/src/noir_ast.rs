@@ -0,0 +1,1048 @@
+//! A typed AST of Noir programs, lowered from the parse tree of `start`.
+//!
+//! The types follow the shape of `noirc_frontend`'s `ParsedModule`, so that both
+//! parsers can be compared structurally. The grammar matches binary operators as a
+//! flat list, which the lowering folds with Noir's precedence. Everything else is
+//! kept as written, e.g. parentheses and compound assignments.
+
+use pest::error::Error;
+use pest::iterators::Pair;
+use pest::syntax::AstNode;
+use pest::Parser;
+
+use crate::syntax;
+use crate::{NoirParser, Rule};
+
+pub type Ident = String;
+
+/// A module, i.e. the items of a file or of `mod name { ... }`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Module {
+    pub items: Vec<Item>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Item {
+    Function(Function),
+    Struct(Struct),
+    Impl(Impl),
+    Submodule(Submodule),
+    /// `mod name;`
+    ModuleDeclaration(Ident),
+    Use(UseStatement),
+    Global(Global),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Function {
+    /// The contents of the attribute, e.g. `test` for `#[test]`.
+    pub attribute: Option<String>,
+    pub is_unconstrained: bool,
+    pub is_open: bool,
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    pub parameters: Vec<Parameter>,
+    pub return_visibility: Visibility,
+    pub return_type: Option<Type>,
+    pub body: Block,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Visibility {
+    Private,
+    Public,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Parameter {
+    /// `self` in a method.
+    SelfValue,
+    Typed {
+        pattern: Pattern,
+        visibility: Visibility,
+        typ: Type,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Struct {
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    pub fields: Vec<(Ident, Type)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Impl {
+    pub generics: Vec<Ident>,
+    pub typ: Type,
+    pub methods: Vec<Function>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Submodule {
+    pub name: Ident,
+    pub contents: Module,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UseStatement {
+    pub path: Path,
+    pub alias: Option<Ident>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Global {
+    pub name: Ident,
+    pub typ: Option<Type>,
+    pub value: Literal,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Path {
+    pub kind: PathKind,
+    pub segments: Vec<Ident>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathKind {
+    Plain,
+    /// `crate::a`
+    Crate,
+    /// `dep::a`
+    Dep,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Type {
+    Field,
+    Bool,
+    /// An unsigned integer type with the given number of bits, e.g. `u8`.
+    Integer(u32),
+    /// `str` or `str<N>`
+    String(Option<TypeExpression>),
+    Named(Path, Vec<Type>),
+    /// `Vec<T>`
+    Vec(Vec<Type>),
+    /// `[T]` or `[T; N]`
+    Array(Option<TypeExpression>, Box<Type>),
+    Tuple(Vec<Type>),
+    Function(Vec<Type>, Box<Type>),
+    /// A generic argument that is an expression, e.g. `N + 1` in `Foo<N + 1>`.
+    Expression(TypeExpression),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeExpression {
+    Variable(Path),
+    Constant(u128),
+    Negation(Box<TypeExpression>),
+    Binary(Box<TypeExpression>, BinaryOp, Box<TypeExpression>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Pattern {
+    Identifier(Ident),
+    Mutable(Box<Pattern>),
+    Tuple(Vec<Pattern>),
+    /// `Foo { a, b: c }`, where the field `a` has the pattern `a`.
+    Struct(Path, Vec<(Ident, Pattern)>),
+}
+
+/// A block, where the last statement without a semicolon is its value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Statement {
+    Let {
+        pattern: Pattern,
+        typ: Option<Type>,
+        value: Expression,
+    },
+    /// `a = b`, or `a += b` with the operator.
+    Assign {
+        lvalue: LValue,
+        operator: Option<BinaryOp>,
+        value: Expression,
+    },
+    /// An expression without a semicolon.
+    Expression(Expression),
+    /// An expression followed by a semicolon.
+    Semi(Expression),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LValue {
+    Ident(Ident),
+    MemberAccess(Box<LValue>, Ident),
+    Index(Box<LValue>, Expression),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expression {
+    Literal(Literal),
+    Variable(Path),
+    Prefix(UnaryOp, Box<Expression>),
+    Infix(Box<Expression>, BinaryOp, Box<Expression>),
+    Call(Box<Expression>, Vec<Expression>),
+    MethodCall(Box<Expression>, Ident, Vec<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    Cast(Box<Expression>, Type),
+    /// `a.b`, where `b` may be a tuple index like `0`.
+    MemberAccess(Box<Expression>, Ident),
+    /// `Foo { a, b: c }`, where the field `a` has the value `a`.
+    Constructor(Path, Vec<(Ident, Expression)>),
+    If {
+        condition: Box<Expression>,
+        consequence: Block,
+        alternative: Option<Box<Expression>>,
+    },
+    Lambda {
+        parameters: Vec<Pattern>,
+        return_type: Option<Type>,
+        body: Box<Expression>,
+    },
+    Block(Block),
+    /// `(a)`, which is not a tuple.
+    Parenthesized(Box<Expression>),
+    /// `()`, `(a,)` or `(a, b)`
+    Tuple(Vec<Expression>),
+    /// `[a, b]`
+    Array(Vec<Expression>),
+    /// `[a; n]`
+    RepeatedArray(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Literal {
+    Integer(u128),
+    Bool(bool),
+    Str(String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnaryOp {
+    Minus,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BinaryOp {
+    /// Returns the operator written as `symbol`.
+    pub fn from_symbol(symbol: &str) -> Option<BinaryOp> {
+        Some(match symbol {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Subtract,
+            "*" => BinaryOp::Multiply,
+            "/" => BinaryOp::Divide,
+            "%" => BinaryOp::Modulo,
+            "==" => BinaryOp::Equal,
+            "!=" => BinaryOp::NotEqual,
+            "<" => BinaryOp::Less,
+            "<=" => BinaryOp::LessEqual,
+            ">" => BinaryOp::Greater,
+            ">=" => BinaryOp::GreaterEqual,
+            "&" => BinaryOp::And,
+            "|" => BinaryOp::Or,
+            "^" => BinaryOp::Xor,
+            "<<" => BinaryOp::ShiftLeft,
+            ">>" => BinaryOp::ShiftRight,
+            _ => return None,
+        })
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::And => "&",
+            BinaryOp::Or => "|",
+            BinaryOp::Xor => "^",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+        }
+    }
+
+    /// Returns how tightly the operator binds, as in noirc's `Precedence`, where all
+    /// operators are left-associative.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Equal | BinaryOp::NotEqual => 0,
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Xor => 3,
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 4,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 5,
+            BinaryOp::Add | BinaryOp::Subtract => 6,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 7,
+        }
+    }
+}
+
+/// Parses `input` from `start` and lowers it.
+#[allow(clippy::result_large_err)]
+pub fn parse(input: &str) -> Result<Module, Error<Rule>> {
+    let start = NoirParser::parse(Rule::start, input)?.next().unwrap();
+    Ok(lower(start))
+}
+
+/// Lowers the parse tree of `start`.
+///
+/// Panics if `start` is not a `start` pair produced by `NoirParser`.
+pub fn lower(start: Pair<'_, Rule>) -> Module {
+    let start = syntax::Start::cast(start).expect("expected a start pair");
+    module(&required(required(start.program()).module()))
+}
+
+fn required<T>(node: Option<T>) -> T {
+    node.expect("the grammar guarantees the child")
+}
+
+/// Returns the inner pairs of `pair` without the implicit whitespace.
+fn inner<'i>(pair: &Pair<'i, Rule>) -> impl Iterator<Item = Pair<'i, Rule>> {
+    pair.clone()
+        .into_inner()
+        .filter(|pair| pair.as_rule() != Rule::WHITESPACE)
+}
+
+fn ident(ident: syntax::Ident<'_>) -> Ident {
+    ident.text().to_owned()
+}
+
+fn module(node: &syntax::Module<'_>) -> Module {
+    let items = node
+        .top_level_statements()
+        .map(|statement| {
+            let pair = required(inner(statement.pair()).next());
+            match pair.as_rule() {
+                Rule::function_definition_forbid_self => Item::Function(function(pair)),
+                Rule::struct_definition => {
+                    let definition = syntax::StructDefinition::cast(pair).unwrap();
+                    let fields = required(definition.struct_fields());
+                    Item::Struct(Struct {
+                        name: ident(required(definition.ident())),
+                        generics: generics(required(definition.generics())),
+                        fields: fields
+                            .idents()
+                            .map(ident)
+                            .zip(fields.types().map(typ))
+                            .collect(),
+                    })
+                }
+                Rule::implementation => {
+                    let implementation = syntax::Implementation::cast(pair).unwrap();
+                    Item::Impl(Impl {
+                        generics: generics(required(implementation.generics())),
+                        typ: typ(required(implementation.r#type())),
+                        methods: implementation
+                            .function_definition_allow_selfs()
+                            .map(|method| function(method.pair().clone()))
+                            .collect(),
+                    })
+                }
+                Rule::submodule => {
+                    let submodule = syntax::Submodule::cast(pair).unwrap();
+                    Item::Submodule(Submodule {
+                        name: ident(required(submodule.ident())),
+                        contents: module(&required(submodule.module())),
+                    })
+                }
+                Rule::module_declaration => {
+                    let declaration = syntax::ModuleDeclaration::cast(pair).unwrap();
+                    Item::ModuleDeclaration(ident(required(declaration.ident())))
+                }
+                Rule::use_statement => {
+                    let statement = syntax::UseStatement::cast(pair).unwrap();
+                    Item::Use(UseStatement {
+                        path: path(required(statement.path())),
+                        alias: statement.ident().map(ident),
+                    })
+                }
+                Rule::global_declaration => {
+                    let global = syntax::GlobalDeclaration::cast(pair).unwrap();
+                    Item::Global(Global {
+                        name: ident(required(global.ident())),
+                        typ: required(global.global_type_annotation()).r#type().map(typ),
+                        value: literal(required(global.literal())),
+                    })
+                }
+                rule => unreachable!("unexpected top-level statement {:?}", rule),
+            }
+        })
+        .collect();
+    Module { items }
+}
+
+/// Lowers either kind of function definition, which only differ in their parameters.
+fn function(pair: Pair<'_, Rule>) -> Function {
+    let mut function = Function {
+        attribute: None,
+        is_unconstrained: false,
+        is_open: false,
+        name: String::new(),
+        generics: vec![],
+        parameters: vec![],
+        return_visibility: Visibility::Private,
+        return_type: None,
+        body: Block { statements: vec![] },
+    };
+    for pair in inner(&pair) {
+        match pair.as_rule() {
+            Rule::attribute => {
+                let text = pair.as_str();
+                function.attribute = Some(text["#[".len()..text.len() - 1].to_owned());
+            }
+            Rule::function_modifiers => {
+                function.is_unconstrained = pair.as_str().contains("unconstrained");
+                function.is_open = pair.as_str().contains("open");
+            }
+            Rule::ident => function.name = pair.as_str().to_owned(),
+            Rule::generics => function.generics = generics(syntax::Generics::cast(pair).unwrap()),
+            Rule::function_parameters_forbid_self | Rule::function_parameters_allow_self => {
+                function.parameters = parameters(&pair)
+            }
+            Rule::function_return_type => {
+                let return_type = syntax::FunctionReturnType::cast(pair).unwrap();
+                if let Some(visibility_pair) = return_type.optional_visibility() {
+                    function.return_visibility = visibility(visibility_pair);
+                }
+                function.return_type = return_type.r#type().map(typ);
+            }
+            Rule::block => function.body = block(syntax::Block::cast(pair).unwrap()),
+            rule => unreachable!("unexpected function part {:?}", rule),
+        }
+    }
+    function
+}
+
+fn parameters(pair: &Pair<'_, Rule>) -> Vec<Parameter> {
+    let mut parameters = vec![];
+    let mut pairs = inner(pair);
+    while let Some(pair) = pairs.next() {
+        if pair.as_rule() == Rule::self_parameter {
+            parameters.push(Parameter::SelfValue);
+            continue;
+        }
+        let visibility_pair = required(pairs.next());
+        let type_pair = required(pairs.next());
+        parameters.push(Parameter::Typed {
+            pattern: pattern(syntax::Pattern::cast(pair).unwrap()),
+            visibility: visibility(syntax::OptionalVisibility::cast(visibility_pair).unwrap()),
+            typ: typ(syntax::Type::cast(type_pair).unwrap()),
+        });
+    }
+    parameters
+}
+
+fn visibility(visibility: syntax::OptionalVisibility<'_>) -> Visibility {
+    if visibility.text().is_empty() {
+        Visibility::Private
+    } else {
+        Visibility::Public
+    }
+}
+
+fn generics(generics: syntax::Generics<'_>) -> Vec<Ident> {
+    generics.idents().map(ident).collect()
+}
+
+fn path(path: syntax::Path<'_>) -> Path {
+    let kind = if path.text().starts_with("crate") {
+        PathKind::Crate
+    } else if path.text().starts_with("dep") {
+        PathKind::Dep
+    } else {
+        PathKind::Plain
+    };
+    Path {
+        kind,
+        segments: required(path.idents()).idents().map(ident).collect(),
+    }
+}
+
+fn typ(node: syntax::Type<'_>) -> Type {
+    let pair = required(inner(required(node.type_inner()).pair()).next());
+    match pair.as_rule() {
+        Rule::field_type => Type::Field,
+        Rule::bool_type => Type::Bool,
+        Rule::int_type => {
+            let token = required(syntax::IntType::cast(pair).unwrap().int_type_token());
+            Type::Integer(token.text()[1..].parse().unwrap())
+        }
+        Rule::string_type => Type::String(type_expression_atom(&pair)),
+        Rule::named_type => {
+            let named = syntax::NamedType::cast(pair).unwrap();
+            Type::Named(
+                path(required(named.path())),
+                generic_type_args(required(named.generic_type_args())),
+            )
+        }
+        Rule::vec_type => {
+            let vec = syntax::VecType::cast(pair).unwrap();
+            Type::Vec(generic_type_args(required(vec.generic_type_args())))
+        }
+        Rule::array_type => {
+            let array = syntax::ArrayType::cast(pair.clone()).unwrap();
+            Type::Array(
+                type_expression_atom(&pair),
+                Box::new(typ(required(array.r#type()))),
+            )
+        }
+        Rule::tuple_type => {
+            let tuple = syntax::TupleType::cast(pair).unwrap();
+            Type::Tuple(tuple.types().map(typ).collect())
+        }
+        Rule::function_type => {
+            let mut types: Vec<Type> = syntax::FunctionType::cast(pair)
+                .unwrap()
+                .types()
+                .map(typ)
+                .collect();
+            let return_type = types.pop().unwrap();
+            Type::Function(types, Box::new(return_type))
+        }
+        rule => unreachable!("unexpected type {:?}", rule),
+    }
+}
+
+fn generic_type_args(args: syntax::GenericTypeArgs<'_>) -> Vec<Type> {
+    inner(args.pair())
+        .map(|pair| match pair.as_rule() {
+            Rule::r#type => typ(syntax::Type::cast(pair).unwrap()),
+            Rule::type_expression => Type::Expression(type_expression(&pair)),
+            rule => unreachable!("unexpected generic argument {:?}", rule),
+        })
+        .collect()
+}
+
+/// Returns the length of `str<N>` or `[T; N]`, which is a variable or an integer.
+fn type_expression_atom(pair: &Pair<'_, Rule>) -> Option<TypeExpression> {
+    inner(pair).find_map(|pair| match pair.as_rule() {
+        Rule::variable => Some(TypeExpression::Variable(path(required(
+            syntax::Variable::cast(pair).unwrap().path(),
+        )))),
+        Rule::integer => Some(TypeExpression::Constant(integer(pair.as_str()))),
+        _ => None,
+    })
+}
+
+fn type_expression(pair: &Pair<'_, Rule>) -> TypeExpression {
+    let mut operands = vec![];
+    let mut operators = vec![];
+    for pair in inner(pair) {
+        match pair.as_rule() {
+            Rule::type_expression_term => operands.push(type_expression_term(&pair)),
+            Rule::type_operator => operators.push(binary_op(&pair)),
+            rule => unreachable!("unexpected type expression part {:?}", rule),
+        }
+    }
+    fold_infix(operands, operators, |lhs, operator, rhs| {
+        TypeExpression::Binary(Box::new(lhs), operator, Box::new(rhs))
+    })
+}
+
+fn type_expression_term(pair: &Pair<'_, Rule>) -> TypeExpression {
+    let pair = required(inner(pair).next());
+    match pair.as_rule() {
+        Rule::negation_in_type_expression => {
+            let term = required(inner(&pair).next());
+            TypeExpression::Negation(Box::new(type_expression_term(&term)))
+        }
+        Rule::type_expression_atom => {
+            let atom = required(inner(&pair).next());
+            match atom.as_rule() {
+                Rule::variable => TypeExpression::Variable(path(required(
+                    syntax::Variable::cast(atom).unwrap().path(),
+                ))),
+                Rule::type_literal => TypeExpression::Constant(integer(atom.as_str().trim())),
+                Rule::type_expression => type_expression(&atom),
+                rule => unreachable!("unexpected type expression atom {:?}", rule),
+            }
+        }
+        rule => unreachable!("unexpected type expression term {:?}", rule),
+    }
+}
+
+/// Folds `operands` separated by `operators` into a tree by the operators'
+/// precedence, where each operator is left-associative.
+fn fold_infix<T>(
+    operands: Vec<T>,
+    operators: Vec<BinaryOp>,
+    combine: impl Fn(T, BinaryOp, T) -> T,
+) -> T {
+    let mut operands = operands.into_iter();
+    let mut output = vec![required(operands.next())];
+    let mut pending: Vec<BinaryOp> = vec![];
+    for (operator, operand) in operators.into_iter().zip(operands) {
+        while pending
+            .last()
+            .is_some_and(|last| last.precedence() >= operator.precedence())
+        {
+            let rhs = output.pop().unwrap();
+            let lhs = output.pop().unwrap();
+            output.push(combine(lhs, pending.pop().unwrap(), rhs));
+        }
+        pending.push(operator);
+        output.push(operand);
+    }
+    while let Some(operator) = pending.pop() {
+        let rhs = output.pop().unwrap();
+        let lhs = output.pop().unwrap();
+        output.push(combine(lhs, operator, rhs));
+    }
+    output.pop().unwrap()
+}
+
+fn binary_op(pair: &Pair<'_, Rule>) -> BinaryOp {
+    BinaryOp::from_symbol(pair.as_str().trim()).expect("the grammar only matches operators")
+}
+
+/// Returns the value of an `integer`, which may be hexadecimal.
+fn integer(text: &str) -> u128 {
+    match text.strip_prefix("0x") {
+        Some(hex) => {
+            let digits = hex.trim_start_matches('0');
+            if digits.is_empty() {
+                0
+            } else {
+                u128::from_str_radix(digits, 16).unwrap()
+            }
+        }
+        None => text.parse().unwrap(),
+    }
+}
+
+fn literal(literal: syntax::Literal<'_>) -> Literal {
+    let pair = required(inner(literal.pair()).next());
+    match pair.as_rule() {
+        Rule::integer => Literal::Integer(integer(pair.as_str())),
+        Rule::bool => Literal::Bool(pair.as_str() == "true"),
+        Rule::str => {
+            let text = pair.as_str();
+            Literal::Str(text[1..text.len() - 1].to_owned())
+        }
+        rule => unreachable!("unexpected literal {:?}", rule),
+    }
+}
+
+fn pattern(pattern_node: syntax::Pattern<'_>) -> Pattern {
+    let text = pattern_node.text();
+    if let Some(struct_path) = pattern_node.path() {
+        let mut fields = vec![];
+        let mut pairs = inner(pattern_node.pair()).skip(1).peekable();
+        while let Some(field) = pairs.next() {
+            let name = field.as_str().to_owned();
+            let field_pattern = match pairs.peek().map(Pair::as_rule) {
+                Some(Rule::pattern) => {
+                    pattern(syntax::Pattern::cast(pairs.next().unwrap()).unwrap())
+                }
+                _ => Pattern::Identifier(name.clone()),
+            };
+            fields.push((name, field_pattern));
+        }
+        Pattern::Struct(path(struct_path), fields)
+    } else if text.starts_with('(') {
+        Pattern::Tuple(pattern_node.patterns().map(pattern).collect())
+    } else if let Some(inner_pattern) = pattern_node.patterns().next() {
+        Pattern::Mutable(Box::new(pattern(inner_pattern)))
+    } else {
+        Pattern::Identifier(text.to_owned())
+    }
+}
+
+fn block(block: syntax::Block<'_>) -> Block {
+    let mut statements: Vec<Statement> = block
+        .statement_not_lasts()
+        .map(|statement| {
+            let pair = required(inner(statement.pair()).next());
+            match pair.as_rule() {
+                Rule::declaration => {
+                    let declaration = syntax::Declaration::cast(pair).unwrap();
+                    Statement::Let {
+                        pattern: pattern(required(declaration.pattern())),
+                        typ: required(declaration.optional_type_annotation())
+                            .r#type()
+                            .map(typ),
+                        value: expression(required(declaration.expression()).pair()),
+                    }
+                }
+                Rule::assignment => {
+                    let assignment = syntax::Assignment::cast(pair).unwrap();
+                    let operator = required(assignment.assign_operator());
+                    let operator = operator.text().trim_end_matches('=');
+                    Statement::Assign {
+                        lvalue: lvalue(required(assignment.lvalue())),
+                        operator: BinaryOp::from_symbol(operator),
+                        value: expression(required(assignment.expression()).pair()),
+                    }
+                }
+                Rule::expression => Statement::Semi(expression(&pair)),
+                Rule::expression_not_semi => {
+                    Statement::Expression(expression(&required(inner(&pair).next())))
+                }
+                rule => unreachable!("unexpected statement {:?}", rule),
+            }
+        })
+        .collect();
+    if let Some(value) = block.expression() {
+        statements.push(Statement::Expression(expression(value.pair())));
+    }
+    Block { statements }
+}
+
+fn lvalue(lvalue: syntax::Lvalue<'_>) -> LValue {
+    let mut pairs = inner(lvalue.pair());
+    let mut result = LValue::Ident(required(pairs.next()).as_str().to_owned());
+    for pair in pairs {
+        result = match pair.as_rule() {
+            Rule::ident | Rule::lvalue_integer => {
+                LValue::MemberAccess(Box::new(result), pair.as_str().to_owned())
+            }
+            Rule::expression => LValue::Index(Box::new(result), expression(&pair)),
+            rule => unreachable!("unexpected lvalue part {:?}", rule),
+        }
+    }
+    result
+}
+
+/// Lowers any kind of expression, with or without constructors, or one of the
+/// expressions that can be an atom.
+fn expression(pair: &Pair<'_, Rule>) -> Expression {
+    match pair.as_rule() {
+        Rule::expression | Rule::expression_no_constructors => {
+            let mut operands = vec![];
+            let mut operators = vec![];
+            for pair in inner(pair) {
+                match pair.as_rule() {
+                    Rule::operator => operators.push(binary_op(&pair)),
+                    _ => operands.push(expression(&pair)),
+                }
+            }
+            fold_infix(operands, operators, |lhs, operator, rhs| {
+                Expression::Infix(Box::new(lhs), operator, Box::new(rhs))
+            })
+        }
+        Rule::term
+        | Rule::term_no_constructors
+        | Rule::atom
+        | Rule::atom_no_constructors
+        | Rule::literal_or_collection
+        | Rule::array_expr => expression(&required(inner(pair).next())),
+        Rule::not | Rule::not_no_constructors => Expression::Prefix(
+            UnaryOp::Not,
+            Box::new(expression(&required(inner(pair).next()))),
+        ),
+        Rule::negation | Rule::negation_no_constructors => Expression::Prefix(
+            UnaryOp::Minus,
+            Box::new(expression(&required(inner(pair).next()))),
+        ),
+        Rule::atom_or_right_unary | Rule::atom_or_right_unary_no_constructors => {
+            let mut pairs = inner(pair);
+            let mut result = expression(&required(pairs.next()));
+            for pair in pairs {
+                result = postfix(result, pair);
+            }
+            result
+        }
+        Rule::tuple => {
+            let list = required(syntax::Tuple::cast(pair.clone()).unwrap().expression_list());
+            let mut elements = expression_list(&list);
+            if elements.len() == 1 && !list.text().trim_end().ends_with(',') {
+                Expression::Parenthesized(Box::new(elements.pop().unwrap()))
+            } else {
+                Expression::Tuple(elements)
+            }
+        }
+        Rule::if_expr => {
+            let if_expr = syntax::IfExpr::cast(pair.clone()).unwrap();
+            let mut blocks = if_expr.blocks();
+            let consequence = block(required(blocks.next()));
+            let alternative = match (blocks.next(), if_expr.if_expr()) {
+                (Some(alternative), _) => Some(Expression::Block(block(alternative))),
+                (None, Some(alternative)) => Some(expression(alternative.pair())),
+                (None, None) => None,
+            };
+            Expression::If {
+                condition: Box::new(expression(
+                    required(if_expr.expression_no_constructors()).pair(),
+                )),
+                consequence,
+                alternative: alternative.map(Box::new),
+            }
+        }
+        Rule::standard_array => {
+            let array = syntax::StandardArray::cast(pair.clone()).unwrap();
+            Expression::Array(array.expressions().map(|e| expression(e.pair())).collect())
+        }
+        Rule::array_sugar => {
+            let mut elements = syntax::ArraySugar::cast(pair.clone())
+                .unwrap()
+                .expressions();
+            let value = expression(required(elements.next()).pair());
+            let length = expression(required(elements.next()).pair());
+            Expression::RepeatedArray(Box::new(value), Box::new(length))
+        }
+        Rule::constructor => {
+            let constructor = syntax::Constructor::cast(pair.clone()).unwrap();
+            let fields = constructor
+                .constructor_fields()
+                .map(|field| {
+                    let name = ident(required(field.ident()));
+                    let value = match field.expression() {
+                        Some(value) => expression(value.pair()),
+                        None => Expression::Variable(Path {
+                            kind: PathKind::Plain,
+                            segments: vec![name.clone()],
+                        }),
+                    };
+                    (name, value)
+                })
+                .collect();
+            Expression::Constructor(path(required(constructor.path())), fields)
+        }
+        Rule::lambda => {
+            let lambda = syntax::Lambda::cast(pair.clone()).unwrap();
+            Expression::Lambda {
+                parameters: required(lambda.lambda_parameters())
+                    .patterns()
+                    .map(pattern)
+                    .collect(),
+                return_type: required(lambda.lambda_return_type()).r#type().map(typ),
+                body: Box::new(expression(required(lambda.expression()).pair())),
+            }
+        }
+        Rule::block => Expression::Block(block(syntax::Block::cast(pair.clone()).unwrap())),
+        Rule::variable => Expression::Variable(path(required(
+            syntax::Variable::cast(pair.clone()).unwrap().path(),
+        ))),
+        Rule::literal => Expression::Literal(literal(syntax::Literal::cast(pair.clone()).unwrap())),
+        rule => unreachable!("unexpected expression {:?}", rule),
+    }
+}
+
+fn postfix(lhs: Expression, pair: Pair<'_, Rule>) -> Expression {
+    match pair.as_rule() {
+        Rule::call_rhs => {
+            let call = syntax::CallRhs::cast(pair).unwrap();
+            Expression::Call(
+                Box::new(lhs),
+                expression_list(&required(call.expression_list())),
+            )
+        }
+        Rule::array_rhs => {
+            let index = required(syntax::ArrayRhs::cast(pair).unwrap().expression());
+            Expression::Index(Box::new(lhs), Box::new(expression(index.pair())))
+        }
+        Rule::cast_rhs => {
+            let cast = syntax::CastRhs::cast(pair).unwrap();
+            Expression::Cast(Box::new(lhs), typ(required(cast.r#type())))
+        }
+        Rule::member_rhs => {
+            let member = syntax::MemberRhs::cast(pair).unwrap();
+            let name = required(member.field_name()).text().trim().to_owned();
+            match member.expression_list() {
+                Some(arguments) => {
+                    Expression::MethodCall(Box::new(lhs), name, expression_list(&arguments))
+                }
+                None => Expression::MemberAccess(Box::new(lhs), name),
+            }
+        }
+        rule => unreachable!("unexpected postfix {:?}", rule),
+    }
+}
+
+fn expression_list(list: &syntax::ExpressionList<'_>) -> Vec<Expression> {
+    list.expressions().map(|e| expression(e.pair())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_body(source: &str) -> Vec<Statement> {
+        let module = parse(source).unwrap();
+        match module.items.into_iter().next() {
+            Some(Item::Function(function)) => function.body.statements,
+            item => panic!("expected a function, found {:?}", item),
+        }
+    }
+
+    fn variable(name: &str) -> Expression {
+        Expression::Variable(Path {
+            kind: PathKind::Plain,
+            segments: vec![name.to_owned()],
+        })
+    }
+
+    fn infix(lhs: Expression, operator: BinaryOp, rhs: Expression) -> Expression {
+        Expression::Infix(Box::new(lhs), operator, Box::new(rhs))
+    }
+
+    #[test]
+    fn precedence() {
+        let statements = function_body("fn main() { a + b * c - d != e | f ^ g & h }");
+        let sum = infix(
+            infix(
+                variable("a"),
+                BinaryOp::Add,
+                infix(variable("b"), BinaryOp::Multiply, variable("c")),
+            ),
+            BinaryOp::Subtract,
+            variable("d"),
+        );
+        assert_eq!(
+            statements,
+            [Statement::Expression(infix(
+                sum,
+                BinaryOp::NotEqual,
+                infix(
+                    variable("e"),
+                    BinaryOp::Or,
+                    infix(
+                        infix(variable("f"), BinaryOp::Xor, variable("g")),
+                        BinaryOp::And,
+                        variable("h"),
+                    ),
+                ),
+            ))]
+        );
+    }
+
+    #[test]
+    fn items() {
+        let module = parse(
+            "mod foo;\n\
+             struct Foo<T> { a: Field, b: [u8; 32] }\n\
+             impl Foo { fn get(self, i: u8) -> pub Field { self.a } }\n\
+             global N: Field = 0x10;\n",
+        )
+        .unwrap();
+        assert_eq!(module.items.len(), 4);
+        assert_eq!(module.items[0], Item::ModuleDeclaration("foo".to_owned()));
+        assert_eq!(
+            module.items[1],
+            Item::Struct(Struct {
+                name: "Foo".to_owned(),
+                generics: vec!["T".to_owned()],
+                fields: vec![
+                    ("a".to_owned(), Type::Field),
+                    (
+                        "b".to_owned(),
+                        Type::Array(
+                            Some(TypeExpression::Constant(32)),
+                            Box::new(Type::Integer(8))
+                        )
+                    ),
+                ],
+            })
+        );
+        let method = match &module.items[2] {
+            Item::Impl(implementation) => &implementation.methods[0],
+            item => panic!("expected an impl, found {:?}", item),
+        };
+        assert_eq!(
+            method.parameters,
+            [
+                Parameter::SelfValue,
+                Parameter::Typed {
+                    pattern: Pattern::Identifier("i".to_owned()),
+                    visibility: Visibility::Private,
+                    typ: Type::Integer(8),
+                },
+            ]
+        );
+        assert_eq!(method.return_visibility, Visibility::Public);
+        assert_eq!(
+            method.body.statements,
+            [Statement::Expression(Expression::MemberAccess(
+                Box::new(variable("self")),
+                "a".to_owned()
+            ))]
+        );
+        assert_eq!(
+            module.items[3],
+            Item::Global(Global {
+                name: "N".to_owned(),
+                typ: Some(Type::Field),
+                value: Literal::Integer(16),
+            })
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let statements =
+            function_body("fn main() { let mut x: u8 = -f(0, (20)).y; x[0] += 10; { (x,) } }");
+        assert_eq!(
+            statements[0],
+            Statement::Let {
+                pattern: Pattern::Mutable(Box::new(Pattern::Identifier("x".to_owned()))),
+                typ: Some(Type::Integer(8)),
+                value: Expression::Prefix(
+                    UnaryOp::Minus,
+                    Box::new(Expression::MemberAccess(
+                        Box::new(Expression::Call(
+                            Box::new(variable("f")),
+                            vec![
+                                Expression::Literal(Literal::Integer(0)),
+                                Expression::Parenthesized(Box::new(Expression::Literal(
+                                    Literal::Integer(20)
+                                ))),
+                            ]
+                        )),
+                        "y".to_owned()
+                    ))
+                ),
+            }
+        );
+        assert_eq!(
+            statements[1],
+            Statement::Assign {
+                lvalue: LValue::Index(
+                    Box::new(LValue::Ident("x".to_owned())),
+                    Expression::Literal(Literal::Integer(0))
+                ),
+                operator: Some(BinaryOp::Add),
+                value: Expression::Literal(Literal::Integer(10)),
+            }
+        );
+        assert_eq!(
+            statements[2],
+            Statement::Expression(Expression::Block(Block {
+                statements: vec![Statement::Expression(Expression::Tuple(vec![variable(
+                    "x"
+                )]))],
+            }))
+        );
+    }
+}
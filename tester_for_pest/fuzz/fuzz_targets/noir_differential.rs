@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate tester_for_pest;
+
+use tester_for_pest::differential::{check, Config};
+
+fuzz_target!(|data: &[u8]| {
+    let report = check(&Config::default(), data);
+    if let Some(error) = report.error {
+        panic!("ERR: {}", error);
+    }
+});
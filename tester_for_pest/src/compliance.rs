@@ -0,0 +1,98 @@
+//! The reverse direction of the differential test: real Noir sources that noirc
+//! accepts must be accepted by the grammar as well.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use noirc_frontend::parse_program;
+use pest::error::{ErrorVariant, LineColLocation};
+use pest::Parser;
+use pest_based_noir_parser::{NoirParser, Rule};
+
+/// A source file that noirc accepts and pest rejects.
+pub struct Rejection {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    /// The rules that pest expected at the position.
+    pub expected: Vec<Rule>,
+}
+
+/// The outcome of checking a corpus of Noir sources.
+#[derive(Default)]
+pub struct Compliance {
+    pub files: usize,
+    /// Files that noirc rejects, so pest may reject them too.
+    pub rejected_by_noirc: usize,
+    pub rejections: Vec<Rejection>,
+}
+
+/// Returns the `.nr` files under `dir`, in order.
+pub fn nr_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(nr_files(&path)?);
+        } else if path.extension().map_or(false, |extension| extension == "nr") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parses every `.nr` file under `dir` with both parsers.
+pub fn check_corpus(dir: &Path) -> io::Result<Compliance> {
+    let mut compliance = Compliance::default();
+    for path in nr_files(dir)? {
+        let code = fs::read_to_string(&path)?;
+        compliance.files += 1;
+        if !parse_program(&code).1.is_empty() {
+            compliance.rejected_by_noirc += 1;
+            continue;
+        }
+        if let Err(error) = NoirParser::parse(Rule::start, &code) {
+            let (line, col) = match error.line_col {
+                LineColLocation::Pos(pos) => pos,
+                LineColLocation::Span(start, _) => start,
+            };
+            let expected = match error.variant {
+                ErrorVariant::ParsingError { positives, .. } => positives,
+                ErrorVariant::CustomError { .. } => vec![],
+            };
+            compliance.rejections.push(Rejection { path, line, col, expected });
+        }
+    }
+    Ok(compliance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_corpus_of_temp_dir() {
+        let dir = std::env::temp_dir().join(format!("tester_for_pest_compliance_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("accepted.nr"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("sub").join("rejected_by_pest.nr"), "fn main() {\n    let x = 1;\n}\n").unwrap();
+        fs::write(dir.join("rejected_by_noirc.nr"), "fn (\0").unwrap();
+        fs::write(dir.join("notes.txt"), "not Noir").unwrap();
+
+        let files = nr_files(&dir);
+        let compliance = check_corpus(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files.unwrap().len(), 3);
+        let compliance = compliance.unwrap();
+        assert_eq!(compliance.files, 3);
+        assert_eq!(compliance.rejected_by_noirc, 1);
+        assert_eq!(compliance.rejections.len(), 1);
+        let rejection = &compliance.rejections[0];
+        assert!(rejection.path.ends_with("sub/rejected_by_pest.nr"));
+        assert_eq!((rejection.line, rejection.col), (2, 13));
+        assert!(rejection.expected.contains(&Rule::term));
+    }
+}
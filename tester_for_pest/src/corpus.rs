@@ -0,0 +1,113 @@
+//! Generation of corpora from a seed, without a fuzzer.
+
+use std::fmt;
+
+/// How many bytes drive the generation of one program.
+const SEEDED_DRIVER_LEN: usize = 4096;
+
+/// Returns driver bytes from a splitmix64 generator, the same for the same seed.
+pub fn seeded_driver(seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    let mut driver = Vec::with_capacity(SEEDED_DRIVER_LEN);
+    while driver.len() < SEEDED_DRIVER_LEN {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        driver.extend_from_slice(&(z ^ (z >> 31)).to_le_bytes());
+    }
+    driver
+}
+
+/// How the parsers judged the program generated from a driver.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The driver didn't lead to a program.
+    Skipped,
+    /// pest and noirc both accept the program and parse it into the same structure.
+    Agreed,
+    /// pest and noirc both accept the program, but parse it into different structures.
+    Mismatched,
+    /// pest rejects the program, which it generated or noirc accepts.
+    RejectedByPest,
+    /// pest accepts the program and noirc rejects it.
+    RejectedByNoirc,
+    /// pest and noirc both reject raw text.
+    RejectedByBoth,
+    /// Generation failed, which means the grammar or the harness is broken.
+    Failed,
+}
+
+/// Counts of outcomes over a corpus.
+#[derive(Default)]
+pub struct Stats {
+    pub skipped: usize,
+    pub agreed: usize,
+    pub mismatched: usize,
+    pub rejected_by_pest: usize,
+    pub rejected_by_noirc: usize,
+    pub rejected_by_both: usize,
+    pub failed: usize,
+    /// How many samples lookaheads rejected while generating the programs.
+    pub rejections: u64,
+}
+
+impl Stats {
+    pub fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Agreed => self.agreed += 1,
+            Outcome::Mismatched => self.mismatched += 1,
+            Outcome::RejectedByPest => self.rejected_by_pest += 1,
+            Outcome::RejectedByNoirc => self.rejected_by_noirc += 1,
+            Outcome::RejectedByBoth => self.rejected_by_both += 1,
+            Outcome::Failed => self.failed += 1,
+        }
+    }
+
+    /// Returns how many drivers led to a program.
+    pub fn programs(&self) -> usize {
+        self.agreed + self.mismatched + self.rejected_by_pest + self.rejected_by_noirc + self.rejected_by_both
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let programs = self.programs();
+        writeln!(f, "{} programs, {} drivers skipped, {} failed", programs, self.skipped, self.failed)?;
+        writeln!(f, "{} samples rejected by lookaheads", self.rejections)?;
+        let percent = |count: usize| if programs == 0 { 0.0 } else { 100.0 * count as f64 / programs as f64 };
+        writeln!(f, "{:>8}  {:>5.1}%  accepted by pest and noirc", self.agreed, percent(self.agreed))?;
+        writeln!(f, "{:>8}  {:>5.1}%  parsed differently by pest and noirc", self.mismatched, percent(self.mismatched))?;
+        writeln!(f, "{:>8}  {:>5.1}%  rejected by pest", self.rejected_by_pest, percent(self.rejected_by_pest))?;
+        writeln!(f, "{:>8}  {:>5.1}%  rejected by noirc", self.rejected_by_noirc, percent(self.rejected_by_noirc))?;
+        writeln!(f, "{:>8}  {:>5.1}%  rejected by pest and noirc", self.rejected_by_both, percent(self.rejected_by_both))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_drivers() {
+        assert_eq!(seeded_driver(7), seeded_driver(7));
+        assert_ne!(seeded_driver(7), seeded_driver(8));
+        assert_eq!(seeded_driver(7).len(), SEEDED_DRIVER_LEN);
+    }
+
+    #[test]
+    fn stats() {
+        let mut stats = Stats::default();
+        for outcome in [Outcome::Agreed, Outcome::Agreed, Outcome::Agreed, Outcome::RejectedByNoirc, Outcome::Skipped] {
+            stats.add(outcome);
+        }
+        assert_eq!(stats.programs(), 4);
+        assert!(stats.to_string().contains("75.0%  accepted by pest and noirc"), "{}", stats);
+        stats.add(Outcome::Mismatched);
+        assert_eq!(stats.programs(), 5);
+        assert!(stats.to_string().contains("20.0%  parsed differently by pest and noirc"), "{}", stats);
+        stats.rejections += 3;
+        assert!(stats.to_string().contains("3 samples rejected by lookaheads"), "{}", stats);
+    }
+}
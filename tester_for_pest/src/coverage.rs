@@ -0,0 +1,113 @@
+//! Grammar coverage of generated programs: how often each rule and each branch
+//! of a choice was generated.
+
+use std::fmt;
+
+use pest::generation::{Derivation, DerivationTree};
+use pest::Parser;
+use pest_based_noir_parser::{NoirParser, Rule};
+
+/// Hit counts of the rules and alternatives of the grammar.
+pub struct Coverage {
+    pub derivations: usize,
+    /// Rule names with the number of times each was generated, in order.
+    pub rules: Vec<(&'static str, usize)>,
+    /// The number of times each branch of `NoirParser::alternatives()` was picked.
+    pub alternatives: Vec<usize>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Coverage {
+            derivations: 0,
+            rules: NoirParser::rule_names().iter().map(|&name| (name, 0)).collect(),
+            alternatives: vec![0; NoirParser::alternatives().len()],
+        }
+    }
+}
+
+impl Coverage {
+    pub fn add(&mut self, derivation: &Derivation<Rule>) {
+        self.derivations += 1;
+        for tree in &derivation.trees {
+            self.add_tree(tree);
+        }
+        for &idx in &derivation.alternatives {
+            self.alternatives[idx] += 1;
+        }
+    }
+
+    fn add_tree(&mut self, tree: &DerivationTree<Rule>) {
+        let name = format!("{:?}", tree.rule);
+        if let Ok(idx) = self.rules.binary_search_by(|&(rule, _)| rule.cmp(&name[..])) {
+            self.rules[idx].1 += 1;
+        }
+        for child in &tree.children {
+            self.add_tree(child);
+        }
+    }
+
+    /// Returns a table of all hit counts, rules first.
+    pub fn counts(&self) -> String {
+        let mut counts = String::new();
+        for &(rule, count) in &self.rules {
+            counts.push_str(&format!("{:>8}  {}\n", count, rule));
+        }
+        for ((rule, expr), count) in NoirParser::alternatives().iter().zip(&self.alternatives) {
+            counts.push_str(&format!("{:>8}  {}: {}\n", count, rule, expr));
+        }
+        counts
+    }
+}
+
+impl fmt::Display for Coverage {
+    /// Formats a summary with the rules and alternatives that were never generated.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unvisited_rules: Vec<&str> = self.rules.iter().filter(|&&(_, count)| count == 0).map(|&(rule, _)| rule).collect();
+        let unvisited_alternatives: Vec<&(&str, &str)> = NoirParser::alternatives()
+            .iter()
+            .zip(&self.alternatives)
+            .filter(|&(_, &count)| count == 0)
+            .map(|(alternative, _)| alternative)
+            .collect();
+        writeln!(
+            f,
+            "{} derivations cover {} of {} rules and {} of {} alternatives",
+            self.derivations,
+            self.rules.len() - unvisited_rules.len(),
+            self.rules.len(),
+            self.alternatives.len() - unvisited_alternatives.len(),
+            self.alternatives.len()
+        )?;
+        if !unvisited_rules.is_empty() {
+            writeln!(f, "rules never generated:")?;
+            for rule in unvisited_rules {
+                writeln!(f, "    {}", rule)?;
+            }
+        }
+        if !unvisited_alternatives.is_empty() {
+            writeln!(f, "alternatives never generated:")?;
+            for (rule, expr) in unvisited_alternatives {
+                writeln!(f, "    {}: {}", rule, expr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_hits() {
+        let mut coverage = Coverage::default();
+        assert!(coverage.to_string().contains("cover 0 of"));
+        let derivation = NoirParser::generate("program", &[200, 200, 200, 200], None).unwrap();
+        coverage.add(&derivation);
+        let program = coverage.rules.iter().find(|&&(rule, _)| rule == "program").unwrap();
+        assert_eq!(program.1, 1);
+        assert!(coverage.alternatives.iter().sum::<usize>() > 0);
+        assert!(!coverage.to_string().contains("\n    program\n"), "{}", coverage);
+    }
+}
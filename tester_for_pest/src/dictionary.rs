@@ -0,0 +1,53 @@
+//! Raw-text fuzzing of noirc with the strings that the grammar matches literally.
+
+use std::fmt::Write;
+
+/// Formats `literals` as a dictionary for AFL and libFuzzer.
+pub fn dictionary(literals: &[&str]) -> String {
+    let mut dictionary = String::new();
+    for (idx, literal) in literals.iter().enumerate() {
+        write!(dictionary, "literal_{}=\"", idx).unwrap();
+        for byte in literal.bytes() {
+            match byte {
+                b'"' | b'\\' => write!(dictionary, "\\{}", byte as char).unwrap(),
+                b' '..=b'~' => dictionary.push(byte as char),
+                _ => write!(dictionary, "\\x{:02x}", byte).unwrap(),
+            }
+        }
+        dictionary.push_str("\"\n");
+    }
+    dictionary
+}
+
+/// Builds text for noirc out of fuzzer input. Each byte below the number of
+/// literals splices that literal, surrounded by spaces; other bytes become
+/// printable ASCII characters, so that names and numbers come up as well.
+pub fn splice(data: &[u8], literals: &[&str]) -> String {
+    let mut text = String::new();
+    for &byte in data {
+        match literals.get(byte as usize) {
+            Some(literal) => {
+                text.push(' ');
+                text.push_str(literal);
+                text.push(' ');
+            }
+            None => text.push((b' ' + byte % 95) as char),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        assert_eq!(dictionary(&["fn", "\"", "\n"]), "literal_0=\"fn\"\nliteral_1=\"\\\"\"\nliteral_2=\"\\x0a\"\n");
+    }
+
+    #[test]
+    fn splices_literals() {
+        assert_eq!(splice(&[0, 160, 161, 1], &["fn", "("]), " fn ab ( ");
+    }
+}
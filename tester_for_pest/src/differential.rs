@@ -0,0 +1,277 @@
+//! The differential check: a program is generated from a driver, parsed by
+//! pest, and then by noirc, which should accept it too, with the same structure.
+//! In raw mode the input is text for both parsers, which should agree on it.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use noirc_frontend::parse_program;
+use pest::error::InputLocation;
+use pest::generation::{Derivation, GenerationError};
+use pest::Parser;
+use pest_based_noir_parser::{NoirParser, Rule};
+
+use crate::corpus::Outcome;
+use crate::dictionary::splice;
+use crate::reduce::{pest_accepts, reduce};
+use crate::structure::{compare, Mismatch};
+use crate::triage::{normalize, Signature};
+
+/// Settings of the differential check.
+pub struct Config {
+    /// The rule that programs are generated from.
+    pub start_rule: String,
+    /// The maximum number of terminals in a generated program.
+    pub limit: u64,
+    /// Where debug files are saved.
+    pub output_dir: PathBuf,
+    /// Wraps the generated text into a program for noirc, with `{}` standing for the text.
+    pub template: String,
+    /// Reads inputs as literals spliced into text for noirc instead of as generation drivers.
+    pub raw: bool,
+}
+
+impl Default for Config {
+    /// Generates whole programs, as the subcommands do without options.
+    fn default() -> Self {
+        Config {
+            start_rule: "program".to_string(),
+            limit: 100_000,
+            output_dir: PathBuf::from("debug"),
+            template: "{}".to_string(),
+            raw: false,
+        }
+    }
+}
+
+/// The outcome of generating and parsing a program from a single driver.
+pub struct Report {
+    /// Everything learned about the driver, saved to the debug file.
+    pub debug: String,
+    /// The generated program.
+    pub program: Option<String>,
+    /// Why the driver is a finding, if it is.
+    pub error: Option<String>,
+    /// What the finding has in common with findings of the same bug.
+    pub signature: Option<Signature>,
+    /// Why the driver didn't lead to a program, if it didn't.
+    pub skipped: Option<GenerationError>,
+    /// How the parsers judged the program.
+    pub outcome: Outcome,
+    /// How many samples lookaheads rejected while generating the program.
+    pub rejections: u64,
+}
+
+fn debug_filename(config: &Config, data: &[u8]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    let data_hash = hasher.finish();
+    config.output_dir.join(format!("{:x}", data_hash))
+}
+
+pub fn save_debug(config: &Config, data: &[u8], debug: &str) {
+    fs::create_dir_all(&config.output_dir).unwrap();
+    fs::write(debug_filename(config, data), debug).unwrap();
+}
+
+/// Generates a program from `data` and checks that pest and noirc both accept it.
+/// With `config.raw`, checks that they agree on text spliced from `data` instead.
+pub fn check(config: &Config, data: &[u8]) -> Report {
+    if config.raw {
+        return check_raw(data);
+    }
+    check_generated(config, NoirParser::generate(&config.start_rule, data, Some(config.limit)))
+}
+
+/// Checks that pest and noirc both accept a program generated from `config.start_rule`.
+pub fn check_generated(config: &Config, program_code: Result<Derivation<Rule>, GenerationError>) -> Report {
+    let mut debug = String::new();
+    let mut program = None;
+    let mut error = None;
+    let mut signature = None;
+    let mut skipped = None;
+    let mut outcome = Outcome::Failed;
+    let mut rejections = 0;
+    match program_code {
+        // A start rule that is silent or atomic leaves no tree to tell which rule to parse.
+        Ok(derivation) if derivation.trees.is_empty() => {
+            signature = Some(Signature::new("no derivation".to_string(), vec![]));
+            error = Some(format!("rule {} derived no pairs", config.start_rule));
+        }
+        Ok(derivation) => {
+            rejections = derivation.rejections;
+            let code = &derivation.string;
+            writeln!(debug, "{}", code).unwrap();
+            // Spans of noirc are relative to the template, with the generated text after `prefix`.
+            let prefix = config.template.find("{}").unwrap_or(0);
+            let wrapped = config.template.replacen("{}", code, 1);
+            program = Some(wrapped.clone());
+            let parsed = NoirParser::parse(derivation.trees[0].rule, &code[..]);
+            match parsed {
+                Ok(mut foo) => {
+                    if let Some(bar) = foo.next() {
+                        writeln!(debug, "{:?}", bar).unwrap();
+                        let noirc_result = parse_program(&wrapped[..]);
+                        if noirc_result.1 == vec![] {
+                            writeln!(debug, "{:?}", noirc_result.0).unwrap();
+                            match compare(&wrapped, &noirc_result.0) {
+                                None => outcome = Outcome::Agreed,
+                                Some(mismatch) => {
+                                    outcome = Outcome::Mismatched;
+                                    signature = Some(Signature::new(mismatch.kind(), vec![]));
+                                    error = Some(mismatch_error(&mismatch));
+                                }
+                            }
+                        } else {
+                            outcome = Outcome::RejectedByNoirc;
+                            let first = &noirc_result.1[0];
+                            let rules = match (first.span().start() as usize).checked_sub(prefix) {
+                                Some(pos) => derivation.rules_at(pos),
+                                None => vec![],
+                            };
+                            signature = Some(Signature::new(normalize(&format!("{:?}", first)), rules));
+                            error = Some(format!("noir parser failed with errors {:?}", noirc_result.1));
+                        }
+                        // assert_eq!(noirc_result.1, vec![]);
+                    } else {
+                        signature = Some(Signature::new("no pairs".to_string(), vec![]));
+                        error = Some("second unwrap failed".to_string());
+                    }
+                }
+                Err(pest_error) => {
+                    let pos = match pest_error.location {
+                        InputLocation::Pos(pos) => pos,
+                        InputLocation::Span((start, _)) => start,
+                    };
+                    outcome = Outcome::RejectedByPest;
+                    let kind = format!("pest rejected the generated program: {}", pest_error.variant.message());
+                    signature = Some(Signature::new(kind, derivation.rules_at(pos)));
+                    error = Some("first unwrap failed".to_string());
+                }
+            }
+        }
+        // The input doesn't drive generation to a program; not a finding.
+        Err(generation_error @ GenerationError::LimitExceeded)
+        | Err(generation_error @ GenerationError::DriverExhausted)
+        | Err(generation_error @ GenerationError::LookaheadAttemptsExceeded) => {
+            skipped = Some(generation_error);
+            outcome = Outcome::Skipped;
+        }
+        // The grammar or this harness is broken.
+        Err(generation_error) => {
+            signature = Some(Signature::new(format!("generation failed: {}", generation_error), vec![]));
+            error = Some(format!("generation failed: {}", generation_error));
+        }
+    }
+    if let Some(generation_error) = &skipped {
+        writeln!(debug, "SKIP: {}", generation_error).unwrap();
+    }
+    if let Some(error) = &error {
+        writeln!(debug, "ERR: {}", error).unwrap();
+    }
+    Report { debug, program, error, signature, skipped, outcome, rejections }
+}
+
+/// Checks that noirc accepts `code`, a string that pest matches from `config.start_rule`,
+/// e.g. one of those that `NoirParser::generate_all` enumerates, into the same structure.
+pub fn check_enumerated(config: &Config, code: &str) -> Report {
+    let mut debug = String::new();
+    writeln!(debug, "{}", code).unwrap();
+    let wrapped = config.template.replacen("{}", code, 1);
+    let noirc_result = parse_program(&wrapped);
+    let (outcome, signature, error) = match noirc_result.1.first() {
+        None => {
+            writeln!(debug, "{:?}", noirc_result.0).unwrap();
+            match compare(&wrapped, &noirc_result.0) {
+                None => (Outcome::Agreed, None, None),
+                Some(mismatch) => {
+                    let error = mismatch_error(&mismatch);
+                    writeln!(debug, "ERR: {}", error).unwrap();
+                    (Outcome::Mismatched, Some(Signature::new(mismatch.kind(), vec![])), Some(error))
+                }
+            }
+        }
+        Some(first) => {
+            let signature = Signature::new(normalize(&format!("{:?}", first)), vec![]);
+            let error = format!("noir parser failed with errors {:?}", noirc_result.1);
+            writeln!(debug, "ERR: {}", error).unwrap();
+            (Outcome::RejectedByNoirc, Some(signature), Some(error))
+        }
+    };
+    Report { debug, program: Some(wrapped), error, signature, skipped: None, outcome, rejections: 0 }
+}
+
+fn check_raw(data: &[u8]) -> Report {
+    let mut debug = String::new();
+    let code = splice(data, NoirParser::literals());
+    writeln!(debug, "{}", code).unwrap();
+    let pest_result = NoirParser::parse(Rule::start, &code);
+    let noirc_result = parse_program(&code);
+    let (outcome, signature, error) = match (pest_result, noirc_result.1.first()) {
+        (Ok(_), None) => match compare(&code, &noirc_result.0) {
+            None => (Outcome::Agreed, None, None),
+            Some(mismatch) => {
+                writeln!(debug, "{:?}", noirc_result.0).unwrap();
+                (Outcome::Mismatched, Some(Signature::new(mismatch.kind(), vec![])), Some(mismatch_error(&mismatch)))
+            }
+        },
+        (Err(_), Some(_)) => (Outcome::RejectedByBoth, None, None),
+        (Ok(pairs), Some(first)) => {
+            writeln!(debug, "{:?}", pairs).unwrap();
+            let signature = Signature::new(normalize(&format!("{:?}", first)), vec![]);
+            let error = format!("noir parser failed with errors {:?}", noirc_result.1);
+            (Outcome::RejectedByNoirc, Some(signature), Some(error))
+        }
+        (Err(pest_error), None) => {
+            writeln!(debug, "{:?}", noirc_result.0).unwrap();
+            let kind = format!("pest rejected what noirc accepts: {}", pest_error.variant.message());
+            let error = format!("pest parser failed with error {}", pest_error);
+            (Outcome::RejectedByPest, Some(Signature::new(kind, vec![])), Some(error))
+        }
+    };
+    if let Some(error) = &error {
+        writeln!(debug, "ERR: {}", error).unwrap();
+    }
+    Report { debug, program: Some(code), error, signature, skipped: None, outcome, rejections: 0 }
+}
+
+fn mismatch_error(mismatch: &Mismatch) -> String {
+    format!("pest and noirc parse trees differ {}", mismatch)
+}
+
+/// Returns the normalized first error of noirc for `code` if pest accepts it.
+fn noirc_finding(code: &str) -> Option<String> {
+    if !pest_accepts(code) {
+        return None;
+    }
+    parse_program(code).1.first().map(|error| normalize(&format!("{:?}", error)))
+}
+
+/// Returns the kind of the first difference between the parse trees of pest and
+/// noirc for `code` if both accept it.
+fn mismatch_finding(code: &str) -> Option<String> {
+    let (parsed, errors) = parse_program(code);
+    if !errors.is_empty() {
+        return None;
+    }
+    compare(code, &parsed).map(|mismatch| mismatch.kind())
+}
+
+/// Adds a reduced program to the debug output if the report is about a program
+/// that pest accepts and noirc rejects, or that they parse differently. The reduced
+/// program fails noirc with the same kind of error, or differs in the same way.
+pub fn add_reduced(report: &mut Report) {
+    let finding = match report.outcome {
+        Outcome::Mismatched => mismatch_finding,
+        _ => noirc_finding,
+    };
+    if let Some(program) = &report.program {
+        if let Some(kind) = finding(program) {
+            let reduced = reduce(program, |code| finding(code).as_ref() == Some(&kind));
+            writeln!(report.debug, "REDUCED:\n{}", reduced).unwrap();
+        }
+    }
+}
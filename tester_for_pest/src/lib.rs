@@ -0,0 +1,10 @@
+//! Differential testing of the pest grammar of Noir against `noirc_frontend`.
+
+pub mod compliance;
+pub mod corpus;
+pub mod coverage;
+pub mod dictionary;
+pub mod differential;
+pub mod reduce;
+pub mod structure;
+pub mod triage;
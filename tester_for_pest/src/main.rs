@@ -1,130 +1,329 @@
-extern crate pest_based_noir_parser;
-
 #[macro_use]
 extern crate afl;
 
-use std::env;
 use std::fs;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::fmt::Write;
 use std::io;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
 
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use noirc_frontend::parse_program;
-
+use pest::generation::Guide;
 use pest::Parser;
-use pest::error::Error;
-use pest_based_noir_parser::{NoirParser, Rule};
-
-fn main() -> Result<(), Error<Rule>> {
-    let args: Vec<String> = env::args().collect();
-
-    if let Some(first_arg) = args.get(1) {
-        if first_arg == "--fuzz-and-save" {
-            fuzz(true);
-        } else if first_arg == "--parse-noir" {
-            parse_noir();
-        } else if first_arg == "--all" {
-            if let Some(second_arg) = args.get(2) {
-                return read_and_parse(&second_arg[..], true);
+use pest_based_noir_parser::NoirParser;
+
+use tester_for_pest::compliance::check_corpus;
+use tester_for_pest::coverage::Coverage;
+use tester_for_pest::corpus::{seeded_driver, Outcome, Stats};
+use tester_for_pest::dictionary::dictionary;
+use tester_for_pest::differential::{add_reduced, check, check_enumerated, check_generated, save_debug, Config};
+use tester_for_pest::triage::Buckets;
+
+/// Returns the settings given with the global options.
+fn config(matches: &ArgMatches<'_>) -> Config {
+    Config {
+        start_rule: matches.value_of("start-rule").unwrap().to_string(),
+        limit: matches.value_of("limit").unwrap().parse().expect("expected a number of steps"),
+        output_dir: PathBuf::from(matches.value_of("output-dir").unwrap()),
+        template: matches.value_of("template").unwrap().to_string(),
+        raw: matches.is_present("raw"),
+    }
+}
+
+fn main() {
+    let matches = App::new("tester_for_pest")
+        .about("Differential fuzzing of the Noir parser against the pest grammar")
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(Arg::with_name("start-rule").long("start-rule").value_name("RULE").default_value("program").global(true)
+            .help("The rule to generate programs from"))
+        .arg(Arg::with_name("limit").long("limit").value_name("STEPS").default_value("100000").global(true)
+            .help("The maximum number of terminals in a generated program"))
+        .arg(Arg::with_name("output-dir").long("output-dir").value_name("DIR").default_value("debug").global(true)
+            .help("Where debug files are saved"))
+        .arg(Arg::with_name("template").long("template").value_name("TEMPLATE").default_value("{}").global(true)
+            .help("Wraps generated text into a program for noirc, e.g. \"fn main() { let _ = {}; }\""))
+        .arg(Arg::with_name("raw").long("raw").global(true)
+            .help("Splices the literals of the grammar into text for noirc and pest instead of generating programs"))
+        .subcommand(SubCommand::with_name("fuzz").about("Fuzzes with AFL, the default without a subcommand")
+            .arg(Arg::with_name("save").long("save").help("Saves a debug file for every input")))
+        .subcommand(SubCommand::with_name("gen").about("Generates and checks a program driven by stdin or a seed")
+            .arg(Arg::with_name("seed").long("seed").value_name("SEED").help("Drives generation with a seeded PRNG instead of stdin")))
+        .subcommand(SubCommand::with_name("corpus").about("Generates programs from consecutive seeds and reports how the parsers agree")
+            .arg(Arg::with_name("dir").required(true).help("Where drivers and programs are written"))
+            .arg(Arg::with_name("seed").long("seed").value_name("SEED").default_value("0").help("The seed of the first program"))
+            .arg(Arg::with_name("count").long("count").value_name("N").default_value("1000").help("How many programs to generate"))
+            .arg(Arg::with_name("jobs").long("jobs").value_name("N").help("How many threads generate, by default one per CPU")
+                .validator(|jobs| match jobs.parse::<usize>() {
+                    Ok(0) => Err("expected at least one job".to_owned()),
+                    Ok(_) => Ok(()),
+                    Err(_) => Err("expected a number of jobs".to_owned()),
+                }))
+            .arg(Arg::with_name("guided").long("guided").conflicts_with_all(&["jobs", "raw"])
+                .help("Favors the choices that earlier programs never made, generating in a single thread")))
+        .subcommand(SubCommand::with_name("enumerate").about("Checks every program up to a length, shortest first, and reports how the parsers agree")
+            .arg(Arg::with_name("dir").required(true).help("Where programs are written"))
+            .arg(Arg::with_name("max-len").long("max-len").value_name("N").default_value("8").help("The maximum number of characters in a program")))
+        .subcommand(SubCommand::with_name("replay").about("Checks a saved input and saves a debug file")
+            .arg(Arg::with_name("input").required(true)))
+        .subcommand(SubCommand::with_name("triage").about("Checks the crashes in a directory and groups them into buckets")
+            .arg(Arg::with_name("dir").required(true)))
+        .subcommand(SubCommand::with_name("coverage").about("Reports the rules and alternatives that the drivers in a directory never generate")
+            .arg(Arg::with_name("dir").required(true))
+            .arg(Arg::with_name("counts").long("counts").help("Prints the hit counts of all rules and alternatives")))
+        .subcommand(SubCommand::with_name("dict").about("Prints an AFL and libFuzzer dictionary of the literals of the grammar"))
+        .subcommand(SubCommand::with_name("parse-noir").about("Parses stdin with noirc"))
+        .subcommand(SubCommand::with_name("parse-test-data").about("Reports Noir sources that noirc accepts and pest rejects")
+            .arg(Arg::with_name("dir").required(true)))
+        .get_matches();
+
+    match matches.subcommand() {
+        ("fuzz", Some(sub_matches)) => fuzz(&config(sub_matches), sub_matches.is_present("save")),
+        ("gen", Some(sub_matches)) => {
+            let data = match sub_matches.value_of("seed") {
+                Some(seed) => seeded_driver(seed.parse().expect("expected a number as the seed")),
+                None => read_stdin(),
+            };
+            generate(&config(sub_matches), &data[..]);
+        }
+        ("corpus", Some(sub_matches)) => {
+            let jobs = match sub_matches.value_of("jobs") {
+                Some(jobs) => jobs.parse().expect("expected a number of jobs"),
+                None => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+            };
+            let dir = Path::new(sub_matches.value_of("dir").unwrap());
+            let seed = sub_matches.value_of("seed").unwrap().parse().expect("expected a number as the seed");
+            let count = sub_matches.value_of("count").unwrap().parse().expect("expected a number of programs");
+            if sub_matches.is_present("guided") {
+                guided_corpus(&config(sub_matches), dir, seed, count);
             } else {
-                panic!("expected `--all dir`");
+                corpus(&config(sub_matches), dir, seed, count, jobs);
             }
-        } else if first_arg == "--gen-and-save" {
-            let mut buf = vec![];
-            let stdin = io::stdin();
-            let mut locked = stdin.lock();
-            locked.read_to_end(&mut buf).unwrap();
-            parse(&buf[..], true, true);
-        } else {
-            return read_and_parse(&first_arg[..], false);
         }
-    } else {
-        fuzz(false);
+        ("enumerate", Some(sub_matches)) => enumerate(
+            &config(sub_matches),
+            Path::new(sub_matches.value_of("dir").unwrap()),
+            sub_matches.value_of("max-len").unwrap().parse().expect("expected a number of characters"),
+        ),
+        ("replay", Some(sub_matches)) => replay(&config(sub_matches), Path::new(sub_matches.value_of("input").unwrap())),
+        ("triage", Some(sub_matches)) => triage(&config(sub_matches), Path::new(sub_matches.value_of("dir").unwrap())),
+        ("coverage", Some(sub_matches)) => coverage(
+            &config(sub_matches),
+            Path::new(sub_matches.value_of("dir").unwrap()),
+            sub_matches.is_present("counts"),
+        ),
+        ("dict", Some(_)) => print!("{}", dictionary(NoirParser::literals())),
+        ("parse-noir", Some(_)) => parse_noir(),
+        ("parse-test-data", Some(sub_matches)) => parse_test_data(sub_matches.value_of("dir").unwrap()),
+        _ => fuzz(&config(&matches), false),
     }
-    Ok(())
 }
 
-fn fuzz(save: bool) {
+fn read_stdin() -> Vec<u8> {
+    let mut buf = vec![];
+    let stdin = io::stdin();
+    let mut locked = stdin.lock();
+    locked.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn fuzz(config: &Config, save: bool) {
     fuzz!(|data: &[u8]| {
-        parse(data, save, true);
+        parse(config, data, save, true);
     });
 }
 
-fn parse(data: &[u8], save: bool, do_panic: bool) {
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    let data_hash = hasher.finish();
-    let filename = format!("debug/{:x}", data_hash);
-    let mut debug = String::new();
-    let mut error = String::new();
-    let program_code = NoirParser::generate("program", data, Some(100_000));
-    //
-    if let Ok(code) = program_code {
-        writeln!(debug, "{}", code).unwrap();
-        let parsed = NoirParser::parse(Rule::program, &code[..]);
-        if let Ok(mut foo) = parsed {
-            if let Some(bar) = foo.next() {
-                writeln!(debug, "{:?}", bar).unwrap();
-                let noirc_result = parse_program(&code[..]);
-                if noirc_result.1 == vec![] {
-                    writeln!(debug, "{:?}", noirc_result.0).unwrap();
-                } else {
-                    error = format!("noir parser failed with errors {:?}", noirc_result.1);
-                }
-                // assert_eq!(noirc_result.1, vec![]);
-            } else {
-                error = "second unwrap failed".to_string();
-            }
-        } else {
-            error = "first unwrap failed".to_string();
+fn parse(config: &Config, data: &[u8], save: bool, do_panic: bool) {
+    let report = check(config, data);
+    if save {
+        save_debug(config, data, &report.debug);
+    }
+    if let Some(generation_error) = &report.skipped {
+        eprintln!("SKIP: {}", generation_error);
+    }
+    if let Some(error) = &report.error {
+        if do_panic {
+            panic!("ERR: {}", error);
         }
-    } else {
-        error = "generation exceeded the limit".to_string();
     }
-    if !error.is_empty() {
-        writeln!(debug, "ERR: {}", error).unwrap();
+}
+
+/// Prints the program generated from `data` and saves a debug file, exiting with
+/// an error status if it is a finding.
+fn generate(config: &Config, data: &[u8]) {
+    let report = check(config, data);
+    save_debug(config, data, &report.debug);
+    if let Some(program) = &report.program {
+        println!("{}", program);
     }
-    if save {
-        fs::write(filename, debug).unwrap();
+    if let Some(generation_error) = &report.skipped {
+        eprintln!("SKIP: {}", generation_error);
     }
-    if !error.is_empty() && do_panic {
-        if error == "generation exceeded the limit" {
-            eprintln!("ERR: {}", error);
-        } else {
-            panic!("ERR: {}", error);
+    if let Some(error) = &report.error {
+        eprintln!("ERR: {}", error);
+        process::exit(1);
+    }
+}
+
+/// Generates `count` programs from consecutive seeds starting at `seed`, with `jobs` threads.
+/// For each seed `dir` gets the driver, named like an AFL input so that `replay`
+/// and `triage` take it, and the generated program. The same seed always leads
+/// to the same corpus.
+fn corpus(config: &Config, dir: &Path, seed: u64, count: u64, jobs: usize) {
+    fs::create_dir_all(dir).unwrap();
+    let outcomes: Vec<Vec<(Outcome, u64)>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs as u64)
+            .map(|job| {
+                scope.spawn(move || {
+                    let mut outcomes = vec![];
+                    for index in (job..count).step_by(jobs) {
+                        let driver = seeded_driver(seed.wrapping_add(index));
+                        let report = check(config, &driver[..]);
+                        fs::write(dir.join(format!("id:{:06}", index)), &driver).unwrap();
+                        if let Some(program) = &report.program {
+                            fs::write(dir.join(format!("{:06}.nr", index)), program).unwrap();
+                        }
+                        outcomes.push((report.outcome, report.rejections));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+    let mut stats = Stats::default();
+    for (outcome, rejections) in outcomes.into_iter().flatten() {
+        stats.add(outcome);
+        stats.rejections += rejections;
+    }
+    print!("{}", stats);
+}
+
+/// Generates `count` programs from consecutive seeds starting at `seed`, each one
+/// favoring the choices that the programs before it never made. A driver only
+/// reproduces its program after the same programs before it, so `dir` gets the
+/// programs alone, and findings get a debug file right away.
+fn guided_corpus(config: &Config, dir: &Path, seed: u64, count: u64) {
+    fs::create_dir_all(dir).unwrap();
+    let mut guide = Guide::new();
+    let mut coverage = Coverage::default();
+    let mut stats = Stats::default();
+    for index in 0..count {
+        let driver = seeded_driver(seed.wrapping_add(index));
+        let generated = NoirParser::generate_guided(&config.start_rule, &driver[..], Some(config.limit), &mut guide);
+        if let Ok(derivation) = &generated {
+            coverage.add(derivation);
+        }
+        let report = check_generated(config, generated);
+        if let Some(program) = &report.program {
+            fs::write(dir.join(format!("{:06}.nr", index)), program).unwrap();
+        }
+        if report.error.is_some() {
+            save_debug(config, &driver[..], &report.debug);
         }
+        stats.add(report.outcome);
+        stats.rejections += report.rejections;
     }
+    print!("{}", stats);
+    print!("{}", coverage);
 }
 
-fn read_and_parse(path: &str, all: bool) -> Result<(), Error<Rule>> {
-    // traverse all files in the dir
-    if all {
-        let paths = fs::read_dir(path).unwrap();
-    
-        for maybe_file_path in paths {
-            let file_path = maybe_file_path.unwrap().path();
-            if file_path.file_name().unwrap().to_str().unwrap().starts_with("id") {
-                let contents = fs::read(file_path)
-                    .expect("Should have been able to read the file");
-                parse(&contents[..], true, false);
+/// Checks every program derived from the start rule with at most `max_len` characters
+/// against noirc. The programs are written to `dir` in order, and findings get a debug file.
+/// The same length always leads to the same programs, so this is a regression suite.
+fn enumerate(config: &Config, dir: &Path, max_len: usize) {
+    fs::create_dir_all(dir).unwrap();
+    let programs = NoirParser::generate_all(&config.start_rule, max_len).expect("expected a rule of the grammar");
+    let mut stats = Stats::default();
+    for (index, program) in programs.iter().enumerate() {
+        let report = check_enumerated(config, program);
+        if let Some(wrapped) = &report.program {
+            fs::write(dir.join(format!("{:06}.nr", index)), wrapped).unwrap();
+        }
+        if let Some(error) = &report.error {
+            eprintln!("ERR: {:?}: {}", program, error);
+            save_debug(config, program.as_bytes(), &report.debug);
+        }
+        stats.add(report.outcome);
+    }
+    print!("{}", stats);
+}
+
+fn replay(config: &Config, path: &Path) {
+    let contents = fs::read(path)
+        .expect("Should have been able to read the file");
+    let mut report = check(config, &contents[..]);
+    add_reduced(&mut report);
+    save_debug(config, &contents[..], &report.debug);
+}
+
+/// Checks the AFL crashes in `dir`, prints them grouped into buckets and saves
+/// a debug file for the first finding of each bucket.
+fn triage(config: &Config, dir: &Path) {
+    let paths = fs::read_dir(dir).unwrap();
+    let mut buckets = Buckets::default();
+
+    for maybe_file_path in paths {
+        let file_path = maybe_file_path.unwrap().path();
+        if file_path.file_name().unwrap().to_str().unwrap().starts_with("id") {
+            let contents = fs::read(&file_path)
+                .expect("Should have been able to read the file");
+            let mut report = check(config, &contents[..]);
+            if let (Some(error), Some(signature)) = (report.error.clone(), report.signature.clone()) {
+                // Only the first finding of each bucket is worth a debug file.
+                if buckets.add(signature, file_path, error) {
+                    add_reduced(&mut report);
+                    save_debug(config, &contents[..], &report.debug);
+                }
             }
         }
-        Ok(())
-    } else {
-        let contents = fs::read(path)
-            .expect("Should have been able to read the file");
-        parse(&contents[..], true, false);
-        Ok(())
     }
+    print!("{}", buckets);
+}
+
+/// Prints the grammar coverage of the drivers in `dir`, e.g. an AFL queue or a corpus.
+fn coverage(config: &Config, dir: &Path, counts: bool) {
+    let mut coverage = Coverage::default();
+    for maybe_file_path in fs::read_dir(dir).unwrap() {
+        let file_path = maybe_file_path.unwrap().path();
+        if file_path.file_name().unwrap().to_str().unwrap().starts_with("id") {
+            let contents = fs::read(&file_path)
+                .expect("Should have been able to read the file");
+            if let Ok(derivation) = NoirParser::generate(&config.start_rule, &contents[..], Some(config.limit)) {
+                coverage.add(&derivation);
+            }
+        }
+    }
+    if counts {
+        print!("{}", coverage.counts());
+    }
+    print!("{}", coverage);
+}
+
+/// Reports the `.nr` files under `dir` that noirc accepts and pest rejects.
+fn parse_test_data(dir: &str) {
+    let compliance = check_corpus(Path::new(dir)).expect("Should have been able to read the corpus");
+    for rejection in &compliance.rejections {
+        let expected: Vec<String> = rejection.expected.iter().map(|rule| format!("{:?}", rule)).collect();
+        println!(
+            "{}:{}:{}: expected {}",
+            rejection.path.display(),
+            rejection.line,
+            rejection.col,
+            expected.join(", ")
+        );
+    }
+    println!(
+        "{} files, {} rejected by noirc, {} accepted by noirc and rejected by pest",
+        compliance.files,
+        compliance.rejected_by_noirc,
+        compliance.rejections.len()
+    );
 }
 
 fn parse_noir() {
-    let mut buf = vec![];
-    let stdin = io::stdin();
-    let mut locked = stdin.lock();
-    locked.read_to_end(&mut buf).unwrap();
+    let buf = read_stdin();
     let mut error = String::new();
     let code = ::std::str::from_utf8(&buf[..]).unwrap();
     let noirc_result = parse_program(&code[..]);
@@ -0,0 +1,106 @@
+//! Reduction of findings to small programs with the same property, e.g. that
+//! pest accepts them and noirc rejects them.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use pest::Parser;
+use pest_based_noir_parser::{NoirParser, Rule};
+
+/// A replacement of a byte span of the program.
+struct Edit<'a> {
+    span: Range<usize>,
+    replacement: &'a str,
+}
+
+impl<'a> Edit<'a> {
+    fn saved(&self) -> usize {
+        self.span.len() - self.replacement.len()
+    }
+
+    fn apply(&self, code: &str) -> String {
+        [&code[..self.span.start], self.replacement, &code[self.span.end..]].concat()
+    }
+}
+
+/// Returns whether pest parses all of `code`.
+pub fn pest_accepts(code: &str) -> bool {
+    NoirParser::parse(Rule::start, code).is_ok()
+}
+
+/// Shrinks `code` as long as `is_interesting` holds for the result.
+///
+/// The edits follow pest's derivation of the program: each rule's text is
+/// dropped, which removes optional and repeated parts, or swapped for the
+/// shortest text of the same rule elsewhere in the program, which picks shorter
+/// alternatives and hoists nested ones. Runs of whitespace shrink to one space.
+/// Edits that save the most are tried first, until none of them is interesting.
+pub fn reduce<F: Fn(&str) -> bool>(code: &str, is_interesting: F) -> String {
+    let mut code = code.to_string();
+    'reduce: loop {
+        for edit in edits(&code) {
+            let candidate = edit.apply(&code);
+            if is_interesting(&candidate) {
+                code = candidate;
+                continue 'reduce;
+            }
+        }
+        return code;
+    }
+}
+
+fn edits(code: &str) -> Vec<Edit<'_>> {
+    let mut spans = vec![];
+    if let Ok(pairs) = NoirParser::parse(Rule::start, code) {
+        for pair in pairs.flatten() {
+            let span = pair.as_span();
+            if span.start() < span.end() {
+                spans.push((pair.as_rule(), span.start()..span.end()));
+            }
+        }
+    }
+    let mut shortest: HashMap<Rule, &str> = HashMap::new();
+    for (rule, span) in &spans {
+        let text = &code[span.clone()];
+        let entry = shortest.entry(*rule).or_insert(text);
+        if text.len() < entry.len() {
+            *entry = text;
+        }
+    }
+
+    let mut edits = vec![];
+    for (rule, span) in spans {
+        if shortest[&rule].len() < span.len() {
+            edits.push(Edit { span: span.clone(), replacement: shortest[&rule] });
+        }
+        edits.push(Edit { span, replacement: "" });
+    }
+    let mut chars = code.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        if ch.is_whitespace() {
+            let mut end = start + ch.len_utf8();
+            while let Some((idx, ch)) = chars.next_if(|&(_, ch)| ch.is_whitespace()) {
+                end = idx + ch.len_utf8();
+            }
+            if code[start..end] != *" " {
+                edits.push(Edit { span: start..end, replacement: " " });
+            }
+        }
+    }
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.saved()));
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_property() {
+        let code = "fn a ( ) { }\tstruct\t Foo  { bar : u8 , baz : Field }  fn b ( ) { }";
+        let reduced = reduce(code, |code| pest_accepts(code) && code.contains("Field"));
+        assert!(pest_accepts(&reduced), "{:?}", reduced);
+        assert!(reduced.contains("Field"), "{:?}", reduced);
+        assert!(reduced.len() < 30, "{:?}", reduced);
+    }
+}
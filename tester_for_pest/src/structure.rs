@@ -0,0 +1,749 @@
+//! Structural comparison of how pest and noirc parse a program that both accept.
+//!
+//! Both parse trees are normalized into S-expressions: pest's through the typed
+//! AST of `pest_based_noir_parser::noir_ast`, and noirc's from its `ParsedModule`.
+//! The normal form drops spans, parentheses and attributes, groups the items of a
+//! module by kind as noirc does, and desugars what noirc desugars while parsing,
+//! e.g. `a += b` into `a = a + b`. Any remaining difference, such as a wrong
+//! operator precedence or a generic `<` read as a comparison, is a finding.
+
+use std::fmt;
+
+use noirc_frontend::parser::ParsedModule;
+use noirc_frontend::{
+    ArrayLiteral, BinaryTypeOperator, BlockExpression, Expression, ExpressionKind, FunctionReturnType, LValue,
+    LetStatement, Literal, NoirFunction, Pattern, StatementKind, UnaryOp, UnresolvedType, UnresolvedTypeData,
+    UnresolvedTypeExpression, UseTree, UseTreeKind,
+};
+use pest_based_noir_parser::noir_ast;
+
+/// A normalized parse tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn atom(text: impl ToString) -> Sexp {
+        Sexp::Atom(text.to_string())
+    }
+
+    /// A list that starts with the atom `head`.
+    fn list(head: &str, items: impl IntoIterator<Item = Sexp>) -> Sexp {
+        Sexp::List(std::iter::once(Sexp::atom(head)).chain(items).collect())
+    }
+
+    /// Returns what kind of node this is: the head of a list, or `atom`.
+    pub fn head(&self) -> &str {
+        match self {
+            Sexp::List(items) => match items.first() {
+                Some(Sexp::Atom(head)) => head,
+                _ => "list",
+            },
+            Sexp::Atom(_) => "atom",
+        }
+    }
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sexp::Atom(text) => write!(f, "{}", text),
+            Sexp::List(items) => {
+                write!(f, "(")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// The first place where the normalized parse trees differ.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The heads of the enclosing lists, outermost first, with the index of the child taken.
+    pub path: Vec<(String, usize)>,
+    pub pest: Sexp,
+    pub noirc: Sexp,
+}
+
+impl Mismatch {
+    /// Returns what mismatches of the same bug have in common: the enclosing node and
+    /// the kinds of the differing ones, e.g. `+` where noirc has `*`.
+    pub fn kind(&self) -> String {
+        let parent = self.path.last().map_or("", |(head, _)| head.as_str());
+        format!("parse trees differ in {}: pest has {} where noirc has {}", parent, self.pest.head(), self.noirc.head())
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path: Vec<String> = self.path.iter().map(|(head, index)| format!("{}[{}]", head, index)).collect();
+        write!(f, "at {}: pest has {}, noirc has {}", path.join(" "), self.pest, self.noirc)
+    }
+}
+
+/// Returns the first place in pre-order where `pest` and `noirc` differ. Lists of the
+/// same head and length are descended into, anything else is reported as a whole.
+pub fn first_mismatch(pest: &Sexp, noirc: &Sexp) -> Option<Mismatch> {
+    let mut path = vec![];
+    let (mut pest, mut noirc) = (pest, noirc);
+    loop {
+        match (pest, noirc) {
+            (Sexp::List(pest_items), Sexp::List(noirc_items))
+                if pest_items.len() == noirc_items.len() && pest.head() == noirc.head() =>
+            {
+                let index = pest_items.iter().zip(noirc_items).position(|(a, b)| a != b)?;
+                path.push((pest.head().to_string(), index));
+                pest = &pest_items[index];
+                noirc = &noirc_items[index];
+            }
+            _ if pest == noirc => return None,
+            _ => return Some(Mismatch { path, pest: pest.clone(), noirc: noirc.clone() }),
+        }
+    }
+}
+
+/// Returns the first difference between how pest and noirc parse `code`, given what
+/// noirc parsed from it without errors. Nothing is compared if pest rejects `code`.
+pub fn compare(code: &str, parsed: &ParsedModule) -> Option<Mismatch> {
+    let module = noir_ast::parse(code).ok()?;
+    first_mismatch(&from_pest(&module), &from_noirc(parsed))
+}
+
+/// Normalizes a module parsed by pest.
+pub fn from_pest(module: &noir_ast::Module) -> Sexp {
+    use noir_ast::Item;
+
+    let mut imports = vec![];
+    let mut globals = vec![];
+    let mut types = vec![];
+    let mut functions = vec![];
+    let mut impls = vec![];
+    let mut module_decls = vec![];
+    let mut submodules = vec![];
+    for item in &module.items {
+        match item {
+            Item::Use(use_statement) => imports.push(pest_use(use_statement)),
+            Item::Global(global) => globals.push(Sexp::list(
+                "let",
+                [
+                    Sexp::atom(&global.name),
+                    global.typ.as_ref().map_or(Sexp::atom("_"), pest_type),
+                    pest_literal(&global.value),
+                ],
+            )),
+            Item::Struct(noir_struct) => types.push(Sexp::list(
+                "struct",
+                [
+                    Sexp::atom(&noir_struct.name),
+                    generics(&noir_struct.generics),
+                    Sexp::list("fields", noir_struct.fields.iter().map(|(name, typ)| field(name, pest_type(typ)))),
+                ],
+            )),
+            Item::Function(function) => functions.push(pest_function(function)),
+            Item::Impl(noir_impl) => impls.push(Sexp::list(
+                "impl",
+                [generics(&noir_impl.generics), pest_type(&noir_impl.typ)]
+                    .into_iter()
+                    .chain(noir_impl.methods.iter().map(pest_function)),
+            )),
+            Item::ModuleDeclaration(name) => module_decls.push(Sexp::atom(name)),
+            Item::Submodule(submodule) => {
+                submodules.push(Sexp::list("mod", [Sexp::atom(&submodule.name), from_pest(&submodule.contents)]))
+            }
+        }
+    }
+    module_sexp(imports, globals, types, functions, impls, module_decls, submodules, [vec![], vec![], vec![]])
+}
+
+/// Normalizes a module parsed by noirc.
+pub fn from_noirc(module: &ParsedModule) -> Sexp {
+    let imports = module.imports.iter().map(noirc_use).collect();
+    let globals = module.globals.iter().map(noirc_let).collect();
+    let types = module
+        .types
+        .iter()
+        .map(|noir_struct| {
+            Sexp::list(
+                "struct",
+                [
+                    Sexp::atom(&noir_struct.name),
+                    generics(&noir_struct.generics),
+                    Sexp::list("fields", noir_struct.fields.iter().map(|(name, typ)| field(name, noirc_type(typ)))),
+                ],
+            )
+        })
+        .collect();
+    let functions = module.functions.iter().map(noirc_function).collect();
+    let impls = module
+        .impls
+        .iter()
+        .map(|type_impl| {
+            Sexp::list(
+                "impl",
+                [generics(&type_impl.generics), noirc_type(&type_impl.object_type)]
+                    .into_iter()
+                    .chain(type_impl.methods.iter().map(noirc_function)),
+            )
+        })
+        .collect();
+    let module_decls = module.module_decls.iter().map(Sexp::atom).collect();
+    let submodules = module
+        .submodules
+        .iter()
+        .map(|submodule| {
+            let head = if submodule.is_contract { "contract" } else { "mod" };
+            Sexp::list(head, [Sexp::atom(&submodule.name), from_noirc(&submodule.contents)])
+        })
+        .collect();
+    // The grammar has no traits or type aliases, so they are only counted.
+    let unsupported = [
+        module.traits.iter().map(|_| Sexp::atom("trait")).collect(),
+        module.trait_impls.iter().map(|_| Sexp::atom("trait_impl")).collect(),
+        module.type_aliases.iter().map(|_| Sexp::atom("type_alias")).collect(),
+    ];
+    module_sexp(imports, globals, types, functions, impls, module_decls, submodules, unsupported)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn module_sexp(
+    imports: Vec<Sexp>,
+    globals: Vec<Sexp>,
+    types: Vec<Sexp>,
+    functions: Vec<Sexp>,
+    impls: Vec<Sexp>,
+    module_decls: Vec<Sexp>,
+    submodules: Vec<Sexp>,
+    [traits, trait_impls, type_aliases]: [Vec<Sexp>; 3],
+) -> Sexp {
+    Sexp::list(
+        "module",
+        [
+            Sexp::list("imports", imports),
+            Sexp::list("globals", globals),
+            Sexp::list("types", types),
+            Sexp::list("functions", functions),
+            Sexp::list("impls", impls),
+            Sexp::list("module_decls", module_decls),
+            Sexp::list("submodules", submodules),
+            Sexp::list("traits", traits),
+            Sexp::list("trait_impls", trait_impls),
+            Sexp::list("type_aliases", type_aliases),
+        ],
+    )
+}
+
+fn generics<T: fmt::Display>(generics: &[T]) -> Sexp {
+    Sexp::list("generics", generics.iter().map(Sexp::atom))
+}
+
+fn field(name: impl ToString, value: Sexp) -> Sexp {
+    Sexp::List(vec![Sexp::atom(name), value])
+}
+
+/// Formats a visibility, a path kind or signedness from its `Debug` output, which
+/// is all that the two ASTs agree on.
+fn lowercase(value: impl fmt::Debug) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+/// `returns` and the visibility, followed by the type unless it is left out.
+fn returns(visibility: String, typ: Option<Sexp>) -> Sexp {
+    Sexp::list("returns", std::iter::once(Sexp::Atom(visibility)).chain(typ))
+}
+
+fn modifiers(is_unconstrained: bool, is_open: bool) -> Sexp {
+    let flags = [(is_unconstrained, "unconstrained"), (is_open, "open")];
+    Sexp::list("modifiers", flags.into_iter().filter(|(set, _)| *set).map(|(_, flag)| Sexp::atom(flag)))
+}
+
+fn pest_use(use_statement: &noir_ast::UseStatement) -> Sexp {
+    let path = pest_path(&use_statement.path);
+    let alias = use_statement.alias.iter().map(|alias| Sexp::list("as", [Sexp::atom(alias)]));
+    Sexp::list("use", std::iter::once(path).chain(alias))
+}
+
+fn pest_path(path: &noir_ast::Path) -> Sexp {
+    path_atom(&lowercase(path.kind), path.segments.iter().map(String::as_str))
+}
+
+/// A path as a single atom, e.g. `dep::std::hash`.
+fn path_atom<'a>(kind: &str, segments: impl IntoIterator<Item = &'a str>) -> Sexp {
+    let mut text = match kind {
+        "plain" => String::new(),
+        kind => format!("{}::", kind),
+    };
+    text.push_str(&segments.into_iter().collect::<Vec<_>>().join("::"));
+    Sexp::Atom(text)
+}
+
+fn pest_function(function: &noir_ast::Function) -> Sexp {
+    let parameters = function.parameters.iter().map(|parameter| match parameter {
+        noir_ast::Parameter::SelfValue => parameter_sexp("private".to_string(), Sexp::atom("self"), self_type()),
+        noir_ast::Parameter::Typed { pattern, visibility, typ } => {
+            parameter_sexp(lowercase(visibility), pest_pattern(pattern), pest_type(typ))
+        }
+    });
+    Sexp::list(
+        "fn",
+        [
+            Sexp::atom(&function.name),
+            modifiers(function.is_unconstrained, function.is_open),
+            generics(&function.generics),
+            Sexp::list("params", parameters),
+            returns(lowercase(function.return_visibility), function.return_type.as_ref().map(pest_type)),
+            pest_block(&function.body),
+        ],
+    )
+}
+
+fn parameter_sexp(visibility: String, pattern: Sexp, typ: Sexp) -> Sexp {
+    Sexp::list("param", [Sexp::Atom(visibility), pattern, typ])
+}
+
+/// The type that noirc gives `self` parameters.
+fn self_type() -> Sexp {
+    Sexp::list("named", [Sexp::atom("Self")])
+}
+
+fn pest_type(typ: &noir_ast::Type) -> Sexp {
+    use noir_ast::Type;
+
+    match typ {
+        Type::Field => Sexp::atom("Field"),
+        Type::Bool => Sexp::atom("bool"),
+        Type::Integer(bits) => Sexp::Atom(format!("u{}", bits)),
+        Type::String(None) => Sexp::atom("str"),
+        Type::String(Some(length)) => Sexp::list("str", [pest_type_expression(length)]),
+        Type::Named(path, arguments) => {
+            Sexp::list("named", std::iter::once(pest_path(path)).chain(arguments.iter().map(pest_type)))
+        }
+        Type::Vec(arguments) => Sexp::list("named", std::iter::once(Sexp::atom("Vec")).chain(arguments.iter().map(pest_type))),
+        Type::Array(None, element) => Sexp::list("slice", [pest_type(element)]),
+        Type::Array(Some(length), element) => Sexp::list("array", [pest_type(element), pest_type_expression(length)]),
+        Type::Tuple(elements) if elements.is_empty() => Sexp::atom("unit"),
+        Type::Tuple(elements) => Sexp::list("tuple", elements.iter().map(pest_type)),
+        Type::Function(parameters, result) => {
+            Sexp::list("fn", [Sexp::list("args", parameters.iter().map(pest_type)), pest_type(result)])
+        }
+        Type::Expression(expression) => pest_type_expression(expression),
+    }
+}
+
+fn pest_type_expression(expression: &noir_ast::TypeExpression) -> Sexp {
+    use noir_ast::TypeExpression;
+
+    match expression {
+        TypeExpression::Variable(path) => pest_path(path),
+        TypeExpression::Constant(value) => Sexp::atom(value),
+        // noirc reads `-n` as `0 - n`.
+        TypeExpression::Negation(operand) => Sexp::list("-", [Sexp::atom(0), pest_type_expression(operand)]),
+        TypeExpression::Binary(lhs, operator, rhs) => {
+            Sexp::list(operator.symbol(), [pest_type_expression(lhs), pest_type_expression(rhs)])
+        }
+    }
+}
+
+fn pest_pattern(pattern: &noir_ast::Pattern) -> Sexp {
+    use noir_ast::Pattern;
+
+    match pattern {
+        Pattern::Identifier(name) => Sexp::atom(name),
+        Pattern::Mutable(pattern) => Sexp::list("mut", [pest_pattern(pattern)]),
+        Pattern::Tuple(patterns) => Sexp::list("tuple", patterns.iter().map(pest_pattern)),
+        Pattern::Struct(path, fields) => Sexp::list(
+            "struct",
+            std::iter::once(pest_path(path)).chain(fields.iter().map(|(name, pattern)| field(name, pest_pattern(pattern)))),
+        ),
+    }
+}
+
+fn pest_block(block: &noir_ast::Block) -> Sexp {
+    Sexp::list("block", block.statements.iter().map(pest_statement))
+}
+
+fn pest_statement(statement: &noir_ast::Statement) -> Sexp {
+    use noir_ast::Statement;
+
+    match statement {
+        Statement::Let { pattern, typ, value } => Sexp::list(
+            "let",
+            [pest_pattern(pattern), typ.as_ref().map_or(Sexp::atom("_"), pest_type), pest_expression(value)],
+        ),
+        Statement::Assign { lvalue, operator, value } => {
+            let lvalue = pest_lvalue(lvalue);
+            // noirc desugars `a op= b` into `a = a op b`.
+            let value = match operator {
+                Some(operator) => Sexp::list(operator.symbol(), [lvalue.clone(), pest_expression(value)]),
+                None => pest_expression(value),
+            };
+            Sexp::list("assign", [lvalue, value])
+        }
+        Statement::Expression(expression) => Sexp::list("expr", [pest_expression(expression)]),
+        Statement::Semi(expression) => Sexp::list("semi", [pest_expression(expression)]),
+    }
+}
+
+/// Normalizes an lvalue like the expression that reads it, which is what noirc's
+/// desugaring of compound assignments refers to.
+fn pest_lvalue(lvalue: &noir_ast::LValue) -> Sexp {
+    use noir_ast::LValue;
+
+    match lvalue {
+        LValue::Ident(name) => Sexp::atom(name),
+        LValue::MemberAccess(object, field_name) => Sexp::list(".", [pest_lvalue(object), Sexp::atom(field_name)]),
+        LValue::Index(array, index) => Sexp::list("index", [pest_lvalue(array), pest_expression(index)]),
+    }
+}
+
+fn pest_literal(literal: &noir_ast::Literal) -> Sexp {
+    use noir_ast::Literal;
+
+    match literal {
+        Literal::Integer(value) => Sexp::atom(value),
+        Literal::Bool(value) => Sexp::atom(value),
+        Literal::Str(value) => Sexp::Atom(format!("{:?}", value)),
+    }
+}
+
+fn pest_expression(expression: &noir_ast::Expression) -> Sexp {
+    use noir_ast::{Expression, UnaryOp};
+
+    match expression {
+        Expression::Literal(literal) => pest_literal(literal),
+        Expression::Variable(path) => pest_path(path),
+        Expression::Prefix(operator, operand) => {
+            let symbol = match operator {
+                UnaryOp::Minus => "-",
+                UnaryOp::Not => "!",
+            };
+            Sexp::list(symbol, [pest_expression(operand)])
+        }
+        Expression::Infix(lhs, operator, rhs) => {
+            Sexp::list(operator.symbol(), [pest_expression(lhs), pest_expression(rhs)])
+        }
+        Expression::Call(function, arguments) => {
+            Sexp::list("call", std::iter::once(pest_expression(function)).chain(arguments.iter().map(pest_expression)))
+        }
+        Expression::MethodCall(object, name, arguments) => Sexp::list(
+            "method",
+            [pest_expression(object), Sexp::atom(name)].into_iter().chain(arguments.iter().map(pest_expression)),
+        ),
+        Expression::Index(collection, index) => Sexp::list("index", [pest_expression(collection), pest_expression(index)]),
+        Expression::Cast(operand, typ) => Sexp::list("as", [pest_expression(operand), pest_type(typ)]),
+        Expression::MemberAccess(object, field_name) => Sexp::list(".", [pest_expression(object), Sexp::atom(field_name)]),
+        Expression::Constructor(path, fields) => Sexp::list(
+            "constructor",
+            std::iter::once(pest_path(path))
+                .chain(fields.iter().map(|(name, value)| field(name, pest_expression(value)))),
+        ),
+        Expression::If { condition, consequence, alternative } => Sexp::list(
+            "if",
+            [pest_expression(condition), pest_block(consequence)]
+                .into_iter()
+                .chain(alternative.iter().map(|alternative| pest_expression(alternative))),
+        ),
+        Expression::Lambda { parameters, return_type, body } => Sexp::list(
+            "lambda",
+            [
+                Sexp::list("params", parameters.iter().map(|pattern| Sexp::list("param", [pest_pattern(pattern), Sexp::atom("_")]))),
+                return_type.as_ref().map_or(Sexp::atom("_"), pest_type),
+                pest_expression(body),
+            ],
+        ),
+        Expression::Block(block) => pest_block(block),
+        Expression::Parenthesized(expression) => pest_expression(expression),
+        Expression::Tuple(elements) if elements.is_empty() => Sexp::atom("unit"),
+        Expression::Tuple(elements) => Sexp::list("tuple", elements.iter().map(pest_expression)),
+        Expression::Array(elements) => Sexp::list("array", elements.iter().map(pest_expression)),
+        Expression::RepeatedArray(element, length) => {
+            Sexp::list("repeat", [pest_expression(element), pest_expression(length)])
+        }
+    }
+}
+
+fn noirc_use(use_tree: &UseTree) -> Sexp {
+    let mut segments: Vec<String> = use_tree.prefix.segments.iter().map(ToString::to_string).collect();
+    let kind = lowercase(use_tree.prefix.kind);
+    match &use_tree.kind {
+        UseTreeKind::Path(name, alias) => {
+            segments.push(name.to_string());
+            let path = path_atom(&kind, segments.iter().map(String::as_str));
+            let alias = alias.iter().map(|alias| Sexp::list("as", [Sexp::atom(alias)]));
+            Sexp::list("use", std::iter::once(path).chain(alias))
+        }
+        // The grammar has no lists of imports, so only their size is kept.
+        UseTreeKind::List(trees) => {
+            let path = path_atom(&kind, segments.iter().map(String::as_str));
+            Sexp::list("use_list", std::iter::once(path).chain(trees.iter().map(|_| Sexp::atom("tree"))))
+        }
+    }
+}
+
+fn noirc_path(path: &noirc_frontend::Path) -> Sexp {
+    let segments: Vec<String> = path.segments.iter().map(ToString::to_string).collect();
+    path_atom(&lowercase(path.kind), segments.iter().map(String::as_str))
+}
+
+fn noirc_function(function: &NoirFunction) -> Sexp {
+    let def = &function.def;
+    let parameters = def
+        .parameters
+        .iter()
+        .map(|(pattern, typ, visibility)| parameter_sexp(lowercase(visibility), noirc_pattern(pattern), noirc_type(typ)));
+    let return_type = match &def.return_type {
+        FunctionReturnType::Default(_) => None,
+        FunctionReturnType::Ty(typ) => Some(noirc_type(typ)),
+    };
+    Sexp::list(
+        "fn",
+        [
+            Sexp::atom(&def.name),
+            modifiers(def.is_unconstrained, def.is_open),
+            generics(&def.generics),
+            Sexp::list("params", parameters),
+            returns(lowercase(def.return_visibility), return_type),
+            noirc_block(&def.body),
+        ],
+    )
+}
+
+fn noirc_type(typ: &UnresolvedType) -> Sexp {
+    match &typ.typ {
+        UnresolvedTypeData::FieldElement => Sexp::atom("Field"),
+        UnresolvedTypeData::Bool => Sexp::atom("bool"),
+        UnresolvedTypeData::Integer(signedness, bits) => {
+            let prefix = if lowercase(signedness) == "signed" { "i" } else { "u" };
+            Sexp::Atom(format!("{}{}", prefix, bits))
+        }
+        UnresolvedTypeData::String(None) => Sexp::atom("str"),
+        UnresolvedTypeData::String(Some(length)) => Sexp::list("str", [noirc_type_expression(length)]),
+        UnresolvedTypeData::Named(path, arguments) => {
+            Sexp::list("named", std::iter::once(noirc_path(path)).chain(arguments.iter().map(noirc_type)))
+        }
+        UnresolvedTypeData::Array(None, element) => Sexp::list("slice", [noirc_type(element)]),
+        UnresolvedTypeData::Array(Some(length), element) => {
+            Sexp::list("array", [noirc_type(element), noirc_type_expression(length)])
+        }
+        UnresolvedTypeData::Unit => Sexp::atom("unit"),
+        UnresolvedTypeData::Tuple(elements) => Sexp::list("tuple", elements.iter().map(noirc_type)),
+        // The environment of closures isn't written in the source.
+        UnresolvedTypeData::Function(parameters, result, ..) => {
+            Sexp::list("fn", [Sexp::list("args", parameters.iter().map(noirc_type)), noirc_type(result)])
+        }
+        UnresolvedTypeData::Expression(expression) => noirc_type_expression(expression),
+        UnresolvedTypeData::Unspecified => Sexp::atom("_"),
+        other => Sexp::Atom(format!("{:?}", other)),
+    }
+}
+
+fn noirc_type_expression(expression: &UnresolvedTypeExpression) -> Sexp {
+    match expression {
+        UnresolvedTypeExpression::Variable(path) => noirc_path(path),
+        UnresolvedTypeExpression::Constant(value, ..) => Sexp::atom(value),
+        UnresolvedTypeExpression::BinaryOperation(lhs, operator, rhs, ..) => {
+            let symbol = match operator {
+                BinaryTypeOperator::Addition => "+",
+                BinaryTypeOperator::Subtraction => "-",
+                BinaryTypeOperator::Multiplication => "*",
+                BinaryTypeOperator::Division => "/",
+                BinaryTypeOperator::Modulo => "%",
+            };
+            Sexp::list(symbol, [noirc_type_expression(lhs), noirc_type_expression(rhs)])
+        }
+    }
+}
+
+fn noirc_pattern(pattern: &Pattern) -> Sexp {
+    match pattern {
+        Pattern::Identifier(name) => Sexp::atom(name),
+        Pattern::Mutable(pattern, ..) => Sexp::list("mut", [noirc_pattern(pattern)]),
+        Pattern::Tuple(patterns, ..) => Sexp::list("tuple", patterns.iter().map(noirc_pattern)),
+        Pattern::Struct(path, fields, ..) => Sexp::list(
+            "struct",
+            std::iter::once(noirc_path(path))
+                .chain(fields.iter().map(|(name, pattern)| field(name, noirc_pattern(pattern)))),
+        ),
+    }
+}
+
+fn noirc_block(block: &BlockExpression) -> Sexp {
+    Sexp::list("block", block.0.iter().map(|statement| noirc_statement(&statement.kind)))
+}
+
+fn noirc_let(let_statement: &LetStatement) -> Sexp {
+    Sexp::list(
+        "let",
+        [
+            noirc_pattern(&let_statement.pattern),
+            noirc_type(&let_statement.r#type),
+            noirc_expression(&let_statement.expression),
+        ],
+    )
+}
+
+fn noirc_statement(statement: &StatementKind) -> Sexp {
+    match statement {
+        StatementKind::Let(let_statement) => noirc_let(let_statement),
+        StatementKind::Assign(assign) => {
+            Sexp::list("assign", [noirc_lvalue(&assign.lvalue), noirc_expression(&assign.expression)])
+        }
+        StatementKind::Expression(expression) => Sexp::list("expr", [noirc_expression(expression)]),
+        StatementKind::Semi(expression) => Sexp::list("semi", [noirc_expression(expression)]),
+        other => Sexp::Atom(format!("{:?}", other)),
+    }
+}
+
+fn noirc_lvalue(lvalue: &LValue) -> Sexp {
+    match lvalue {
+        LValue::Ident(name) => Sexp::atom(name),
+        LValue::MemberAccess { object, field_name, .. } => Sexp::list(".", [noirc_lvalue(object), Sexp::atom(field_name)]),
+        LValue::Index { array, index, .. } => Sexp::list("index", [noirc_lvalue(array), noirc_expression(index)]),
+        other => Sexp::Atom(format!("{:?}", other)),
+    }
+}
+
+fn noirc_expression(expression: &Expression) -> Sexp {
+    match &expression.kind {
+        ExpressionKind::Literal(literal) => match literal {
+            Literal::Integer(value, ..) => Sexp::atom(value.to_u128()),
+            Literal::Bool(value) => Sexp::atom(value),
+            Literal::Str(value) => Sexp::Atom(format!("{:?}", value)),
+            Literal::Unit => Sexp::atom("unit"),
+            Literal::Array(ArrayLiteral::Standard(elements)) => Sexp::list("array", elements.iter().map(noirc_expression)),
+            Literal::Array(ArrayLiteral::Repeated { repeated_element, length }) => {
+                Sexp::list("repeat", [noirc_expression(repeated_element), noirc_expression(length)])
+            }
+            other => Sexp::Atom(format!("{:?}", other)),
+        },
+        ExpressionKind::Variable(path) => noirc_path(path),
+        ExpressionKind::Prefix(prefix) => {
+            let symbol = match &prefix.operator {
+                UnaryOp::Minus => "-".to_string(),
+                UnaryOp::Not => "!".to_string(),
+                other => format!("{:?}", other),
+            };
+            Sexp::list(&symbol, [noirc_expression(&prefix.rhs)])
+        }
+        ExpressionKind::Infix(infix) => {
+            Sexp::list(infix.operator.contents.as_string(), [noirc_expression(&infix.lhs), noirc_expression(&infix.rhs)])
+        }
+        ExpressionKind::Call(call) => {
+            Sexp::list("call", std::iter::once(noirc_expression(&call.func)).chain(call.arguments.iter().map(noirc_expression)))
+        }
+        ExpressionKind::MethodCall(call) => Sexp::list(
+            "method",
+            [noirc_expression(&call.object), Sexp::atom(&call.method_name)]
+                .into_iter()
+                .chain(call.arguments.iter().map(noirc_expression)),
+        ),
+        ExpressionKind::Index(index) => {
+            Sexp::list("index", [noirc_expression(&index.collection), noirc_expression(&index.index)])
+        }
+        ExpressionKind::Cast(cast) => Sexp::list("as", [noirc_expression(&cast.lhs), noirc_type(&cast.r#type)]),
+        ExpressionKind::MemberAccess(access) => Sexp::list(".", [noirc_expression(&access.lhs), Sexp::atom(&access.rhs)]),
+        ExpressionKind::Constructor(constructor) => Sexp::list(
+            "constructor",
+            std::iter::once(noirc_path(&constructor.type_name))
+                .chain(constructor.fields.iter().map(|(name, value)| field(name, noirc_expression(value)))),
+        ),
+        ExpressionKind::If(if_expression) => Sexp::list(
+            "if",
+            [noirc_expression(&if_expression.condition), noirc_expression(&if_expression.consequence)]
+                .into_iter()
+                .chain(if_expression.alternative.iter().map(noirc_expression)),
+        ),
+        ExpressionKind::Lambda(lambda) => Sexp::list(
+            "lambda",
+            [
+                Sexp::list(
+                    "params",
+                    lambda.parameters.iter().map(|(pattern, typ)| Sexp::list("param", [noirc_pattern(pattern), noirc_type(typ)])),
+                ),
+                noirc_type(&lambda.return_type),
+                noirc_expression(&lambda.body),
+            ],
+        ),
+        ExpressionKind::Block(block) => noirc_block(block),
+        ExpressionKind::Parenthesized(expression) => noirc_expression(expression),
+        ExpressionKind::Tuple(elements) => Sexp::list("tuple", elements.iter().map(noirc_expression)),
+        other => Sexp::Atom(format!("{:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pest(code: &str) -> Sexp {
+        from_pest(&noir_ast::parse(code).unwrap())
+    }
+
+    fn body(code: &str) -> String {
+        match pest(code) {
+            Sexp::List(items) => match &items[4] {
+                Sexp::List(functions) => match &functions[1] {
+                    Sexp::List(function) => function[6].to_string(),
+                    function => panic!("expected a function, found {}", function),
+                },
+                functions => panic!("expected functions, found {}", functions),
+            },
+            module => panic!("expected a module, found {}", module),
+        }
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(body("fn main() { a + b * c }"), "(block (expr (+ a (* b c))))");
+        assert_eq!(body("fn main() { (a + b) * c }"), "(block (expr (* (+ a b) c)))");
+        assert_eq!(body("fn main() { a != b | c }"), "(block (expr (!= a (| b c))))");
+    }
+
+    #[test]
+    fn desugared_assignment() {
+        assert_eq!(body("fn main() { x[0] += 10; }"), "(block (assign (index x 0) (+ (index x 0) 10)))");
+        assert_eq!(body("fn main() { a.b = c; }"), "(block (assign (. a b) c))");
+    }
+
+    #[test]
+    fn items_grouped_by_kind() {
+        let module = pest("fn f() {} struct S { a: Field } fn g() {}").to_string();
+        assert!(module.contains("(types (struct S (generics) (fields (a Field))))"), "{}", module);
+        assert!(module.contains("(functions (fn f "), "{}", module);
+        assert!(module.contains(" (fn g "), "{}", module);
+    }
+
+    #[test]
+    fn first_mismatch_is_innermost() {
+        let mismatch = first_mismatch(&pest("fn main() { a + b * c }"), &pest("fn main() { (a + b) * c }")).unwrap();
+        let path: Vec<_> = mismatch.path.iter().map(|(head, index)| (head.as_str(), *index)).collect();
+        assert_eq!(path, [("module", 4), ("functions", 1), ("fn", 6), ("block", 1), ("expr", 1)]);
+        assert_eq!(mismatch.pest.to_string(), "(+ a (* b c))");
+        assert_eq!(mismatch.noirc.to_string(), "(* (+ a b) c)");
+        assert_eq!(mismatch.kind(), "parse trees differ in expr: pest has + where noirc has *");
+        assert!(first_mismatch(&pest("fn main() { (a) }"), &pest("fn main() { a }")).is_none());
+    }
+
+    #[test]
+    fn noirc_agrees() {
+        let programs = [
+            "fn main(x: pub Field, y: [u8; 40]) -> pub Field { let mut z = x * 20 + y[0]; z -= 10; z }",
+            "struct Foo<T> { a: T, b: (bool, str<30>) } impl Foo<Field> { fn get(self) -> Field { self.a } }",
+            "fn main() { let f = |a, b| a & b ^ 10; f(true, (false)); foo::bar(Foo { a: 10 }, [10; 20], ()) }",
+        ];
+        for code in programs {
+            let (parsed, errors) = noirc_frontend::parse_program(code);
+            assert!(errors.is_empty(), "{}: {:?}", code, errors);
+            if let Some(mismatch) = first_mismatch(&pest(code), &from_noirc(&parsed)) {
+                panic!("{}: {}", code, mismatch);
+            }
+        }
+    }
+}
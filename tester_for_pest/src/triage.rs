@@ -0,0 +1,134 @@
+//! Grouping of findings into buckets of likely duplicates.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use pest_based_noir_parser::Rule;
+
+/// How many of the innermost rules around the offending span go into a signature.
+/// The outer rules mostly tell where in the program the bug happened to be generated.
+const RULE_PATH_LEN: usize = 3;
+
+/// What findings of the same bug have in common: the normalized error and the
+/// innermost pest rules that generated the offending span.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Signature {
+    pub kind: String,
+    pub rules: Vec<Rule>,
+}
+
+impl Signature {
+    /// Creates a signature from an error kind and the rules enclosing the offending
+    /// span, outermost first.
+    pub fn new(kind: String, mut rules: Vec<Rule>) -> Self {
+        let outer = rules.len().saturating_sub(RULE_PATH_LEN);
+        rules.drain(..outer);
+        Signature { kind, rules }
+    }
+}
+
+/// Normalizes the `Debug` output of an error, so that errors that differ only in
+/// string literals, numbers and positions compare equal.
+pub fn normalize(debug: &str) -> String {
+    let mut result = String::new();
+    let mut chars = debug.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                let mut escaped = false;
+                for ch in chars.by_ref() {
+                    match ch {
+                        '"' if !escaped => break,
+                        '\\' => escaped = !escaped,
+                        _ => escaped = false,
+                    }
+                }
+                result.push_str("\"_\"");
+            }
+            '0'..='9' => {
+                while chars.next_if(char::is_ascii_digit).is_some() {}
+                result.push('N');
+            }
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+/// The findings with the same signature.
+pub struct Bucket {
+    pub count: usize,
+    /// The first input with this signature.
+    pub representative: PathBuf,
+    pub error: String,
+}
+
+/// Findings grouped by signature.
+#[derive(Default)]
+pub struct Buckets {
+    buckets: BTreeMap<Signature, Bucket>,
+}
+
+impl Buckets {
+    /// Adds a finding, returning whether it is the first one with its signature.
+    pub fn add(&mut self, signature: Signature, path: PathBuf, error: String) -> bool {
+        let mut is_new = false;
+        let bucket = self.buckets.entry(signature).or_insert_with(|| {
+            is_new = true;
+            Bucket { count: 0, representative: path, error }
+        });
+        bucket.count += 1;
+        is_new
+    }
+}
+
+impl fmt::Display for Buckets {
+    /// Formats a summary table, the largest buckets first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buckets: Vec<_> = self.buckets.iter().collect();
+        buckets.sort_by_key(|(_, bucket)| Reverse(bucket.count));
+        let total: usize = buckets.iter().map(|(_, bucket)| bucket.count).sum();
+        let rule_paths: Vec<String> = buckets
+            .iter()
+            .map(|(signature, _)| {
+                let rules: Vec<String> = signature.rules.iter().map(|rule| format!("{:?}", rule)).collect();
+                rules.join(" > ")
+            })
+            .collect();
+        let width = rule_paths.iter().map(String::len).max().unwrap_or(0).max("rules".len());
+        writeln!(f, "{} findings in {} buckets", total, buckets.len())?;
+        writeln!(f, "{:>6}  {:<width$}  representative", "count", "rules", width = width)?;
+        for ((_, bucket), rule_path) in buckets.iter().zip(rule_paths) {
+            writeln!(f, "{:>6}  {:<width$}  {}", bucket.count, rule_path, bucket.representative.display(), width = width)?;
+            writeln!(f, "        {}", bucket.error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_literals_and_numbers() {
+        assert_eq!(
+            normalize(r#"ParserError { found: Ident("x\"y"), span: Span(12..340) }"#),
+            normalize(r#"ParserError { found: Ident("z"), span: Span(3..4) }"#)
+        );
+        assert_ne!(normalize(r#"found: Ident("x")"#), normalize(r#"found: Int(3)"#));
+    }
+
+    #[test]
+    fn buckets() {
+        let signature = |kind: &str| Signature::new(kind.to_owned(), vec![Rule::program, Rule::module, Rule::top_level_statement, Rule::ident]);
+        let mut buckets = Buckets::default();
+        assert!(buckets.add(signature("a"), PathBuf::from("id:1"), "a".to_owned()));
+        assert!(!buckets.add(signature("a"), PathBuf::from("id:2"), "a".to_owned()));
+        assert!(buckets.add(signature("b"), PathBuf::from("id:3"), "b".to_owned()));
+        assert_eq!(signature("a").rules, [Rule::module, Rule::top_level_statement, Rule::ident]);
+        assert!(buckets.to_string().starts_with("3 findings in 2 buckets"));
+    }
+}